@@ -0,0 +1,165 @@
+//! Procedural macros for [wrk-api-bench](https://github.com/crisidev/wrk-api-bench-rs).
+#![warn(missing_docs)]
+
+use std::time::Duration;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{meta::ParseNestedMeta, parse_macro_input, ItemFn, LitInt, LitStr};
+
+/// Arguments of the `#[wrk_benchmark]` attribute.
+#[derive(Default)]
+struct Args {
+    url: Option<LitStr>,
+    duration: Option<Duration>,
+    regression: Option<f64>,
+    threads: Option<u16>,
+    connections: Option<u16>,
+}
+
+impl Args {
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("url") {
+            self.url = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("duration") {
+            let value: LitStr = meta.value()?.parse()?;
+            self.duration = Some(parse_duration(&value.value()).map_err(|e| syn::Error::new(value.span(), e))?);
+        } else if meta.path.is_ident("regression") {
+            let value: LitStr = meta.value()?.parse()?;
+            self.regression = Some(parse_percentage(&value.value()).map_err(|e| syn::Error::new(value.span(), e))?);
+        } else if meta.path.is_ident("threads") {
+            self.threads = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+        } else if meta.path.is_ident("connections") {
+            self.connections = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+        } else {
+            return Err(meta.error("unsupported wrk_benchmark argument"));
+        }
+        Ok(())
+    }
+}
+
+/// Parse a duration like `5000ms`, `5s` or `1m`. wrk only runs for whole seconds, so other
+/// durations are rejected instead of being truncated.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in duration {}", value))?;
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|e| format!("invalid duration {}: {}", value, e))?;
+    let duration = match unit {
+        "ms" => Duration::from_millis(number),
+        "s" => Duration::from_secs(number),
+        "m" => Duration::from_secs(number * 60),
+        _ => {
+            return Err(format!(
+                "unsupported unit {} in duration {}, use ms, s or m",
+                unit, value
+            ))
+        }
+    };
+    if duration.is_zero() || duration.subsec_nanos() > 0 {
+        return Err(format!("duration {} must be a positive whole number of seconds", value));
+    }
+    Ok(duration)
+}
+
+/// Parse a percentage like `5%` or `2.5`.
+fn parse_percentage(value: &str) -> Result<f64, String> {
+    value
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|e| format!("invalid percentage {}: {}", value, e))
+}
+
+/// Turn an async function starting a service into a tokio test benchmarking it.
+///
+/// The function is spawned in the background, the benchmark waits for `url` to accept
+/// connections, runs wrk and fails the test when requests/sec or p99 latency regressed by
+/// more than `regression` against the last recorded run. The service is aborted once the
+/// benchmark completes. The `duration` must be a whole number of seconds.
+///
+/// ```ignore
+/// #[wrk_benchmark(url = "http://127.0.0.1:3000/", duration = "5s", regression = "5%")]
+/// async fn hello() {
+///     axum::Server::bind(&"127.0.0.1:3000".parse().unwrap())
+///         .serve(app().into_make_service())
+///         .await
+///         .unwrap();
+/// }
+/// ```
+///
+/// Optional `threads` and `connections` arguments override the benchmark defaults.
+#[proc_macro_attribute]
+pub fn wrk_benchmark(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = Args::default();
+    let parser = syn::meta::parser(|meta| args.parse(meta));
+    parse_macro_input!(attr with parser);
+    let function = parse_macro_input!(item as ItemFn);
+    if function.sig.asyncness.is_none() {
+        return syn::Error::new_spanned(function.sig.fn_token, "wrk_benchmark requires an async function")
+            .to_compile_error()
+            .into();
+    }
+    let url = match args.url {
+        Some(url) => url,
+        None => {
+            return syn::Error::new(proc_macro2::Span::call_site(), "wrk_benchmark requires a url")
+                .to_compile_error()
+                .into()
+        }
+    };
+    let duration = args.duration.unwrap_or(Duration::from_secs(5)).as_secs();
+    let regression = args.regression.unwrap_or(5.0);
+    let threads = args.threads.map(|threads| quote!(.threads(#threads)));
+    let connections = args.connections.map(|connections| quote!(.connections(#connections)));
+    let attrs = &function.attrs;
+    let vis = &function.vis;
+    let name = &function.sig.ident;
+    let body = &function.block;
+    quote! {
+        #(#attrs)*
+        #[::tokio::test(flavor = "multi_thread")]
+        #vis async fn #name() {
+            async fn service() #body
+            let service = ::tokio::spawn(service());
+            let benchmark = ::wrk_api_bench::BenchmarkBuilder::default()
+                .duration(::std::time::Duration::from_secs(#duration))
+                #threads
+                #connections
+                .build()
+                .expect("invalid wrk_benchmark configuration");
+            let result = ::tokio::task::spawn_blocking(move || {
+                ::wrk_api_bench::harness::regression_gate(#url, benchmark, #regression)
+            })
+            .await
+            .expect("benchmark task panicked");
+            service.abort();
+            if let Err(e) = result {
+                panic!("{}", e);
+            }
+        }
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_arguments() {
+        assert_eq!(parse_duration("2000ms"), Ok(Duration::from_secs(2)));
+        assert!(parse_duration("500ms").is_err());
+        assert!(parse_duration("1500ms").is_err());
+        assert!(parse_duration("0s").is_err());
+        assert_eq!(parse_duration("5s"), Ok(Duration::from_secs(5)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("5h").is_err());
+        assert_eq!(parse_percentage("5%"), Ok(5.0));
+        assert_eq!(parse_percentage("2.5"), Ok(2.5));
+        assert!(parse_percentage("five").is_err());
+    }
+}
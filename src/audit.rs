@@ -0,0 +1,120 @@
+//! Machine-parsable log of the executed engine commands.
+use std::{fs, path::Path};
+
+use chrono::{DateTime, Utc};
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{ProcessOutput, Result, Storage};
+
+/// Name of the JSONL audit log stored in the history directory.
+pub(crate) const AUDIT_LOG: &str = "audit.jsonl";
+
+/// Single executed engine command, stored as a line of the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Getters)]
+pub struct AuditEntry {
+    /// Executed program.
+    #[getset(get = "pub")]
+    command: String,
+    /// Program arguments.
+    #[getset(get = "pub")]
+    args: Vec<String>,
+    /// SHA-256 of the Lua script passed to the program.
+    #[getset(get = "pub")]
+    script_sha256: String,
+    /// Start of the execution.
+    #[getset(get = "pub")]
+    start: DateTime<Utc>,
+    /// End of the execution.
+    #[getset(get = "pub")]
+    end: DateTime<Utc>,
+    /// Exit status, missing when the program could not be started or was killed by a signal.
+    #[getset(get = "pub")]
+    exit_status: Option<i32>,
+    /// Error starting the program.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getset(get = "pub")]
+    error: Option<String>,
+}
+
+impl AuditEntry {
+    pub(crate) fn new(
+        command: &str,
+        args: &[String],
+        script: &Path,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        execution: &Result<ProcessOutput>,
+    ) -> Self {
+        let script_sha256 = match fs::read(script) {
+            Ok(script) => format!("{:x}", Sha256::digest(script)),
+            Err(e) => {
+                warn!("Unable to hash script {}: {}", script.display(), e);
+                String::new()
+            }
+        };
+        let (exit_status, error) = match execution {
+            Ok(output) => (*output.exit_status(), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        Self {
+            command: command.to_string(),
+            args: args.to_vec(),
+            script_sha256,
+            start,
+            end,
+            exit_status,
+            error,
+        }
+    }
+}
+
+/// Append `entry` to the audit log in `dir`.
+pub(crate) fn append(storage: &dyn Storage, dir: &Path, entry: &AuditEntry) -> Result<()> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    storage.append(&dir.join(AUDIT_LOG), line.as_bytes())?;
+    Ok(())
+}
+
+/// Read the audit log in `dir`, which is empty when no command was executed yet.
+pub(crate) fn read(storage: &dyn Storage, dir: &Path) -> Result<Vec<AuditEntry>> {
+    let path = dir.join(AUDIT_LOG);
+    if !storage.exists(&path) {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for line in String::from_utf8_lossy(&storage.read(&path)?).lines() {
+        if !line.trim().is_empty() {
+            entries.push(serde_json::from_str(line)?);
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LocalStorage, WrkError};
+
+    #[test]
+    fn append_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("script.lua");
+        fs::write(&script, "").unwrap();
+        let args = vec!["-t".to_string(), "1".to_string()];
+        let missing = Err(WrkError::Exec("wrk not found".to_string()));
+        let entry = AuditEntry::new("wrk", &args, &script, Utc::now(), Utc::now(), &missing);
+        append(&LocalStorage, dir.path(), &entry).unwrap();
+        append(&LocalStorage, dir.path(), &entry).unwrap();
+        let entries = read(&LocalStorage, dir.path()).unwrap();
+        assert_eq!(entries, vec![entry.clone(), entry]);
+        assert_eq!(
+            entries[0].script_sha256(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert!(entries[0].exit_status().is_none());
+        assert!(entries[0].error().is_some());
+    }
+}
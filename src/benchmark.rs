@@ -1,46 +1,225 @@
 use std::time::Duration;
 
 use getset::{Getters, MutGetters, Setters};
+use http::Method;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
+use crate::{Headers, LoadProfile, Profile};
+
+/// Single wrk benchmark configuration.
 #[derive(Debug, Default, PartialEq, Hash, Clone, Serialize, Deserialize, Getters, Setters, MutGetters, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Benchmark {
+    /// Number of wrk threads.
     #[builder(default = "8")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     threads: u16,
+    /// Number of wrk connections kept open.
     #[builder(default = "32")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     connections: u16,
+    /// Duration of the benchmark.
     #[builder(default = "Duration::from_secs(30)")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     duration: Duration,
+    /// Method overriding the [`crate::Wrk`] one for this benchmark.
+    #[builder(default, setter(strip_option))]
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::method::option")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    method: Option<Method>,
+    /// Body overriding the [`crate::Wrk`] one for this benchmark.
+    #[builder(default, setter(strip_option))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    body: Option<String>,
+    /// Headers merged on top of the [`crate::Wrk`] ones for this benchmark.
+    #[builder(default, setter(strip_option))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    headers: Option<Headers>,
+    /// Constant throughput in requests/sec, passed to wrk2 as `-R`. Plain wrk does not support
+    /// it.
+    #[builder(default, setter(strip_option))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    rate: Option<u32>,
+    /// Timeout of every request overriding the [`crate::Wrk`] one, IE: tighter for short runs
+    /// than for long soaks.
+    #[builder(default, setter(strip_option))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    timeout: Option<Duration>,
+    /// Human readable label, IE: `low-concurrency`, shown in reports and plots.
+    #[builder(default, setter(strip_option))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    label: Option<String>,
 }
 
 impl BenchmarkBuilder {
+    fn validate(&self) -> std::result::Result<(), String> {
+        if let Some(Some(method)) = &self.method {
+            crate::method::validate(method)?;
+        }
+        if let Some(Some(headers)) = &self.headers {
+            crate::headers::validate(headers)?;
+        }
+        Ok(())
+    }
+
+    /// Generate a list of benchmarks with exponentially growing threads and connections,
+    /// labelled by their concurrency level.
     pub fn exponential(duration: Option<Duration>) -> Vec<Benchmark> {
         let duration = duration.unwrap_or_else(|| Duration::from_secs(30));
         let threads_list = [2, 4, 8, 16];
-        let connections_list = [32, 64, 128, 256];
+        let connections_list = [
+            (32, "low-concurrency"),
+            (64, "medium-concurrency"),
+            (128, "high-concurrency"),
+            (256, "stress"),
+        ];
         let mut benchmarks = Vec::new();
         for threads in threads_list {
-            for connections in connections_list {
+            for (connections, level) in connections_list {
                 benchmarks.push(Benchmark {
                     threads,
                     connections,
                     duration,
+                    label: Some(format!("{}-{}-threads", level, threads)),
+                    ..Default::default()
                 });
             }
         }
         benchmarks
     }
+
+    /// Generate the sequence of benchmarks creating the given load shape.
+    pub fn profile(profile: &LoadProfile) -> Vec<Benchmark> {
+        profile.benchmarks()
+    }
+
+    /// Generate the benchmark matrix of the given preset.
+    pub fn preset(profile: Profile) -> Vec<Benchmark> {
+        profile.benchmarks()
+    }
 }
 
 impl Benchmark {
+    /// Create a new benchmark with a duration expressed in seconds.
     pub fn new(threads: u16, connections: u16, duration: u64) -> Self {
         Self {
             threads,
             connections,
             duration: Duration::from_secs(duration),
+            ..Default::default()
+        }
+    }
+
+    /// Key identifying this benchmark configuration, IE: `2-32-30` for 2 threads,
+    /// 32 connections and 30 seconds. The rate, IE: `1000rps`, and the method are appended when
+    /// set, followed by a short hash of the method, body and headers when a body or headers are
+    /// set, so benchmarks of different requests never share a key.
+    pub fn key(&self) -> String {
+        let mut key = format!("{}-{}-{}", self.threads, self.connections, self.duration.as_secs());
+        if let Some(rate) = self.rate {
+            key = format!("{}-{}rps", key, rate);
+        }
+        if let Some(method) = &self.method {
+            key = format!("{}-{}", key, method);
         }
+        if self.body.is_some() || self.headers.is_some() {
+            key = format!("{}-{}", key, self.request_hash());
+        }
+        key
+    }
+
+    /// First 8 hexadecimal digits of the SHA-256 of the method, body and headers.
+    fn request_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.method.as_ref().map_or("", Method::as_str));
+        hasher.update([0]);
+        hasher.update(self.body.as_deref().unwrap_or_default());
+        hasher.update([0]);
+        for (name, value) in self.headers.iter().flatten() {
+            hasher.update(name.to_ascii_lowercase());
+            hasher.update([0]);
+            hasher.update(value);
+            hasher.update([0]);
+        }
+        format!("{:x}", hasher.finalize())[..8].to_string()
+    }
+
+    /// Label of this benchmark, falling back to its [`Benchmark::key`].
+    pub fn name(&self) -> String {
+        self.label.clone().unwrap_or_else(|| self.key())
+    }
+
+    /// Whether `other` runs the same configuration, regardless of the label and of the values
+    /// of headers redacted in either of them.
+    pub fn is_comparable(&self, other: &Benchmark) -> bool {
+        let redacted: Vec<String> = [self, other]
+            .iter()
+            .filter_map(|b| b.headers.as_ref())
+            .flatten()
+            .filter(|(_, value)| *value == crate::headers::REDACTED)
+            .map(|(name, _)| name.clone())
+            .collect();
+        let unlabelled = |b: &Benchmark| Benchmark {
+            label: None,
+            headers: b.headers.as_ref().map(|h| crate::headers::redact(h, &redacted)),
+            ..b.clone()
+        };
+        unlabelled(self) == unlabelled(other)
+    }
+
+    /// Method to use for this benchmark, falling back to `default`.
+    pub fn method_or<'a>(&'a self, default: &'a Method) -> &'a Method {
+        self.method.as_ref().unwrap_or(default)
+    }
+
+    /// Body to use for this benchmark, falling back to `default`.
+    pub fn body_or<'a>(&'a self, default: &'a str) -> &'a str {
+        self.body.as_deref().unwrap_or(default)
+    }
+
+    /// Headers to use for this benchmark: `default` with the benchmark headers merged on top.
+    pub fn headers_or(&self, default: &Headers) -> Headers {
+        let mut headers = default.clone();
+        if let Some(overrides) = &self.headers {
+            headers.extend(overrides.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key() {
+        let mut benchmark = Benchmark::new(2, 32, 30);
+        assert_eq!(benchmark.key(), "2-32-30");
+        benchmark.set_method(Some(Method::POST));
+        assert_eq!(benchmark.key(), "2-32-30-POST");
+        benchmark.set_body(Some("{\"a\":1}".to_string()));
+        let with_body = benchmark.key();
+        assert!(with_body.starts_with("2-32-30-POST-"));
+        assert_eq!(with_body.len(), "2-32-30-POST-".len() + 8);
+        benchmark.set_body(Some("{\"a\":2}".to_string()));
+        assert_ne!(benchmark.key(), with_body);
+        let with_headers = |name: &str| {
+            let mut benchmark = Benchmark::new(2, 32, 30);
+            benchmark.set_headers(Some(Headers::from([(
+                name.to_string(),
+                "application/json".to_string(),
+            )])));
+            benchmark.key()
+        };
+        assert_eq!(with_headers("Accept"), with_headers("accept"));
+        assert_ne!(with_headers("Accept"), with_headers("Content-Type"));
     }
 }
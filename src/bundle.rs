@@ -0,0 +1,325 @@
+//! Reproducibility bundles: zip archives holding everything needed to inspect or re-run a
+//! recorded benchmark run, and history archives exchanging baselines between machines.
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+use crate::{
+    encryption::{self, HistoryKey},
+    headers, history, Benchmark, Benchmarks, HistoryPeriod, Result, Wrk, WrkError,
+};
+
+const CONFIG: &str = "wrk.json";
+const PLAN: &str = "plan.json";
+const RESULTS: &str = "results.json";
+const ENVIRONMENT: &str = "environment.json";
+const USER_SCRIPT: &str = "user_script.lua";
+const SCRIPTS: &str = "scripts";
+/// Directory of the history files in a history archive.
+const HISTORY: &str = "history";
+/// Directory of the temporary directory where [`import`] extracts user scripts, named after
+/// their hash.
+const EXTRACTED_USER_SCRIPTS: &str = "bundle-scripts";
+
+/// Metadata of the machine which produced the bundle.
+#[derive(Debug, Serialize, Deserialize)]
+struct Environment {
+    crate_version: String,
+    os: String,
+    arch: String,
+    wrk_version: Option<String>,
+    exported_at: DateTime<Utc>,
+}
+
+impl Environment {
+    /// Environment of this machine, with the version of the program running the first
+    /// benchmark of `plan` through the runner of `wrk`.
+    fn current(wrk: &Wrk, plan: &[Benchmark]) -> Self {
+        let wrk_version = wrk.program_version(plan.first().unwrap_or(&Benchmark::default()));
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            wrk_version,
+            exported_at: Utc::now(),
+        }
+    }
+}
+
+/// Write a bundle of `wrk` configuration and `results` to `path`.
+pub(crate) fn export(wrk: &Wrk, path: &Path, results: &Benchmarks) -> Result<()> {
+    let mut plan: Vec<Benchmark> = Vec::new();
+    for benchmark in results.iter().filter_map(|r| r.benchmark().as_ref()) {
+        if !plan.contains(benchmark) {
+            plan.push(benchmark.clone());
+        }
+    }
+    let mut config = wrk.clone();
+    config.set_headers(headers::redact(wrk.headers(), wrk.redacted_headers()));
+    let redacted_plan: Vec<Benchmark> = plan
+        .iter()
+        .cloned()
+        .map(|mut benchmark| {
+            if let Some(headers) = benchmark.headers_mut() {
+                *headers = headers::redact(headers, wrk.redacted_headers());
+            }
+            benchmark
+        })
+        .collect();
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = FileOptions::default();
+    zip.start_file(CONFIG, options)?;
+    serde_json::to_writer_pretty(&mut zip, &config)?;
+    zip.start_file(PLAN, options)?;
+    serde_json::to_writer_pretty(&mut zip, &redacted_plan)?;
+    zip.start_file(RESULTS, options)?;
+    serde_json::to_writer_pretty(&mut zip, &wrk.redact(results))?;
+    zip.start_file(ENVIRONMENT, options)?;
+    serde_json::to_writer_pretty(&mut zip, &Environment::current(wrk, &plan))?;
+    if let Some(user_script) = wrk.user_script() {
+        zip.start_file(USER_SCRIPT, options)?;
+        zip.write_all(&fs::read(user_script)?)?;
+    }
+    for benchmark in &plan {
+        zip.start_file(format!("{}/{}.lua", SCRIPTS, benchmark.key()), options)?;
+        zip.write_all(wrk.command_for(benchmark)?.script().as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>> {
+    let mut entry = archive.by_name(name)?;
+    let mut content = Vec::new();
+    entry.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// Read the configuration and benchmark plan stored in the bundle at `path`. A bundled user
+/// script is extracted in the temporary directory, never in a location taken from the bundle.
+pub(crate) fn import(path: &Path) -> Result<(Wrk, Vec<Benchmark>)> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut wrk: Wrk = serde_json::from_slice(&read_entry(&mut archive, CONFIG)?)?;
+    let plan: Vec<Benchmark> = serde_json::from_slice(&read_entry(&mut archive, PLAN)?)?;
+    if wrk
+        .headers()
+        .values()
+        .chain(
+            plan.iter()
+                .filter_map(|b| b.headers().as_ref())
+                .flat_map(|h| h.values()),
+        )
+        .any(|value| value == headers::REDACTED)
+    {
+        warn!(
+            "Bundle {} holds redacted headers, set them again before running it",
+            path.display()
+        );
+    }
+    if wrk.user_script().is_some() {
+        let script = read_entry(&mut archive, USER_SCRIPT)
+            .map_err(|e| WrkError::History(format!("Bundle {} lacks its user script: {}", path.display(), e)))?;
+        wrk.set_user_script(Some(extract_user_script(&script)?));
+    }
+    Ok((wrk, plan))
+}
+
+/// Write the user `script` of a bundle to the temporary directory, under its hash.
+fn extract_user_script(script: &[u8]) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join("wrk-api-bench").join(EXTRACTED_USER_SCRIPTS);
+    let path = dir.join(format!("{:x}.lua", Sha256::digest(script)));
+    if !path.exists() {
+        fs::create_dir_all(&dir)?;
+        let mut file = tempfile::Builder::new().prefix(".script.").tempfile_in(&dir)?;
+        file.write_all(script)?;
+        file.persist(&path)?;
+    }
+    Ok(path)
+}
+
+/// Write the history files of `wrk` recorded in `period` to the zip archive at `path`, as they
+/// are stored.
+pub(crate) fn export_history(wrk: &Wrk, period: &HistoryPeriod, path: &Path) -> Result<()> {
+    let mut files = wrk.history_files(None)?;
+    if *period == HistoryPeriod::Last {
+        files = files.pop().into_iter().collect();
+    } else {
+        files.retain(|(date, _, _)| *date >= period.since(wrk.now()));
+    }
+    let mut zip = ZipWriter::new(File::create(path)?);
+    for (_, _, file) in files {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        zip.start_file(format!("{}/{}", HISTORY, name), FileOptions::default())?;
+        zip.write_all(&wrk.history_store().read(&file)?)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// Merge the history files of the archive at `path` into the history of `wrk`, skipping the
+/// runs already stored. Files are converted to the configured history format. Returns the
+/// number of imported results.
+pub(crate) fn import_history(wrk: &Wrk, path: &Path) -> Result<usize> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut known: HashSet<String> = wrk
+        .history(&HistoryPeriod::Forever, None)?
+        .iter()
+        .map(|r| r.identity())
+        .collect();
+    let names: Vec<String> = archive
+        .file_names()
+        .filter(|name| name.starts_with(&format!("{}/", HISTORY)))
+        .map(str::to_string)
+        .collect();
+    let mut imported = 0;
+    for name in names {
+        let Some((date, format)) = history::parse_filename(Path::new(&name)) else {
+            warn!("Skipping archive entry {} without a valid history file name", name);
+            continue;
+        };
+        let date = date.to_utc();
+        let results: Benchmarks = format
+            .read(&encryption::decode(Path::new(&name), read_entry(&mut archive, &name)?)?[..])?
+            .into_iter()
+            .filter(|r| known.insert(r.identity()))
+            .collect();
+        if results.is_empty() {
+            continue;
+        }
+        imported += results.len();
+        let local = wrk.history_file(&date, HistoryKey::from_env()?.is_some());
+        let mut merged = if wrk.history_store().exists(&local) {
+            wrk.read(&local, *wrk.history_format())?
+        } else {
+            Benchmarks::new()
+        };
+        merged.extend(results);
+        wrk.dump(date, &merged)?;
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::result_builder, CommandSpec, ProcessOutput, ProcessRunner, WrkBuilder};
+
+    /// Runner answering `-v` with the version of wrk.
+    #[derive(Debug)]
+    struct VersionRunner;
+
+    impl ProcessRunner for VersionRunner {
+        fn run(&self, spec: &CommandSpec) -> Result<ProcessOutput> {
+            assert_eq!(spec.args(), &vec!["-v".to_string()]);
+            Ok(ProcessOutput::new(
+                Some(1),
+                String::new(),
+                "wrk 4.1.0 [epoll] Copyright (C) 2012 Will Glozer\n".to_string(),
+            ))
+        }
+    }
+
+    #[test]
+    fn export_and_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let user_script = dir.path().join("user.lua");
+        fs::write(&user_script, "-- bundled user script").unwrap();
+        let history_dir = dir.path().join("history");
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13734/api".to_string())
+            .history_dir(history_dir.clone())
+            .runner(VersionRunner)
+            .timeout(5)
+            .build()
+            .unwrap();
+        let benchmark = Benchmark::new(2, 32, 10);
+        let results = vec![result_builder(0.0, 0.0).benchmark(benchmark.clone()).build().unwrap()];
+        let bundle = dir.path().join("bundle.zip");
+        export(&wrk, &bundle, &results).unwrap();
+        let (restored, plan) = import(&bundle).unwrap();
+        assert_eq!(restored.url(), wrk.url());
+        assert_eq!(*restored.timeout(), 5);
+        assert_eq!(plan, vec![benchmark.clone()]);
+        let mut archive = ZipArchive::new(File::open(&bundle).unwrap()).unwrap();
+        let script = read_entry(&mut archive, &format!("{}/{}.lua", SCRIPTS, benchmark.key())).unwrap();
+        assert!(String::from_utf8(script).unwrap().contains("/api"));
+        let environment: Environment = serde_json::from_slice(&read_entry(&mut archive, ENVIRONMENT).unwrap()).unwrap();
+        assert_eq!(
+            environment.wrk_version.as_deref(),
+            Some("wrk 4.1.0 [epoll] Copyright (C) 2012 Will Glozer")
+        );
+
+        // The user script is never extracted in the history directory of the bundle.
+        let mut scripted = wrk.clone();
+        scripted.set_user_script(Some(user_script));
+        export(&scripted, &bundle, &results).unwrap();
+        let (restored, _) = import(&bundle).unwrap();
+        let extracted = restored.user_script().clone().unwrap();
+        assert!(!extracted.starts_with(&history_dir));
+        assert!(extracted.starts_with(std::env::temp_dir()));
+        assert_eq!(fs::read_to_string(extracted).unwrap(), "-- bundled user script");
+    }
+
+    #[test]
+    fn export_and_import_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let wrk = |name: &str| {
+            WrkBuilder::default()
+                .url("http://127.0.0.1:13740/api".to_string())
+                .history_dir(dir.path().join(name))
+                .build()
+                .unwrap()
+        };
+        let (laptop, ci) = (wrk("laptop"), wrk("ci"));
+        let result = || result_builder(0.0, 0.0).build().unwrap();
+        let now = Utc::now();
+        let shared = result();
+        laptop
+            .dump(now - chrono::Duration::minutes(2), &vec![shared.clone(), result()])
+            .unwrap();
+        laptop
+            .dump(now - chrono::Duration::minutes(1), &vec![result()])
+            .unwrap();
+        ci.dump(now - chrono::Duration::minutes(2), &vec![shared]).unwrap();
+
+        let archive = dir.path().join("history.zip");
+        export_history(&laptop, &HistoryPeriod::Forever, &archive).unwrap();
+        assert_eq!(import_history(&ci, &archive).unwrap(), 2);
+        assert_eq!(import_history(&ci, &archive).unwrap(), 0);
+        assert_eq!(ci.history(&HistoryPeriod::Forever, None).unwrap().len(), 3);
+        assert_eq!(ci.history_files(None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn import_history_into_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let wrk = || {
+            WrkBuilder::default()
+                .url("http://127.0.0.1:13740/api".to_string())
+                .history_store(crate::HistoryStore::in_memory())
+                .build()
+                .unwrap()
+        };
+        let (laptop, ci) = (wrk(), wrk());
+        let result = || result_builder(0.0, 0.0).build().unwrap();
+        let date = Utc::now() - chrono::Duration::minutes(1);
+        laptop.dump(date, &vec![result()]).unwrap();
+        let existing = result();
+        ci.dump(date, &vec![existing.clone()]).unwrap();
+
+        let archive = dir.path().join("history.zip");
+        export_history(&laptop, &HistoryPeriod::Forever, &archive).unwrap();
+        assert_eq!(import_history(&ci, &archive).unwrap(), 1);
+        let history = ci.history(&HistoryPeriod::Forever, None).unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().any(|r| r.identity() == existing.identity()));
+    }
+}
@@ -0,0 +1,94 @@
+//! Text charts of the history, embeddable in GitHub job summaries where images are
+//! inconvenient.
+use serde::{Deserialize, Serialize};
+
+use crate::WrkResult;
+
+/// Width in characters of the longest bar of [`TextChart::Markdown`].
+const BAR_WIDTH: usize = 20;
+
+/// Format of a text chart of requests/sec over time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextChart {
+    /// Mermaid `xychart-beta` line chart in a fenced code block, rendered by GitHub.
+    #[default]
+    Mermaid,
+    /// Markdown table with a bar of block characters per run.
+    Markdown,
+}
+
+impl TextChart {
+    /// Render the requests/sec of `runs`, in the given order.
+    pub fn render(&self, title: &str, runs: &[WrkResult]) -> String {
+        match self {
+            Self::Mermaid => mermaid(title, runs),
+            Self::Markdown => markdown(title, runs),
+        }
+    }
+}
+
+fn mermaid(title: &str, runs: &[WrkResult]) -> String {
+    let labels: Vec<_> = runs
+        .iter()
+        .map(|r| format!("\"{}\"", r.date().format("%m-%d %H:%M")))
+        .collect();
+    let values: Vec<_> = runs.iter().map(|r| format!("{:.2}", r.requests_sec())).collect();
+    format!(
+        "```mermaid\nxychart-beta\n    title \"{}\"\n    x-axis [{}]\n    y-axis \"Requests/sec\"\n    line [{}]\n```\n",
+        title.replace('"', "'"),
+        labels.join(", "),
+        values.join(", ")
+    )
+}
+
+fn markdown(title: &str, runs: &[WrkResult]) -> String {
+    let max = runs.iter().map(|r| *r.requests_sec()).fold(0.0, f64::max);
+    let mut chart = format!(
+        "### {}\n\n|Date|Benchmark|Requests/sec|P99 latency (ms)||\n|-|-|-|-|-|\n",
+        title
+    );
+    for run in runs {
+        let width = if max > 0.0 {
+            (run.requests_sec() / max * BAR_WIDTH as f64).round() as usize
+        } else {
+            0
+        };
+        chart += &format!(
+            "|{}|{}|{:.2}|{:.2}|{}|\n",
+            run.date().format("%Y-%m-%d %H:%M"),
+            run.benchmark().as_ref().map(|b| b.name()).unwrap_or_default(),
+            run.requests_sec(),
+            run.p99_latency_ms(),
+            "█".repeat(width)
+        );
+    }
+    chart
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::test_utils::result_builder;
+
+    #[test]
+    fn render_charts() {
+        let result = |requests_sec: f64, minute: u32| {
+            result_builder(requests_sec, 4.0)
+                .date(Utc.with_ymd_and_hms(2024, 3, 1, 12, minute, 0).unwrap())
+                .build()
+                .unwrap()
+        };
+        let runs = vec![result(500.0, 1), result(1000.0, 2)];
+        assert_eq!(
+            TextChart::Mermaid.render("api \"users\"", &runs),
+            "```mermaid\nxychart-beta\n    title \"api 'users'\"\n    x-axis [\"03-01 12:01\", \"03-01 12:02\"]\n    \
+             y-axis \"Requests/sec\"\n    line [500.00, 1000.00]\n```\n"
+        );
+        let markdown = TextChart::Markdown.render("api", &runs);
+        assert!(markdown.contains(&format!("|2024-03-01 12:01|1-1-1|500.00|4.00|{}|\n", "█".repeat(10))));
+        assert!(markdown.contains(&format!("|1000.00|4.00|{}|\n", "█".repeat(20))));
+    }
+}
@@ -0,0 +1,49 @@
+//! Source of the current time of a [`crate::Wrk`], injectable so history periods, rollups and
+//! file names can be tested deterministically.
+use std::{fmt::Debug, sync::Mutex};
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+
+/// Source of the current time.
+pub trait Clock: Debug + Send + Sync {
+    /// Current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Clock of the system, used by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock standing still until it is moved by hand, for tests.
+#[derive(Debug)]
+pub struct ManualClock(Mutex<DateTime<Utc>>);
+
+impl ManualClock {
+    /// Clock stopped at `now`.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self(Mutex::new(now))
+    }
+
+    /// Move the clock to `now`.
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    /// Move the clock forward by `duration`.
+    pub fn advance(&self, duration: ChronoDuration) {
+        let mut now = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        *now += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,365 @@
+//! Posting benchmark reports as pull request comments on GitHub, GitLab and Bitbucket, configured
+//! from the CI environment.
+use std::{
+    env,
+    fmt::{self, Debug},
+};
+
+use serde_json::{json, Value};
+
+use crate::{headers::REDACTED, Result, Variance, WrkError};
+
+/// Headers carrying the token of the code host, redacted when debug printed.
+const TOKEN_HEADERS: [&str; 2] = ["Authorization", "PRIVATE-TOKEN"];
+
+/// An HTTP request posting a comment, built by a [`CodeHost`].
+#[derive(Clone, PartialEq)]
+pub struct CommentRequest {
+    /// Endpoint receiving the comment.
+    pub url: String,
+    /// Request headers, including the authentication one.
+    pub headers: Vec<(String, String)>,
+    /// JSON payload.
+    pub body: Value,
+}
+
+impl fmt::Debug for CommentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let headers: Vec<_> = self
+            .headers
+            .iter()
+            .map(
+                |(name, value)| match TOKEN_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+                    true => (name.as_str(), REDACTED),
+                    false => (name.as_str(), value.as_str()),
+                },
+            )
+            .collect();
+        f.debug_struct("CommentRequest")
+            .field("url", &self.url)
+            .field("headers", &headers)
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+impl CommentRequest {
+    /// Send the request.
+    pub fn send(&self) -> Result<()> {
+        let mut request = ureq::post(&self.url).set("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        request
+            .send_string(&self.body.to_string())
+            .map_err(|e| WrkError::CodeHost(format!("Unable to post comment to {}: {}", self.url, e)))?;
+        Ok(())
+    }
+}
+
+/// A code hosting service accepting comments on the pull (or merge) request under test.
+pub trait CodeHost: Debug {
+    /// Name of the service.
+    fn name(&self) -> &str;
+
+    /// Build the request posting `body`, as markdown, on the pull request.
+    fn comment_request(&self, body: &str) -> CommentRequest;
+
+    /// Post `body`, as markdown, on the pull request.
+    fn comment(&self, body: &str) -> Result<()> {
+        info!("Posting benchmark report to {}", self.name());
+        self.comment_request(body).send()
+    }
+
+    /// Post the markdown report of `variance` on the pull request.
+    fn report(&self, variance: &Variance) -> Result<()> {
+        self.comment(&variance.to_markdown())
+    }
+}
+
+fn required<F: Fn(&str) -> Option<String>>(lookup: &F, name: &str) -> Result<String> {
+    lookup(name)
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| WrkError::Config(format!("{} is not set", name)))
+}
+
+fn optional<F: Fn(&str) -> Option<String>>(lookup: &F, name: &str) -> Option<String> {
+    required(lookup, name).ok()
+}
+
+/// GitHub pull request comments, using `GITHUB_TOKEN`, `GITHUB_REPOSITORY` and the pull request
+/// number from `WRK_API_BENCH_PR` or `GITHUB_REF` (`refs/pull/<number>/merge`).
+#[derive(Clone, PartialEq)]
+pub struct GitHub {
+    api_url: String,
+    repository: String,
+    pull_request: String,
+    token: String,
+}
+
+impl fmt::Debug for GitHub {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GitHub")
+            .field("api_url", &self.api_url)
+            .field("repository", &self.repository)
+            .field("pull_request", &self.pull_request)
+            .field("token", &REDACTED)
+            .finish()
+    }
+}
+
+impl GitHub {
+    /// Configure from the process environment.
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|name| env::var(name).ok())
+    }
+
+    fn from_lookup<F: Fn(&str) -> Option<String>>(lookup: F) -> Result<Self> {
+        let pull_request = optional(&lookup, "WRK_API_BENCH_PR")
+            .or_else(|| {
+                optional(&lookup, "GITHUB_REF").and_then(|reference| {
+                    reference
+                        .strip_prefix("refs/pull/")
+                        .and_then(|rest| rest.split('/').next())
+                        .map(str::to_string)
+                })
+            })
+            .ok_or_else(|| WrkError::Config("Unable to find the pull request number in GITHUB_REF".to_string()))?;
+        Ok(Self {
+            api_url: optional(&lookup, "GITHUB_API_URL").unwrap_or_else(|| "https://api.github.com".to_string()),
+            repository: required(&lookup, "GITHUB_REPOSITORY")?,
+            pull_request,
+            token: required(&lookup, "GITHUB_TOKEN")?,
+        })
+    }
+}
+
+impl CodeHost for GitHub {
+    fn name(&self) -> &str {
+        "GitHub"
+    }
+
+    fn comment_request(&self, body: &str) -> CommentRequest {
+        CommentRequest {
+            url: format!(
+                "{}/repos/{}/issues/{}/comments",
+                self.api_url.trim_end_matches('/'),
+                self.repository,
+                self.pull_request
+            ),
+            headers: vec![
+                ("Authorization".to_string(), format!("Bearer {}", self.token)),
+                ("Accept".to_string(), "application/vnd.github+json".to_string()),
+            ],
+            body: json!({ "body": body }),
+        }
+    }
+}
+
+/// GitLab merge request notes, using `GITLAB_TOKEN` and the `CI_API_V4_URL`, `CI_PROJECT_ID` and
+/// `CI_MERGE_REQUEST_IID` predefined variables.
+#[derive(Clone, PartialEq)]
+pub struct GitLab {
+    api_url: String,
+    project_id: String,
+    merge_request: String,
+    token: String,
+}
+
+impl fmt::Debug for GitLab {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GitLab")
+            .field("api_url", &self.api_url)
+            .field("project_id", &self.project_id)
+            .field("merge_request", &self.merge_request)
+            .field("token", &REDACTED)
+            .finish()
+    }
+}
+
+impl GitLab {
+    /// Configure from the process environment.
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|name| env::var(name).ok())
+    }
+
+    fn from_lookup<F: Fn(&str) -> Option<String>>(lookup: F) -> Result<Self> {
+        Ok(Self {
+            api_url: optional(&lookup, "CI_API_V4_URL").unwrap_or_else(|| "https://gitlab.com/api/v4".to_string()),
+            project_id: required(&lookup, "CI_PROJECT_ID")?,
+            merge_request: required(&lookup, "CI_MERGE_REQUEST_IID")?,
+            token: required(&lookup, "GITLAB_TOKEN")?,
+        })
+    }
+}
+
+impl CodeHost for GitLab {
+    fn name(&self) -> &str {
+        "GitLab"
+    }
+
+    fn comment_request(&self, body: &str) -> CommentRequest {
+        CommentRequest {
+            url: format!(
+                "{}/projects/{}/merge_requests/{}/notes",
+                self.api_url.trim_end_matches('/'),
+                self.project_id,
+                self.merge_request
+            ),
+            headers: vec![("PRIVATE-TOKEN".to_string(), self.token.clone())],
+            body: json!({ "body": body }),
+        }
+    }
+}
+
+/// Bitbucket Cloud pull request comments, using `BITBUCKET_TOKEN` and the `BITBUCKET_WORKSPACE`,
+/// `BITBUCKET_REPO_SLUG` and `BITBUCKET_PR_ID` predefined variables.
+#[derive(Clone, PartialEq)]
+pub struct Bitbucket {
+    api_url: String,
+    workspace: String,
+    repository: String,
+    pull_request: String,
+    token: String,
+}
+
+impl fmt::Debug for Bitbucket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Bitbucket")
+            .field("api_url", &self.api_url)
+            .field("workspace", &self.workspace)
+            .field("repository", &self.repository)
+            .field("pull_request", &self.pull_request)
+            .field("token", &REDACTED)
+            .finish()
+    }
+}
+
+impl Bitbucket {
+    /// Configure from the process environment.
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|name| env::var(name).ok())
+    }
+
+    fn from_lookup<F: Fn(&str) -> Option<String>>(lookup: F) -> Result<Self> {
+        Ok(Self {
+            api_url: optional(&lookup, "BITBUCKET_API_URL")
+                .unwrap_or_else(|| "https://api.bitbucket.org/2.0".to_string()),
+            workspace: required(&lookup, "BITBUCKET_WORKSPACE")?,
+            repository: required(&lookup, "BITBUCKET_REPO_SLUG")?,
+            pull_request: required(&lookup, "BITBUCKET_PR_ID")?,
+            token: required(&lookup, "BITBUCKET_TOKEN")?,
+        })
+    }
+}
+
+impl CodeHost for Bitbucket {
+    fn name(&self) -> &str {
+        "Bitbucket"
+    }
+
+    fn comment_request(&self, body: &str) -> CommentRequest {
+        CommentRequest {
+            url: format!(
+                "{}/repositories/{}/{}/pullrequests/{}/comments",
+                self.api_url.trim_end_matches('/'),
+                self.workspace,
+                self.repository,
+                self.pull_request
+            ),
+            headers: vec![("Authorization".to_string(), format!("Bearer {}", self.token))],
+            body: json!({ "content": { "raw": body } }),
+        }
+    }
+}
+
+/// Detect the code host of the running CI pipeline from `GITHUB_ACTIONS`, `GITLAB_CI` or
+/// `BITBUCKET_BUILD_NUMBER`, returning `None` outside of a known pipeline.
+pub fn detect() -> Result<Option<Box<dyn CodeHost>>> {
+    detect_with(|name| env::var(name).ok())
+}
+
+fn detect_with<F: Fn(&str) -> Option<String>>(lookup: F) -> Result<Option<Box<dyn CodeHost>>> {
+    let host: Box<dyn CodeHost> = if optional(&lookup, "GITHUB_ACTIONS").is_some() {
+        Box::new(GitHub::from_lookup(lookup)?)
+    } else if optional(&lookup, "GITLAB_CI").is_some() {
+        Box::new(GitLab::from_lookup(lookup)?)
+    } else if optional(&lookup, "BITBUCKET_BUILD_NUMBER").is_some() {
+        Box::new(Bitbucket::from_lookup(lookup)?)
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(host))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn comment_requests() {
+        let github = detect_with(lookup(&[
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_REPOSITORY", "crisidev/wrk-api-bench-rs"),
+            ("GITHUB_REF", "refs/pull/42/merge"),
+            ("GITHUB_TOKEN", "secret"),
+        ]))
+        .unwrap()
+        .unwrap();
+        let request = github.comment_request("report");
+        assert_eq!(
+            request.url,
+            "https://api.github.com/repos/crisidev/wrk-api-bench-rs/issues/42/comments"
+        );
+        assert_eq!(request.body, json!({ "body": "report" }));
+
+        let gitlab = detect_with(lookup(&[
+            ("GITLAB_CI", "true"),
+            ("CI_API_V4_URL", "https://gitlab.example.com/api/v4"),
+            ("CI_PROJECT_ID", "7"),
+            ("CI_MERGE_REQUEST_IID", "3"),
+            ("GITLAB_TOKEN", "secret"),
+        ]))
+        .unwrap()
+        .unwrap();
+        let request = gitlab.comment_request("report");
+        assert_eq!(
+            request.url,
+            "https://gitlab.example.com/api/v4/projects/7/merge_requests/3/notes"
+        );
+        assert_eq!(
+            request.headers,
+            vec![("PRIVATE-TOKEN".to_string(), "secret".to_string())]
+        );
+
+        let bitbucket = detect_with(lookup(&[
+            ("BITBUCKET_BUILD_NUMBER", "12"),
+            ("BITBUCKET_WORKSPACE", "crisidev"),
+            ("BITBUCKET_REPO_SLUG", "wrk-api-bench-rs"),
+            ("BITBUCKET_PR_ID", "5"),
+            ("BITBUCKET_TOKEN", "secret"),
+        ]))
+        .unwrap()
+        .unwrap();
+        let request = bitbucket.comment_request("report");
+        assert_eq!(
+            request.url,
+            "https://api.bitbucket.org/2.0/repositories/crisidev/wrk-api-bench-rs/pullrequests/5/comments"
+        );
+        assert_eq!(request.body, json!({ "content": { "raw": "report" } }));
+
+        assert!(!format!("{:?}{:?}", bitbucket, request).contains("secret"));
+        assert!(!format!("{:?}", gitlab.comment_request("report")).contains("secret"));
+
+        assert!(detect_with(lookup(&[])).unwrap().is_none());
+        assert!(detect_with(lookup(&[("GITLAB_CI", "true")])).is_err());
+    }
+}
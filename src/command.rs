@@ -0,0 +1,51 @@
+//! Engine command lines, inspectable before running and serializable for remote executors.
+use std::{collections::BTreeMap, process::Command};
+
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+
+/// Path of the Lua script in the arguments of [`crate::Wrk::command_for`], relative to the
+/// working directory of the command.
+pub(crate) const SCRIPT_PATH: &str = "wrk-api-bench.lua";
+
+/// Exact engine invocation for a single benchmark.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Getters)]
+pub struct CommandSpec {
+    /// Program to execute.
+    #[getset(get = "pub")]
+    program: String,
+    /// Program arguments.
+    #[getset(get = "pub")]
+    args: Vec<String>,
+    /// Environment variables set for the program.
+    #[getset(get = "pub")]
+    env: BTreeMap<String, String>,
+    /// Content of the Lua script referenced by the `-s` argument.
+    #[getset(get = "pub")]
+    script: String,
+}
+
+impl CommandSpec {
+    pub(crate) fn new(program: &str, args: Vec<String>, env: BTreeMap<String, String>, script: String) -> Self {
+        Self {
+            program: program.to_string(),
+            args,
+            env,
+            script,
+        }
+    }
+
+    /// Same specification with the environment variable `key` set to `value`.
+    pub(crate) fn with_env(mut self, key: &str, value: &str) -> Self {
+        self.env.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// [`Command`] running this specification. The script must exist at the path given in the
+    /// arguments.
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).envs(&self.env);
+        command
+    }
+}
@@ -0,0 +1,341 @@
+//! Comparison of the same benchmark plan run against multiple implementations.
+use std::fmt;
+
+use crate::table::{format, Attr, Cell, Row, Table};
+
+use crate::{Benchmark, Benchmarks, WrkResult};
+
+/// Results of every target of [`crate::Wrk::matrix_targets`], or every content type of
+/// [`crate::Wrk::compare_content_types`], in the order they were run.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    targets: Vec<(String, Benchmarks)>,
+    interleaved: bool,
+}
+
+/// Paired comparison of the requests/sec of two targets run interleaved with the same
+/// benchmark configuration, see [`Comparison::paired`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paired {
+    /// Compared benchmark configuration.
+    pub benchmark: Benchmark,
+    /// Number of pairs of successful runs.
+    pub pairs: usize,
+    /// Mean difference of requests/sec of the second target against the first one.
+    pub mean_difference: f64,
+    /// Mean difference as a percentage of the mean requests/sec of the first target.
+    pub relative_difference: f64,
+    /// Standard deviation of the differences.
+    pub stdev: f64,
+    /// Whether the 95% confidence interval of the mean difference excludes zero.
+    pub significant: bool,
+}
+
+impl Comparison {
+    pub(crate) fn new(targets: Vec<(String, Benchmarks)>) -> Self {
+        Self {
+            targets,
+            interleaved: false,
+        }
+    }
+
+    /// Comparison of two targets whose runs of every configuration were alternated.
+    pub(crate) fn interleaved(targets: Vec<(String, Benchmarks)>) -> Self {
+        Self {
+            targets,
+            interleaved: true,
+        }
+    }
+
+    /// Results of every target.
+    pub fn targets(&self) -> &[(String, Benchmarks)] {
+        &self.targets
+    }
+
+    /// Best result of every target ranked by requests/sec, targets without a successful run last.
+    pub fn ranking(&self) -> Vec<(&str, Option<&WrkResult>)> {
+        let mut ranking: Vec<_> = self
+            .targets
+            .iter()
+            .map(|(name, results)| {
+                let best = results
+                    .iter()
+                    .filter(|r| *r.success())
+                    .max_by(|a, b| a.requests_sec().total_cmp(b.requests_sec()));
+                (name.as_str(), best)
+            })
+            .collect();
+        ranking.sort_by(|(_, a), (_, b)| {
+            let requests_sec = |r: &Option<&WrkResult>| r.map_or(f64::MIN, |r| *r.requests_sec());
+            requests_sec(b).total_cmp(&requests_sec(a))
+        });
+        ranking
+    }
+
+    /// Paired comparison of the first two targets for every benchmark configuration, pairing
+    /// their runs in the order they were executed. Pairs with a failed run are skipped.
+    pub fn paired(&self) -> Vec<Paired> {
+        let [(_, a), (_, b), ..] = self.targets.as_slice() else {
+            return Vec::new();
+        };
+        let mut configs: Vec<Benchmark> = Vec::new();
+        for benchmark in a.iter().filter_map(|r| r.benchmark().as_ref()) {
+            if !configs.iter().any(|c| c.is_comparable(benchmark)) {
+                configs.push(benchmark.clone());
+            }
+        }
+        configs
+            .into_iter()
+            .filter_map(|benchmark| {
+                let runs = |results: &Benchmarks| -> Vec<WrkResult> {
+                    results
+                        .iter()
+                        .filter(|r| r.benchmark().as_ref().is_some_and(|b| b.is_comparable(&benchmark)))
+                        .cloned()
+                        .collect()
+                };
+                let (a, b) = (runs(a), runs(b));
+                let pairs: Vec<(f64, f64)> = a
+                    .iter()
+                    .zip(&b)
+                    .filter(|(a, b)| *a.success() && *b.success())
+                    .map(|(a, b)| (*a.requests_sec(), *b.requests_sec()))
+                    .collect();
+                Paired::new(benchmark, &pairs)
+            })
+            .collect()
+    }
+
+    /// Plot a bar chart of requests/sec grouped by benchmark configuration, with a bar per target.
+    #[cfg(feature = "plot")]
+    pub fn plot(&self, title: &str, output: &std::path::Path) -> crate::Result<()> {
+        let mut configs: Vec<Benchmark> = Vec::new();
+        for benchmark in self
+            .targets
+            .iter()
+            .flat_map(|(_, r)| r)
+            .filter_map(|r| r.benchmark().as_ref())
+        {
+            if !configs.iter().any(|c| c.is_comparable(benchmark)) {
+                configs.push(benchmark.clone());
+            }
+        }
+        let columns: Vec<_> = self.targets.iter().map(|(name, _)| name.clone()).collect();
+        let rows: Vec<_> = configs
+            .iter()
+            .map(|config| {
+                let values = self
+                    .targets
+                    .iter()
+                    .map(|(_, results)| {
+                        results
+                            .iter()
+                            .filter(|r| *r.success() && r.benchmark().as_ref().is_some_and(|b| b.is_comparable(config)))
+                            .map(|r| *r.requests_sec())
+                            .fold(0.0, f64::max)
+                    })
+                    .collect();
+                (config.name(), values)
+            })
+            .collect();
+        crate::Gnuplot::new(title, output).bars(&columns, &rows)
+    }
+}
+
+impl Paired {
+    fn new(benchmark: Benchmark, pairs: &[(f64, f64)]) -> Option<Self> {
+        if pairs.is_empty() {
+            return None;
+        }
+        let n = pairs.len() as f64;
+        let differences: Vec<f64> = pairs.iter().map(|(a, b)| b - a).collect();
+        let mean_difference = differences.iter().sum::<f64>() / n;
+        let mean_a = pairs.iter().map(|(a, _)| a).sum::<f64>() / n;
+        let stdev = if pairs.len() > 1 {
+            (differences.iter().map(|d| (d - mean_difference).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        let margin = t_critical(pairs.len() - 1) * stdev / n.sqrt();
+        Some(Self {
+            benchmark,
+            pairs: pairs.len(),
+            mean_difference,
+            relative_difference: mean_difference / mean_a * 100.0,
+            stdev,
+            significant: pairs.len() > 1 && mean_difference.abs() > margin,
+        })
+    }
+}
+
+/// Two-sided 95% critical value of the Student's t distribution with `df` degrees of freedom.
+fn t_critical(df: usize) -> f64 {
+    const TABLE: [f64; 10] = [12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228];
+    match df {
+        0 => f64::INFINITY,
+        1..=10 => TABLE[df - 1],
+        11..=20 => 2.086,
+        21..=30 => 2.042,
+        _ => 1.96,
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_BOX_CHARS);
+        table.set_titles(Row::new(
+            [
+                "Rank",
+                "Target",
+                "Requests/sec",
+                "Relative",
+                "Avg latency",
+                "P99 latency",
+                "Transfer/request",
+                "Errors",
+            ]
+            .iter()
+            .map(|title| Cell::new(title).with_style(Attr::Bold))
+            .collect(),
+        ));
+        let ranking = self.ranking();
+        let top = ranking
+            .first()
+            .and_then(|(_, best)| best.map(|r| *r.requests_sec()))
+            .unwrap_or_default();
+        for (rank, (name, best)) in ranking.iter().enumerate() {
+            let cells = match best {
+                Some(best) => vec![
+                    Cell::new(&(rank + 1).to_string()),
+                    Cell::new(name).with_style(Attr::Bold),
+                    Cell::new(&format!("{:.2}", best.requests_sec())),
+                    Cell::new(&format!("{:.2}%", best.requests_sec() / top * 100.0)),
+                    Cell::new(&format!("{:.2}ms", best.avg_latency_ms())),
+                    Cell::new(&format!("{:.2}ms", best.p99_latency_ms())),
+                    Cell::new(&format!("{:.2}KB", transfer_per_request(best))),
+                    Cell::new(&best.errors().to_string()),
+                ],
+                None => vec![
+                    Cell::new(&(rank + 1).to_string()),
+                    Cell::new(name).with_style(Attr::Bold),
+                    Cell::new("failed"),
+                    Cell::new("-"),
+                    Cell::new("-"),
+                    Cell::new("-"),
+                    Cell::new("-"),
+                    Cell::new("-"),
+                ],
+            };
+            table.add_row(Row::new(cells));
+        }
+        write!(f, "## Rust Wrk targets comparison:\n{}", table)?;
+        let paired = self.paired();
+        if !self.interleaved || paired.is_empty() {
+            return Ok(());
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_BOX_CHARS);
+        table.set_titles(Row::new(
+            ["Benchmark", "Pairs", "Requests/sec difference", "Stdev", "Significant"]
+                .iter()
+                .map(|title| Cell::new(title).with_style(Attr::Bold))
+                .collect(),
+        ));
+        for paired in paired {
+            table.add_row(Row::new(vec![
+                Cell::new(&paired.benchmark.name()).with_style(Attr::Bold),
+                Cell::new(&paired.pairs.to_string()),
+                Cell::new(&format!(
+                    "{:+.2} ({:+.2}%)",
+                    paired.mean_difference, paired.relative_difference
+                )),
+                Cell::new(&format!("{:.2}", paired.stdev)),
+                Cell::new(if paired.significant { "yes" } else { "no" }),
+            ]));
+        }
+        write!(
+            f,
+            "## Paired comparison of {} against {}:\n{}",
+            self.targets[1].0, self.targets[0].0, table
+        )
+    }
+}
+
+/// Average data transferred per request in kilobytes, IE: the cost of the response format.
+fn transfer_per_request(result: &WrkResult) -> f64 {
+    if *result.requests() > 0.0 {
+        result.transfer_mb() * 1024.0 / result.requests()
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::result_builder;
+
+    #[test]
+    fn ranking() {
+        let result =
+            |success: bool, requests_sec: f64| result_builder(requests_sec, 0.0).success(success).build().unwrap();
+        let comparison = Comparison::new(vec![
+            ("actix".to_string(), vec![result(true, 90.0), result(true, 110.0)]),
+            ("broken".to_string(), vec![result(false, 500.0)]),
+            ("axum".to_string(), vec![result(true, 120.0)]),
+        ]);
+        let ranking: Vec<_> = comparison
+            .ranking()
+            .into_iter()
+            .map(|(name, best)| (name, best.map(|r| *r.requests_sec())))
+            .collect();
+        assert_eq!(
+            ranking,
+            vec![("axum", Some(120.0)), ("actix", Some(110.0)), ("broken", None)]
+        );
+        assert!(comparison.to_string().contains("91.67%"));
+    }
+
+    #[test]
+    fn paired() {
+        let result =
+            |success: bool, requests_sec: f64| result_builder(requests_sec, 0.0).success(success).build().unwrap();
+        let comparison = Comparison::interleaved(vec![
+            (
+                "actix".to_string(),
+                vec![
+                    result(true, 100.0),
+                    result(true, 90.0),
+                    result(true, 110.0),
+                    result(false, 0.0),
+                ],
+            ),
+            (
+                "axum".to_string(),
+                vec![
+                    result(true, 105.0),
+                    result(true, 96.0),
+                    result(true, 114.0),
+                    result(true, 500.0),
+                ],
+            ),
+        ]);
+        let paired = comparison.paired();
+        assert_eq!(paired.len(), 1);
+        assert_eq!(paired[0].pairs, 3);
+        assert_eq!(paired[0].mean_difference, 5.0);
+        assert_eq!(paired[0].relative_difference, 5.0);
+        assert!(paired[0].significant);
+        assert!(comparison
+            .to_string()
+            .contains("Paired comparison of axum against actix"));
+
+        let noisy = Comparison::interleaved(vec![
+            ("actix".to_string(), vec![result(true, 100.0), result(true, 100.0)]),
+            ("axum".to_string(), vec![result(true, 130.0), result(true, 80.0)]),
+        ]);
+        assert!(!noisy.paired()[0].significant);
+        assert!(Comparison::new(vec![]).paired().is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+//! Classification of the messages wrk prints on stderr into actionable categories, reported in
+//! [`crate::WrkResult::diagnoses`].
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Known problem reported by wrk on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Diagnosis {
+    /// Connections to the target were refused or dropped.
+    Connect,
+    /// The host name of the target does not resolve.
+    Resolve,
+    /// The open files limit was reached while opening connections.
+    OpenFiles,
+    /// Wrk was unable to start its threads.
+    Threads,
+    /// The Lua script driving wrk failed.
+    Script,
+}
+
+impl Diagnosis {
+    /// Category of a single stderr `line`, if known.
+    pub fn classify(line: &str) -> Option<Self> {
+        let line = line.to_ascii_lowercase();
+        if line.contains("too many open files") {
+            Some(Self::OpenFiles)
+        } else if line.contains("unable to resolve") || line.contains("name or service not known") {
+            Some(Self::Resolve)
+        } else if line.contains("unable to connect") || line.contains("connection refused") {
+            Some(Self::Connect)
+        } else if line.contains("unable to create thread") {
+            Some(Self::Threads)
+        } else if line.contains("panic: unprotected error") || line.contains(".lua:") {
+            Some(Self::Script)
+        } else {
+            None
+        }
+    }
+
+    /// What to do about it.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::Connect => "check the target is up and reachable from the load generator",
+            Self::Resolve => "check the host name of the URL",
+            Self::OpenFiles => "raise the open files limit (ulimit -n) or lower the connections",
+            Self::Threads => "lower the threads or raise the process limit (ulimit -u)",
+            Self::Script => "check the Lua script, IE: the request body, headers and checks",
+        }
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Connect => "unable to connect",
+            Self::Resolve => "unable to resolve host",
+            Self::OpenFiles => "too many open files",
+            Self::Threads => "unable to create threads",
+            Self::Script => "Lua script error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Categories of the lines of `stderr`, in order of appearance and without duplicates.
+pub(crate) fn diagnose(stderr: &str) -> Vec<Diagnosis> {
+    let mut diagnoses = Vec::new();
+    for diagnosis in stderr.lines().filter_map(Diagnosis::classify) {
+        if !diagnoses.contains(&diagnosis) {
+            diagnoses.push(diagnosis);
+        }
+    }
+    diagnoses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_stderr() {
+        let stderr = "unable to connect to localhost:8080 Connection refused\n\
+            unable to connect to localhost:8080 Connection refused\n\
+            unable to create socket: Too many open files\n\
+            something else entirely\n";
+        assert_eq!(diagnose(stderr), vec![Diagnosis::Connect, Diagnosis::OpenFiles]);
+        assert_eq!(
+            Diagnosis::classify("unable to resolve nope:80 Name or service not known"),
+            Some(Diagnosis::Resolve)
+        );
+        assert_eq!(
+            Diagnosis::classify("PANIC: unprotected error in call to Lua API (script.lua:3: boom)"),
+            Some(Diagnosis::Script)
+        );
+        assert!(diagnose("").is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+//! Encryption at rest of the history files with AES-256-GCM, keyed by the
+//! `WRK_API_BENCH_HISTORY_KEY` environment variable.
+//!
+//! When the key is set, dump files are encrypted and get an extra `.enc` extension. Encrypted
+//! files are decrypted transparently on load, so plain and encrypted history can coexist.
+use std::{env, path::Path};
+
+use crate::{Result, WrkError};
+
+/// Environment variable holding the key, 64 hexadecimal characters, IE: the output of
+/// `openssl rand -hex 32`.
+pub(crate) const KEY_VAR: &str = "WRK_API_BENCH_HISTORY_KEY";
+/// Extension appended to the name of encrypted history files.
+pub(crate) const EXTENSION: &str = "enc";
+/// Header of encrypted history files, followed by the nonce and the ciphertext.
+#[cfg(feature = "encryption")]
+const MAGIC: &[u8] = b"WRKENC1";
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 12;
+
+/// Key encrypting the history files.
+#[derive(Clone, PartialEq)]
+pub(crate) struct HistoryKey([u8; 32]);
+
+impl std::fmt::Debug for HistoryKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("HistoryKey(..)")
+    }
+}
+
+impl HistoryKey {
+    /// Read the key from the process environment, if set.
+    pub(crate) fn from_env() -> Result<Option<Self>> {
+        env::var(KEY_VAR)
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .map(|value| Self::from_hex(&value))
+            .transpose()
+    }
+
+    fn from_hex(value: &str) -> Result<Self> {
+        let invalid = || WrkError::Config(format!("{} must be 64 hexadecimal characters", KEY_VAR));
+        if value.len() != 64 || !value.is_ascii() {
+            return Err(invalid());
+        }
+        let mut key = [0; 32];
+        for (byte, pair) in key.iter_mut().zip(value.as_bytes().chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(key))
+    }
+
+    /// Encrypt `data` with a random nonce.
+    #[cfg(feature = "encryption")]
+    pub(crate) fn seal(&self, data: &[u8]) -> Result<Vec<u8>> {
+        use aes_gcm::{
+            aead::{Aead, OsRng},
+            AeadCore, Aes256Gcm, KeyInit,
+        };
+        let cipher = Aes256Gcm::new(&self.0.into());
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, data)
+            .map_err(|_| WrkError::Format("Unable to encrypt history".to_string()))?;
+        Ok([MAGIC, nonce.as_slice(), &ciphertext].concat())
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    pub(crate) fn seal(&self, _data: &[u8]) -> Result<Vec<u8>> {
+        Err(disabled())
+    }
+
+    /// Decrypt `data` written by [`HistoryKey::seal`].
+    #[cfg(feature = "encryption")]
+    pub(crate) fn open(&self, data: &[u8]) -> Result<Vec<u8>> {
+        use aes_gcm::{aead::Aead, Aes256Gcm, KeyInit, Nonce};
+        let invalid = || WrkError::Format(format!("Unable to decrypt history, is {} correct?", KEY_VAR));
+        let data = data
+            .strip_prefix(MAGIC)
+            .filter(|d| d.len() >= NONCE_LEN)
+            .ok_or_else(invalid)?;
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        Aes256Gcm::new(&self.0.into())
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| invalid())
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    pub(crate) fn open(&self, _data: &[u8]) -> Result<Vec<u8>> {
+        Err(disabled())
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+fn disabled() -> WrkError {
+    WrkError::Config(format!(
+        "{} is set but wrk-api-bench was built without the encryption feature",
+        KEY_VAR
+    ))
+}
+
+/// Whether the history file at `path` is encrypted.
+pub(crate) fn is_encrypted(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == EXTENSION)
+}
+
+/// Decrypt the content of the history file `path` if it is encrypted.
+pub(crate) fn decode(path: &Path, data: Vec<u8>) -> Result<Vec<u8>> {
+    if !is_encrypted(path) {
+        return Ok(data);
+    }
+    HistoryKey::from_env()?
+        .ok_or_else(|| WrkError::Config(format!("{} is encrypted, set {} to read it", path.display(), KEY_VAR)))?
+        .open(&data)
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seal_and_open() {
+        let key = HistoryKey::from_hex(&"ab".repeat(32)).unwrap();
+        let sealed = key.seal(b"[]").unwrap();
+        assert_ne!(&sealed[MAGIC.len() + NONCE_LEN..], b"[]");
+        assert_eq!(key.open(&sealed).unwrap(), b"[]");
+        let other = HistoryKey::from_hex(&"cd".repeat(32)).unwrap();
+        assert!(other.open(&sealed).is_err());
+        assert!(HistoryKey::from_hex("abc").is_err());
+        assert!(is_encrypted(Path::new("result.2024-01-01-00:00:00-+0000.json.enc")));
+    }
+}
@@ -1,37 +1,68 @@
 use thiserror::Error;
 
+/// Errors returned by this crate.
 #[derive(Error, Debug)]
 pub enum WrkError {
     // Custom errors
+    /// Wrk execution error.
     #[error("Execution error: {0}")]
     Exec(String),
     // Custom errors
+    /// Historical data loading or storing error.
     #[error("History error: {0}")]
     History(String),
+    /// Bitmap plotting error.
     #[error("Bitmap plotting error: {0}")]
     Bitmap(String),
     // Custom errors
+    /// Lua script rendering error.
     #[error("Lua error: {0}")]
     Lua(String),
     // Custom errors
+    /// Gnuplot plotting error.
     #[error("Plot error: {0}")]
     Plot(String),
+    /// History file format encoding or decoding error.
+    #[error("Format error: {0}")]
+    Format(String),
+    /// Statistics calculation error.
     #[error("Statistics error: {0}")]
     Stats(String),
+    /// Performance regression beyond the allowed threshold.
+    #[error("Regression: {0}")]
+    Regression(String),
+    /// Invalid configuration, IE: a malformed environment override.
+    #[error("Configuration error: {0}")]
+    Config(String),
+    /// Code host API error, IE: a rejected pull request comment.
+    #[error("Code host error: {0}")]
+    CodeHost(String),
+    /// I/O error.
     #[error(transparent)]
     Io(#[from] std::io::Error),
+    /// JSON (de)serialization error.
     #[error(transparent)]
     Serde(#[from] serde_json::Error),
+    /// Date parsing error.
     #[error(transparent)]
     Chrono(#[from] chrono::ParseError),
+    /// Url parsing error.
     #[error(transparent)]
     Url(#[from] url::ParseError),
+    /// Temporary file persisting error.
     #[error(transparent)]
     Tempfile(#[from] tempfile::PersistError),
+    /// Bundle archive error.
+    #[cfg(feature = "bundle")]
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+    /// [`crate::Wrk`] builder error.
     #[error(transparent)]
     WrkBuilder(#[from] crate::wrk::WrkBuilderError),
+    /// [`crate::WrkResult`] builder error.
     #[error(transparent)]
     WrkResultBuilder(#[from] crate::result::WrkResultBuilderError),
+    /// [`crate::Benchmark`] builder error.
     #[error(transparent)]
     BenchmarkBuilder(#[from] crate::benchmark::BenchmarkBuilderError),
 }
@@ -0,0 +1,322 @@
+//! Hierarchy of benchmark suites, IE: one group per service holding a suite per endpoint, so a
+//! single repository can track the performance of several services in an organized way.
+use std::{collections::HashSet, fmt};
+
+use crate::table::{format, Attr, Cell, Row, Table};
+
+use crate::{wrk::slug, BenchSession, Benchmark, Benchmarks, Metric, Rank, Result, Wrk, WrkError, WrkResult};
+
+/// Named, ordered collection of suites and nested groups. Every suite is recorded in the
+/// history namespace made of the slugs of its groups and of its own name, IE: `payments/v2/refunds`,
+/// replacing the project of its configuration.
+#[derive(Debug, Clone)]
+pub struct Group {
+    name: String,
+    members: Vec<Member>,
+}
+
+#[derive(Debug, Clone)]
+enum Member {
+    Suite {
+        name: String,
+        wrk: Box<Wrk>,
+        benchmarks: Vec<Benchmark>,
+    },
+    Group(Group),
+}
+
+impl Member {
+    fn name(&self) -> &str {
+        match self {
+            Self::Suite { name, .. } => name,
+            Self::Group(group) => &group.name,
+        }
+    }
+}
+
+impl Group {
+    /// Empty group called `name`.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            members: Vec::new(),
+        }
+    }
+
+    /// Name of the group.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Append a suite running `benchmarks` with the `wrk` configuration.
+    pub fn suite(mut self, name: &str, wrk: Wrk, benchmarks: Vec<Benchmark>) -> Self {
+        self.members.push(Member::Suite {
+            name: name.to_string(),
+            wrk: Box::new(wrk),
+            benchmarks,
+        });
+        self
+    }
+
+    /// Append a nested group.
+    pub fn group(mut self, group: Group) -> Self {
+        self.members.push(Member::Group(group));
+        self
+    }
+
+    /// History namespaces of every suite, in the order they run.
+    pub fn namespaces(&self) -> Vec<String> {
+        let mut namespaces = Vec::new();
+        self.walk(&slug(&self.name), &mut |namespace| namespaces.push(namespace));
+        namespaces
+    }
+
+    fn walk(&self, prefix: &str, visit: &mut dyn FnMut(String)) {
+        for member in &self.members {
+            match member {
+                Member::Suite { name, .. } => visit(format!("{}/{}", prefix, slug(name))),
+                Member::Group(group) => group.walk(&format!("{}/{}", prefix, slug(&group.name)), visit),
+            }
+        }
+    }
+
+    /// Fail when two members of the same group share a history namespace.
+    fn validate(&self) -> Result<()> {
+        let mut seen = HashSet::new();
+        for member in &self.members {
+            if slug(member.name()).is_empty() || !seen.insert(slug(member.name())) {
+                return Err(WrkError::Config(format!(
+                    "Group {} has an empty or duplicate member name: {}",
+                    self.name,
+                    member.name()
+                )));
+            }
+            if let Member::Group(group) = member {
+                group.validate()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run every suite in order, recording each of them in its own history namespace according
+    /// to its [`crate::RecordPolicy`].
+    pub fn run(&self) -> Result<GroupSummary> {
+        if slug(&self.name).is_empty() {
+            return Err(WrkError::Config("Group name is empty".to_string()));
+        }
+        self.validate()?;
+        self.run_in(&slug(&self.name))
+    }
+
+    fn run_in(&self, prefix: &str) -> Result<GroupSummary> {
+        let mut summary = GroupSummary {
+            name: self.name.clone(),
+            suites: Vec::new(),
+            groups: Vec::new(),
+        };
+        for member in &self.members {
+            match member {
+                Member::Suite { name, wrk, benchmarks } => {
+                    let mut wrk = Wrk::clone(wrk);
+                    wrk.set_project(Some(format!("{}/{}", prefix, slug(name))));
+                    let mut session = BenchSession::from(wrk);
+                    session.bench(benchmarks)?;
+                    summary.suites.push((name.clone(), session.benchmarks().clone()));
+                }
+                Member::Group(group) => {
+                    summary
+                        .groups
+                        .push(group.run_in(&format!("{}/{}", prefix, slug(&group.name)))?)
+                }
+            }
+        }
+        Ok(summary)
+    }
+}
+
+/// Results of a [`Group`], with the suites of every nested group.
+#[derive(Debug, Clone)]
+pub struct GroupSummary {
+    name: String,
+    suites: Vec<(String, Benchmarks)>,
+    groups: Vec<GroupSummary>,
+}
+
+impl GroupSummary {
+    /// Name of the group.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Results of the suites of this group, in the order they were run.
+    pub fn suites(&self) -> &[(String, Benchmarks)] {
+        &self.suites
+    }
+
+    /// Summaries of the nested groups.
+    pub fn groups(&self) -> &[GroupSummary] {
+        &self.groups
+    }
+
+    /// Results of every suite of this group and of its nested groups.
+    pub fn results(&self) -> Benchmarks {
+        self.suites
+            .iter()
+            .flat_map(|(_, results)| results.iter().cloned())
+            .chain(self.groups.iter().flat_map(|group| group.results()))
+            .collect()
+    }
+
+    /// Number of failed runs in this group and its nested groups.
+    pub fn failed(&self) -> usize {
+        self.results().iter().filter(|r| !*r.success()).count()
+    }
+
+    /// Successful result with the best requests/sec of this group and its nested groups.
+    pub fn best(&self) -> Option<WrkResult> {
+        best(&self.results()).cloned()
+    }
+
+    /// One row per suite and nested group: name, runs, failures and best result.
+    fn overview(&self) -> Vec<(String, usize, usize, Option<WrkResult>)> {
+        let row = |name: &str, results: &Benchmarks| {
+            (
+                name.to_string(),
+                results.len(),
+                results.iter().filter(|r| !*r.success()).count(),
+                best(results).cloned(),
+            )
+        };
+        self.suites
+            .iter()
+            .map(|(name, results)| row(name, results))
+            .chain(
+                self.groups
+                    .iter()
+                    .map(|group| row(&format!("{}/", group.name), &group.results())),
+            )
+            .collect()
+    }
+
+    /// Render the summary as GitHub flavoured markdown, a section per group and suite.
+    pub fn to_github_markdown(&self) -> String {
+        self.markdown(2)
+    }
+
+    fn markdown(&self, level: usize) -> String {
+        let heading = |level: usize| "#".repeat(level.min(6));
+        let mut result = format!("{} {}\\n", heading(level), self.name);
+        result += "|Suite|Runs|Failed|Best requests/sec|P99 latency (ms)|\\n|-|-|-|-|-|\\n";
+        for (name, runs, failed, best) in self.overview() {
+            let (requests_sec, p99) = best.map_or(("-".to_string(), "-".to_string()), |r| {
+                (format!("{:.2}", r.requests_sec()), format!("{:.2}", r.p99_latency_ms()))
+            });
+            result += &format!("|{}|{}|{}|{}|{}|\\n", name, runs, failed, requests_sec, p99);
+        }
+        for (name, results) in &self.suites {
+            result += &format!(
+                "{} {}\\n{}",
+                heading(level + 1),
+                name,
+                results.rank_by(Metric::RequestsSec).to_github_markdown()
+            );
+        }
+        for group in &self.groups {
+            result += &group.markdown(level + 1);
+        }
+        result
+    }
+
+    fn display(&self, f: &mut fmt::Formatter, path: &str) -> fmt::Result {
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_BOX_CHARS);
+        table.set_titles(Row::new(
+            ["Suite", "Runs", "Failed", "Best requests/sec", "P99 latency"]
+                .iter()
+                .map(|title| Cell::new(title).with_style(Attr::Bold))
+                .collect(),
+        ));
+        for (name, runs, failed, best) in self.overview() {
+            table.add_row(Row::new(vec![
+                Cell::new(&name),
+                Cell::new(&runs.to_string()),
+                Cell::new(&failed.to_string()),
+                Cell::new(
+                    &best
+                        .as_ref()
+                        .map_or("-".to_string(), |r| format!("{:.2}", r.requests_sec())),
+                ),
+                Cell::new(
+                    &best
+                        .as_ref()
+                        .map_or("-".to_string(), |r| format!("{:.2}ms", r.p99_latency_ms())),
+                ),
+            ]));
+        }
+        writeln!(f, "Group {}", path)?;
+        write!(f, "{}", table)?;
+        for group in &self.groups {
+            group.display(f, &format!("{}/{}", path, group.name))?;
+        }
+        Ok(())
+    }
+}
+
+fn best(results: &Benchmarks) -> Option<&WrkResult> {
+    results
+        .iter()
+        .filter(|r| *r.success())
+        .max_by(|a, b| a.requests_sec().total_cmp(b.requests_sec()))
+}
+
+impl fmt::Display for GroupSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.display(f, &self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::CorpusRunner, HistoryPeriod, HistoryStore, WrkBuilder};
+
+    #[test]
+    fn nested_groups() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13740".to_string())
+            .history_store(HistoryStore::in_memory())
+            .runner(CorpusRunner)
+            .build()
+            .unwrap();
+        let group = Group::new("Payments")
+            .suite("Refunds", wrk.clone(), vec![Benchmark::new(1, 1, 1)])
+            .group(Group::new("v2").suite(
+                "Charges",
+                wrk.clone(),
+                vec![Benchmark::new(1, 1, 1), Benchmark::new(1, 2, 1)],
+            ));
+        assert_eq!(group.namespaces(), vec!["payments/refunds", "payments/v2/charges"]);
+
+        let summary = group.run().unwrap();
+        assert_eq!(summary.suites().len(), 1);
+        assert_eq!(summary.groups()[0].suites()[0].1.len(), 2);
+        assert_eq!(summary.results().len(), 3);
+        assert_eq!(summary.failed(), 0);
+        assert!(summary.best().is_some());
+        let markdown = summary.to_github_markdown();
+        assert!(markdown.starts_with("## Payments\\n"));
+        assert!(markdown.contains("|v2/|2|0|"));
+        assert!(markdown.contains("#### Charges"));
+        assert!(summary.to_string().contains("Group Payments/v2"));
+
+        let mut charges = wrk.clone();
+        charges.set_project(Some("payments/v2/charges".to_string()));
+        assert_eq!(charges.history(&HistoryPeriod::Last, None).unwrap().len(), 2);
+
+        let duplicate =
+            Group::new("Payments")
+                .suite("Refunds", wrk.clone(), Vec::new())
+                .suite("refunds", wrk, Vec::new());
+        assert!(matches!(duplicate.run(), Err(WrkError::Config(_))));
+    }
+}
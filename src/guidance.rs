@@ -0,0 +1,139 @@
+//! Benchmarking best practices enforced before running a suite: long enough runs, with enough
+//! requests for stable statistics.
+use std::time::Duration;
+
+use getset::{Getters, Setters};
+use serde::{Deserialize, Serialize};
+
+use crate::{Benchmark, Result, WrkError};
+
+/// How violations of the [`Guidance`] are handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Enforcement {
+    /// Log a warning and run anyway.
+    #[default]
+    Warn,
+    /// Refuse to run the suite.
+    Reject,
+    /// Skip the checks.
+    Off,
+}
+
+/// Minimum duration and expected request count of every benchmark of a suite.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Getters, Setters, Builder)]
+pub struct Guidance {
+    /// Shortest acceptable benchmark duration.
+    #[builder(default = "Duration::from_secs(10)")]
+    #[serde(default = "default_min_duration")]
+    #[getset(get = "pub", set = "pub")]
+    min_duration: Duration,
+    /// Fewest acceptable requests per benchmark, expected from its [`Benchmark::rate`] or from
+    /// the best historical throughput of the same configuration.
+    #[builder(default = "10_000")]
+    #[serde(default = "default_min_requests")]
+    #[getset(get = "pub", set = "pub")]
+    min_requests: u64,
+    /// Handling of the violations.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub")]
+    enforcement: Enforcement,
+}
+
+fn default_min_duration() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_min_requests() -> u64 {
+    10_000
+}
+
+impl Default for Guidance {
+    fn default() -> Self {
+        Self {
+            min_duration: default_min_duration(),
+            min_requests: default_min_requests(),
+            enforcement: Enforcement::default(),
+        }
+    }
+}
+
+impl Guidance {
+    /// Check `benchmarks`, using `expected_requests_sec` to estimate their throughput when
+    /// they have no constant rate. Violations are logged, or returned as a
+    /// [`WrkError::Config`] with [`Enforcement::Reject`].
+    pub(crate) fn check<F>(&self, benchmarks: &[Benchmark], expected_requests_sec: F) -> Result<()>
+    where
+        F: Fn(&Benchmark) -> Option<f64>,
+    {
+        if self.enforcement == Enforcement::Off {
+            return Ok(());
+        }
+        let violations = self.violations(benchmarks, expected_requests_sec);
+        match self.enforcement {
+            Enforcement::Reject if !violations.is_empty() => Err(WrkError::Config(violations.join(", "))),
+            _ => {
+                for violation in violations {
+                    warn!("{}", violation);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn violations<F>(&self, benchmarks: &[Benchmark], expected_requests_sec: F) -> Vec<String>
+    where
+        F: Fn(&Benchmark) -> Option<f64>,
+    {
+        let mut violations = Vec::new();
+        for benchmark in benchmarks {
+            if *benchmark.duration() < self.min_duration {
+                violations.push(format!(
+                    "Benchmark {} lasts {}s, less than the minimum of {}s",
+                    benchmark.key(),
+                    benchmark.duration().as_secs(),
+                    self.min_duration.as_secs()
+                ));
+            }
+            let requests_sec = benchmark
+                .rate()
+                .map(f64::from)
+                .or_else(|| expected_requests_sec(benchmark));
+            if let Some(requests_sec) = requests_sec {
+                let expected = requests_sec * benchmark.duration().as_secs_f64();
+                if expected < self.min_requests as f64 {
+                    violations.push(format!(
+                        "Benchmark {} is expected to send {:.0} requests, less than the minimum of {} for stable statistics",
+                        benchmark.key(),
+                        expected,
+                        self.min_requests
+                    ));
+                }
+            }
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enforce_guidance() {
+        let guidance = GuidanceBuilder::default()
+            .enforcement(Enforcement::Reject)
+            .build()
+            .unwrap();
+        let long = Benchmark::new(1, 1, 30);
+        assert!(guidance.check(std::slice::from_ref(&long), |_| Some(1000.0)).is_ok());
+        assert!(guidance.check(std::slice::from_ref(&long), |_| None).is_ok());
+        // 100 requests/sec for 30 seconds.
+        let Err(WrkError::Config(message)) = guidance.check(&[long], |_| Some(100.0)) else {
+            panic!("expected a violation");
+        };
+        assert!(message.contains("expected to send 3000 requests"), "{}", message);
+        assert!(guidance.check(&[Benchmark::new(1, 1, 5)], |_| None).is_err());
+        assert!(Guidance::default().check(&[Benchmark::new(1, 1, 5)], |_| None).is_ok());
+    }
+}
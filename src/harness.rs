@@ -0,0 +1,233 @@
+//! `libtest` compatible shim to declare HTTP benchmarks under `benches/` and run them through
+//! `cargo bench`, with results flowing into the normal history store.
+//!
+//! Declare the bench target with `harness = false` in `Cargo.toml` and list the benchmark
+//! functions in [`crate::harness!`]:
+//!
+//! ```ignore
+//! use wrk_api_bench::{harness::Bencher, Benchmark, WrkBuilder};
+//!
+//! fn hello(b: &mut Bencher) {
+//!     let wrk = WrkBuilder::default().url("http://localhost:8080/hello".to_string()).build().unwrap();
+//!     b.bench(wrk, &[Benchmark::new(2, 32, 10)]);
+//! }
+//!
+//! wrk_api_bench::harness!(hello);
+//! ```
+use std::{
+    net::TcpStream,
+    process, thread,
+    time::{Duration, Instant},
+};
+
+use url::Url;
+
+use crate::{BenchSession, Benchmark, HistoryPeriod, Result, Wrk, WrkBuilder, WrkError};
+
+/// How long [`regression_gate`] waits for the target to accept connections.
+pub(crate) const READINESS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Benchmark function declared in [`crate::harness!`].
+pub type BenchFn = fn(&mut Bencher);
+
+/// Handle passed to every benchmark function to run its benchmarks.
+#[derive(Debug, Default)]
+pub struct Bencher {
+    errors: Vec<String>,
+}
+
+impl Bencher {
+    /// Run `benchmarks` against `wrk`, recording them according to its
+    /// [`crate::RecordPolicy`] and printing the variance against the last recorded run.
+    pub fn bench(&mut self, wrk: Wrk, benchmarks: &[Benchmark]) {
+        if let Err(e) = Self::run(wrk, benchmarks) {
+            self.errors.push(e.to_string());
+        }
+    }
+
+    fn run(wrk: Wrk, benchmarks: &[Benchmark]) -> Result<()> {
+        if benchmarks.is_empty() {
+            return Err(WrkError::Config("No benchmarks registered".to_string()));
+        }
+        let mut session = BenchSession::from(wrk);
+        session.bench(benchmarks)?;
+        if session.benchmarks().iter().all(|r| !*r.success()) {
+            return Err(WrkError::Exec("All benchmarks failed".to_string()));
+        }
+        match session.variance(HistoryPeriod::Last) {
+            Ok(variance) => println!("{}", variance),
+            Err(e) => println!("No variance available: {}", e),
+        }
+        Ok(())
+    }
+}
+
+/// Block until `url` accepts TCP connections or `timeout` expires.
+pub(crate) fn wait_ready(url: &Url, timeout: Duration) -> Result<()> {
+    let addrs = url.socket_addrs(|| None)?;
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if addrs
+            .iter()
+            .any(|addr| TcpStream::connect_timeout(addr, Duration::from_millis(100)).is_ok())
+        {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    Err(WrkError::Exec(format!("Target {} not ready after {:?}", url, timeout)))
+}
+
+/// Wait for `url` to be ready, run `benchmark` against it and fail with
+/// [`WrkError::Regression`] when requests/sec or p99 latency regressed by more than
+/// `max_regression` percent against the last recorded run. The first run always passes.
+///
+/// This is the runtime of the `#[wrk_benchmark]` attribute of the `macros` feature.
+pub fn regression_gate(url: &str, benchmark: Benchmark, max_regression: f64) -> Result<()> {
+    wait_ready(&Url::parse(url)?, READINESS_TIMEOUT)?;
+    let mut session = BenchSession::from(WrkBuilder::default().url(url.to_string()).build()?);
+    session.bench(&[benchmark])?;
+    if !session.benchmarks().iter().any(|r| *r.success()) {
+        return Err(WrkError::Exec(format!("Benchmark of {} failed", url)));
+    }
+    match session.variance(HistoryPeriod::Last) {
+        Ok(variance) => {
+            println!("{}", variance);
+            variance.check_regression(max_regression)
+        }
+        Err(WrkError::History(e)) => {
+            info!("No history to compare with, regression gate skipped: {}", e);
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Command line options passed by cargo to the bench target.
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    bench: bool,
+    list: bool,
+    exact: bool,
+    filter: Option<String>,
+}
+
+impl Options {
+    fn parse<I: IntoIterator<Item = String>>(args: I) -> Self {
+        let mut options = Self::default();
+        for arg in args {
+            match arg.as_str() {
+                "--bench" => options.bench = true,
+                "--list" => options.list = true,
+                "--exact" => options.exact = true,
+                arg if arg.starts_with('-') => debug!("Ignoring unsupported argument {}", arg),
+                filter => options.filter = Some(filter.to_string()),
+            }
+        }
+        options
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match &self.filter {
+            Some(filter) if self.exact => name == filter,
+            Some(filter) => name.contains(filter.as_str()),
+            None => true,
+        }
+    }
+}
+
+/// Run the declared benchmarks, following the `libtest` command line conventions. Use
+/// [`crate::harness!`] instead of calling this directly.
+pub fn run(benches: &[(&str, BenchFn)]) {
+    let options = Options::parse(std::env::args().skip(1));
+    if options.list {
+        for (name, _) in benches {
+            println!("{}: bench", name);
+        }
+        return;
+    }
+    let selected: Vec<_> = benches.iter().filter(|(name, _)| options.matches(name)).collect();
+    let filtered = benches.len() - selected.len();
+    println!("\nrunning {} benchmarks", selected.len());
+    let (mut passed, mut failed, mut ignored) = (0, 0, 0);
+    for (name, bench) in selected {
+        // `cargo test` runs bench targets without `--bench`, HTTP benchmarks are too slow for it.
+        if !options.bench {
+            println!("bench {} ... ignored", name);
+            ignored += 1;
+            continue;
+        }
+        let mut bencher = Bencher::default();
+        bench(&mut bencher);
+        if bencher.errors.is_empty() {
+            println!("bench {} ... ok", name);
+            passed += 1;
+        } else {
+            println!("bench {} ... FAILED: {}", name, bencher.errors.join(", "));
+            failed += 1;
+        }
+    }
+    println!(
+        "\nbench result: {}. {} passed; {} failed; {} ignored; {} filtered out\n",
+        if failed == 0 { "ok" } else { "FAILED" },
+        passed,
+        failed,
+        ignored,
+        filtered
+    );
+    if failed > 0 {
+        process::exit(101);
+    }
+}
+
+/// Generate the `main` function of a `harness = false` bench target running the given
+/// benchmark functions, each taking a `&mut` [`harness::Bencher`](crate::harness::Bencher).
+#[macro_export]
+macro_rules! harness {
+    ($($bench:path),+ $(,)?) => {
+        fn main() {
+            $crate::harness::run(&[$((stringify!($bench), $bench as $crate::harness::BenchFn)),+]);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_options() {
+        let args = ["--bench", "--exact", "hello", "--nocapture"].map(String::from);
+        let options = Options::parse(args);
+        assert_eq!(
+            options,
+            Options {
+                bench: true,
+                list: false,
+                exact: true,
+                filter: Some("hello".to_string())
+            }
+        );
+        assert!(options.matches("hello"));
+        assert!(!options.matches("hello_world"));
+        assert!(Options::parse(["hell".to_string()]).matches("hello_world"));
+    }
+
+    #[test]
+    fn no_benchmarks() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13746".to_string())
+            .history_store(crate::HistoryStore::in_memory())
+            .build()
+            .unwrap();
+        assert!(matches!(Bencher::run(wrk.clone(), &[]), Err(WrkError::Config(_))));
+        let mut bencher = Bencher::default();
+        bencher.bench(wrk, &[]);
+        assert_eq!(bencher.errors.len(), 1);
+        assert!(
+            bencher.errors[0].contains("No benchmarks registered"),
+            "{}",
+            bencher.errors[0]
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Validation, Lua escaping and redaction of HTTP headers.
+use http::header::{HeaderName, HeaderValue};
+
+use crate::Headers;
+
+/// Value replacing the redacted headers in persisted configurations and results.
+pub(crate) const REDACTED: &str = "[REDACTED]";
+/// Prefix of the environment variables passing redacted header values to wrk.
+const SECRET_PREFIX: &str = "WRK_API_BENCH_HEADER_";
+
+/// Headers redacted by default: credentials commonly sent by API clients.
+pub(crate) fn default_redacted() -> Vec<String> {
+    ["Authorization", "Cookie", "X-Api-Key"].map(str::to_string).to_vec()
+}
+
+/// Whether the header `name` is in the `denylist`, ignoring case.
+pub(crate) fn is_redacted(name: &str, denylist: &[String]) -> bool {
+    denylist.iter().any(|denied| denied.eq_ignore_ascii_case(name))
+}
+
+/// Copy of `headers` with the values of the headers in the `denylist` redacted.
+pub(crate) fn redact(headers: &Headers, denylist: &[String]) -> Headers {
+    headers
+        .iter()
+        .map(|(name, value)| match is_redacted(name, denylist) {
+            true => (name.clone(), REDACTED.to_string()),
+            false => (name.clone(), value.clone()),
+        })
+        .collect()
+}
+
+/// Environment variable passing the value of the redacted header `name` to the Lua script,
+/// IE: `WRK_API_BENCH_HEADER_X_API_KEY` for `X-Api-Key`.
+pub(crate) fn secret_env(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}{}", SECRET_PREFIX, name)
+}
+
+/// Check every header name is a valid token and every value is free of control characters,
+/// which would corrupt the generated Lua script or the request.
+pub(crate) fn validate(headers: &Headers) -> std::result::Result<(), String> {
+    for (name, value) in headers {
+        HeaderName::from_bytes(name.as_bytes()).map_err(|_| format!("Invalid HTTP header name {:?}", name))?;
+        HeaderValue::from_str(value).map_err(|_| format!("Invalid value {:?} for HTTP header {}", value, name))?;
+    }
+    Ok(())
+}
+
+/// Quote `value` as a Lua string literal.
+pub(crate) fn lua_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\{:03}", c as u8)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_and_escape() {
+        let valid = Headers::from([("X-Quote".to_string(), r#"say "hi" \o/"#.to_string())]);
+        assert!(validate(&valid).is_ok());
+        assert_eq!(lua_string(r#"say "hi" \o/"#), r#""say \"hi\" \\o/""#);
+        // A decimal escape followed by a digit must not swallow it: "\91" would be `[`.
+        assert_eq!(lua_string("a\t1\x7f2"), r#""a\0091\1272""#);
+        let name = Headers::from([("X Bad".to_string(), "value".to_string())]);
+        assert!(validate(&name).is_err());
+        let value = Headers::from([("X-Injected".to_string(), "a\nwrk.method = \"DELETE\"".to_string())]);
+        assert!(validate(&value).is_err());
+    }
+
+    #[test]
+    fn redact_denied_headers() {
+        let headers = Headers::from([
+            ("authorization".to_string(), "Bearer token".to_string()),
+            ("Accept".to_string(), "application/json".to_string()),
+        ]);
+        let redacted = redact(&headers, &default_redacted());
+        assert_eq!(redacted["authorization"], REDACTED);
+        assert_eq!(redacted["Accept"], "application/json");
+        assert_eq!(secret_env("X-Api-Key"), "WRK_API_BENCH_HEADER_X_API_KEY");
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, FixedOffset, Utc};
+#[cfg(feature = "gzip")]
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::{encryption, Benchmarks, Result, WrkError};
+
+pub(crate) const DATE_FORMAT: &str = "%Y-%m-%d-%H:%M:%S-%z";
+pub(crate) const PREFIX: &str = "result.";
+
+/// Serialization format of the history dump files.
+///
+/// Files are always read according to their extension, so the format can be changed without
+/// losing access to the existing history. Formats other than JSON require the feature of the
+/// same name, IE: `cbor`, `msgpack` or `gzip`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum HistoryFormat {
+    /// Plain JSON, `.json` extension.
+    #[default]
+    Json,
+    /// CBOR, `.cbor` extension.
+    Cbor,
+    /// MessagePack, `.msgpack` extension.
+    MessagePack,
+    /// Gzip compressed JSON, `.json.gz` extension.
+    GzipJson,
+}
+
+impl HistoryFormat {
+    const ALL: [HistoryFormat; 4] = [Self::Json, Self::Cbor, Self::MessagePack, Self::GzipJson];
+
+    /// File extension of this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Cbor => "cbor",
+            Self::MessagePack => "msgpack",
+            Self::GzipJson => "json.gz",
+        }
+    }
+
+    /// Detect the format of a history file from its extension, ignoring the `.enc` extension
+    /// of encrypted files.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let filename = path.file_name()?.to_string_lossy();
+        let filename = filename
+            .strip_suffix(&format!(".{}", encryption::EXTENSION))
+            .unwrap_or(&filename);
+        Self::ALL
+            .into_iter()
+            .find(|format| filename.ends_with(&format!(".{}", format.extension())))
+    }
+
+    /// Feature required to read and write this format, `None` when always available.
+    fn feature(&self) -> Option<&'static str> {
+        match self {
+            Self::Json => None,
+            Self::Cbor => Some("cbor"),
+            Self::MessagePack => Some("msgpack"),
+            Self::GzipJson => Some("gzip"),
+        }
+    }
+
+    /// Error reading or writing this format without its feature.
+    fn disabled(&self) -> WrkError {
+        WrkError::Config(format!(
+            "History format {:?} requires the {} feature",
+            self,
+            self.feature().unwrap_or_default()
+        ))
+    }
+
+    pub(crate) fn write<W: Write>(&self, writer: W, benchmarks: &Benchmarks) -> Result<()> {
+        let mut writer = BufWriter::new(writer);
+        match self {
+            Self::Json => serde_json::to_writer(&mut writer, benchmarks)?,
+            #[cfg(feature = "cbor")]
+            Self::Cbor => {
+                ciborium::ser::into_writer(benchmarks, &mut writer).map_err(|e| WrkError::Format(e.to_string()))?
+            }
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => {
+                rmp_serde::encode::write_named(&mut writer, benchmarks).map_err(|e| WrkError::Format(e.to_string()))?
+            }
+            #[cfg(feature = "gzip")]
+            Self::GzipJson => {
+                let mut encoder = GzEncoder::new(&mut writer, Compression::default());
+                serde_json::to_writer(&mut encoder, benchmarks)?;
+                encoder.finish()?;
+            }
+            #[allow(unreachable_patterns)]
+            format => return Err(format.disabled()),
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub(crate) fn read<R: Read>(&self, reader: R) -> Result<Benchmarks> {
+        let mut reader = BufReader::new(reader);
+        Ok(match self {
+            Self::Json => serde_json::from_reader(&mut reader)?,
+            #[cfg(feature = "cbor")]
+            Self::Cbor => ciborium::de::from_reader(&mut reader).map_err(|e| WrkError::Format(e.to_string()))?,
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => rmp_serde::from_read(&mut reader).map_err(|e| WrkError::Format(e.to_string()))?,
+            #[cfg(feature = "gzip")]
+            Self::GzipJson => serde_json::from_reader(GzDecoder::new(&mut reader))?,
+            #[allow(unreachable_patterns)]
+            format => return Err(format.disabled()),
+        })
+    }
+}
+
+/// Name of the history file for a run started at `date`.
+pub(crate) fn filename(date: &DateTime<Utc>, format: HistoryFormat) -> String {
+    format!("{}{}.{}", PREFIX, date.format(DATE_FORMAT), format.extension())
+}
+
+/// Parse the date and format of a history file from its name.
+pub(crate) fn parse_filename(path: &Path) -> Option<(DateTime<FixedOffset>, HistoryFormat)> {
+    let filename = path.file_name()?.to_string_lossy();
+    let date = filename.strip_prefix(PREFIX)?.split('.').next()?;
+    let date = DateTime::parse_from_str(date, DATE_FORMAT).ok()?;
+    Some((date, HistoryFormat::from_path(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WrkResultBuilder;
+
+    #[test]
+    fn formats_round_trip() {
+        let benchmarks = vec![WrkResultBuilder::default()
+            .success(true)
+            .requests_sec(123.45)
+            .build()
+            .unwrap()];
+        for format in HistoryFormat::ALL {
+            let features = [
+                cfg!(feature = "cbor").then_some("cbor"),
+                cfg!(feature = "msgpack").then_some("msgpack"),
+                cfg!(feature = "gzip").then_some("gzip"),
+            ];
+            let enabled = format.feature().is_none_or(|feature| features.contains(&Some(feature)));
+            let mut buffer = Vec::new();
+            if !enabled {
+                assert!(matches!(
+                    format.write(&mut buffer, &benchmarks),
+                    Err(WrkError::Config(_))
+                ));
+                assert!(matches!(format.read(buffer.as_slice()), Err(WrkError::Config(_))));
+                continue;
+            }
+            format.write(&mut buffer, &benchmarks).unwrap();
+            assert_eq!(format.read(buffer.as_slice()).unwrap(), benchmarks);
+            let date = Utc::now();
+            let name = filename(&date, format);
+            let (parsed, parsed_format) = parse_filename(Path::new(&name)).unwrap();
+            assert_eq!(parsed.timestamp(), date.timestamp());
+            assert_eq!(parsed_format, format);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+//! Library to perform HTTP benchmarks using [wrk](https://github.com/wg/wrk) and produce useful
+//! performance regression information.
 #![warn(missing_docs, missing_debug_implementations)]
 
 #[macro_use]
@@ -5,18 +7,112 @@ extern crate derive_builder;
 #[macro_use]
 extern crate log;
 
+mod audit;
 mod benchmark;
+#[cfg(feature = "bundle")]
+mod bundle;
+mod chart;
+mod clock;
+#[cfg(feature = "codehost")]
+pub mod codehost;
+mod command;
+mod comparison;
+mod diagnosis;
+mod encryption;
 mod error;
+mod group;
+mod guidance;
+pub mod harness;
+mod headers;
+mod history;
 mod lua;
+mod method;
+mod metric;
+mod multipart;
+#[cfg(feature = "native")]
+mod native;
+mod number;
+mod output;
+mod overrides;
+mod periods;
+#[cfg(feature = "plot")]
 mod plot;
+mod preflight;
+mod preset;
+mod processor;
+mod profile;
+mod ranking;
+mod response;
 mod result;
+mod rollup;
+mod runner;
+mod sanity;
+#[cfg(feature = "daemon")]
+mod schedule;
+#[cfg(feature = "schema")]
+pub mod schema;
+mod score;
+#[cfg(feature = "serve")]
+pub mod serve;
+mod session;
+mod status;
+mod store;
+mod suite;
+mod table;
+mod target;
+#[cfg(test)]
+mod test_utils;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod thresholds;
+mod timeseries;
+#[cfg(feature = "tui")]
+pub mod tui;
+mod watch;
+mod window;
 mod wrk;
+mod wrk2;
 
+pub use audit::AuditEntry;
 pub use benchmark::{Benchmark, BenchmarkBuilder, BenchmarkBuilderError};
+pub use chart::TextChart;
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use command::CommandSpec;
+pub use comparison::{Comparison, Paired};
+pub use diagnosis::Diagnosis;
 pub use error::WrkError;
+pub use group::{Group, GroupSummary};
+pub use guidance::{Enforcement, Guidance, GuidanceBuilder, GuidanceBuilderError};
+pub use history::HistoryFormat;
+pub use http::Method;
 pub use lua::LuaScript;
+pub use metric::Metric;
+pub use multipart::{Multipart, Part};
+pub use number::NumberFormat;
+pub use periods::MultiVariance;
+#[cfg(feature = "plot")]
 pub use plot::Gnuplot;
-pub use result::{WrkResult, WrkResultBuilder, WrkResultBuilderError};
-pub use wrk::{Benchmarks, Headers, HistoryPeriod, Wrk, WrkBuilder, WrkBuilderError};
+pub use preset::Profile;
+pub use processor::{ResultProcessor, SuccessfulOnly};
+pub use profile::LoadProfile;
+pub use ranking::{Leaderboard, Rank, Ranked};
+pub use response::ResponseCheck;
+pub use result::{LatencyUnit, Variance, WrkResult, WrkResultBuilder, WrkResultBuilderError};
+pub use rollup::Rollup;
+pub use runner::{ProcessOutput, ProcessRunner, RunFuture, SystemRunner};
+#[cfg(feature = "daemon")]
+pub use schedule::Schedule;
+pub use score::{CompositeScore, CompositeScoreBuilder, CompositeScoreBuilderError};
+pub use session::BenchSession;
+pub use status::{RunSummary, SuiteStatus};
+pub use store::{HistoryStore, LocalStorage, MemoryStorage, Storage};
+pub use suite::SuiteSummary;
+pub use target::{DynamicTarget, Teardown};
+pub use thresholds::{MetricRule, Thresholds};
+pub use timeseries::{Sample, Stall, StallDetection};
+pub use window::VarianceWindow;
+pub use wrk::{Benchmarks, Engine, Headers, HistoryPeriod, RecordPolicy, StoredRun, Wrk, WrkBuilder, WrkBuilderError};
+#[cfg(feature = "macros")]
+pub use wrk_api_bench_macros::wrk_benchmark;
 
 pub(crate) type Result<T> = std::result::Result<T, WrkError>;
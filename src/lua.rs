@@ -1,28 +1,87 @@
 use std::{
     env,
-    fs::{self, File},
+    fs::File,
     io::{BufReader, Read, Write},
     path::{Path, PathBuf},
 };
 
-use assert_cmd::prelude::OutputOkExt;
-use getset::{Getters, MutGetters, Setters};
-use rslua::lexer::Lexer;
-use tempfile::NamedTempFile;
+use http::Method;
+#[cfg(feature = "lua-validate")]
+use rslua::{lexer::Lexer, parser::Parser};
 
-use crate::{Headers, Result, WrkError};
+use crate::{headers::lua_string, response, Headers, Multipart, ResponseCheck, Result, WrkError};
 
 const LUA_DEFAULT_DONE_FUNCTION: &str = r#"
 -- The done() function is called at the end of wrk execution
 -- and allows us to produce a well formed JSON output, prefixed
 -- by the string "JSON" which allows us to parse the wrk output
--- easily.
+-- easily. Latencies are reported by wrk in microseconds and are
+-- emitted in milliseconds without losing precision.
 done = function(summary, latency, requests)
+    -- JSON string literal of a header value. Control characters and
+    -- obs-text bytes are escaped, the latter read as ISO-8859-1.
+    local json_string = function(value)
+        local escaped = value:gsub('[\\"]', '\\%0'):gsub('[^ -~]', function(c)
+            return string.format("\\u%04x", c:byte())
+        end)
+        return '"' .. escaped .. '"'
+    end
+    -- Older wrk versions do not expose percentiles to Lua, in which
+    -- case 0 is emitted and the --latency output is parsed instead.
+    local percentile = function(p)
+        local ok, value = pcall(function() return latency:percentile(p) end)
+        if ok then
+            return value / 1000
+        end
+        return 0
+    end
+    -- Responses failing the body checks, counted by each thread.
+    local mismatches = 0
+    for _, thread in ipairs(wrk_api_bench_threads or {}) do
+        mismatches = mismatches + (thread:get("wrk_api_bench_mismatches") or 0)
+    end
+    -- With a status allowlist, status errors are counted by each thread
+    -- instead of relying on wrk's 2xx/3xx assumption.
+    local status_errors = summary.errors.status
+    if wrk_api_bench_success_statuses then
+        status_errors = 0
+        for _, thread in ipairs(wrk_api_bench_threads) do
+            status_errors = status_errors + thread:get("wrk_api_bench_status_errors")
+        end
+    end
     local errors = summary.errors.connect
         + summary.errors.read
         + summary.errors.write
-        + summary.errors.status
+        + status_errors
         + summary.errors.timeout
+        + mismatches
+    -- Per second responses of every thread, written to the sidecar file.
+    local timeseries_path = os.getenv("WRK_API_BENCH_TIMESERIES")
+    if wrk_api_bench_timeseries and timeseries_path then
+        local totals = {}
+        for _, thread in ipairs(wrk_api_bench_threads) do
+            for second, count in pairs(thread:get("wrk_api_bench_timeseries") or {}) do
+                totals[second] = (totals[second] or 0) + count
+            end
+        end
+        local file = io.open(timeseries_path, "w")
+        if file then
+            for second, count in pairs(totals) do
+                file:write(string.format("%d %d\n", second, count))
+            end
+            file:close()
+        end
+    end
+    -- Server and version headers of the first response of any thread.
+    local server, version = "", ""
+    for _, thread in ipairs(wrk_api_bench_threads or {}) do
+        if server == "" then
+            server = thread:get("wrk_api_bench_server") or ""
+        end
+        if version == "" then
+            version = thread:get("wrk_api_bench_version") or ""
+        end
+    end
     io.write("JSON")
     io.write(string.format(
         [[{
@@ -30,16 +89,23 @@ done = function(summary, latency, requests)
     "errors": %.2f,
     "successes": %.2f,
     "requests_sec": %.2f,
-    "avg_latency_ms": %.2f,
-    "min_latency_ms": %.2f,
-    "max_latency_ms": %.2f,
-    "stdev_latency_ms": %.2f,
+    "avg_latency_ms": %.6f,
+    "min_latency_ms": %.6f,
+    "max_latency_ms": %.6f,
+    "stdev_latency_ms": %.6f,
+    "p50_latency_ms": %.6f,
+    "p75_latency_ms": %.6f,
+    "p90_latency_ms": %.6f,
+    "p99_latency_ms": %.6f,
     "transfer_mb": %.2f,
     "errors_connect": %.2f,
     "errors_read": %.2f,
     "errors_write": %.2f,
     "errors_status": %.2f,
-    "errors_timeout": %.2f
+    "errors_timeout": %.2f,
+    "errors_mismatch": %.2f,
+    "server": %s,
+    "app_version": %s
 }
 ]],
         summary.requests,
@@ -50,68 +116,205 @@ done = function(summary, latency, requests)
         (latency.min / 1000),
         (latency.max / 1000),
         (latency.stdev / 1000),
+        percentile(50),
+        percentile(75),
+        percentile(90),
+        percentile(99),
         (summary.bytes / 1048576),
         summary.errors.connect,
         summary.errors.read,
         summary.errors.write,
-        summary.errors.status,
-        summary.errors.timeout
+        status_errors,
+        summary.errors.timeout,
+        mismatches,
+        json_string(server),
+        json_string(version)
     ))
 end
 "#;
 
+/// Lua helper reading the whole content of a file, used by multipart bodies.
+const LUA_READ_FILE_FUNCTION: &str = r#"
+local function read_file(path)
+    local file = assert(io.open(path, "rb"))
+    local content = file:read("*a")
+    file:close()
+    return content
+end
+"#;
+
+/// Methods whose requests never carry a body.
+const BODYLESS_METHODS: [Method; 5] = [
+    Method::HEAD,
+    Method::OPTIONS,
+    Method::DELETE,
+    Method::TRACE,
+    Method::CONNECT,
+];
+
+/// Request options rendered into the Lua script on top of method, headers and body.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct ScriptOptions<'a> {
+    /// Multipart body sent instead of the plain body.
+    pub(crate) multipart: Option<&'a Multipart>,
+    /// Checks every response body must pass.
+    pub(crate) response_checks: &'a [ResponseCheck],
+    /// Status codes counted as success, overriding wrk's 2xx/3xx default when not empty.
+    pub(crate) success_statuses: &'a [u16],
+    /// Record the responses per second in the sidecar file named by the
+    /// `WRK_API_BENCH_TIMESERIES` environment variable.
+    pub(crate) timeseries: bool,
+    /// Capture the `Server` header of the first response.
+    pub(crate) capture_server: bool,
+    /// Header carrying the deployed version, captured from the first response.
+    pub(crate) version_header: Option<&'a str>,
+    /// Headers whose values are read from the environment instead of being written in the
+    /// script, see [`crate::headers::secret_env`].
+    pub(crate) redacted_headers: &'a [String],
+}
+
+/// Renderer of the Lua script executed by wrk.
 #[derive(Debug)]
 pub struct LuaScript {}
 
 impl LuaScript {
-    fn lua_script_from_config(&mut self, uri: &str, method: &str, headers: &Headers, body: &str) -> Result<String> {
+    fn lua_script_from_config(
+        &mut self,
+        uri: &str,
+        method: &Method,
+        headers: &Headers,
+        body: &str,
+        options: ScriptOptions,
+    ) -> Result<String> {
+        let mut headers = Self::default_headers(method, headers, body);
+        let mut prelude = String::new();
+        let lua_body = if let Some(multipart) = options.multipart {
+            headers.insert("Content-Type".to_string(), multipart.content_type());
+            prelude = format!(
+                "{}local multipart_body = {}\n",
+                LUA_READ_FILE_FUNCTION,
+                multipart.lua_body()?
+            );
+            "wrk.body = multipart_body".to_string()
+        } else if BODYLESS_METHODS.contains(method) {
+            if !body.is_empty() {
+                warn!("Ignoring body of {} requests", method);
+            }
+            String::new()
+        } else {
+            format!("wrk.body = {}", lua_string(body))
+        };
         let request = format!(
-            r#"
+            r#"{}
 -- The request() function is called by wrk on all requests
 -- and allow us to configure things like headers, method, body, etc..
 request = function()
-    wrk.method = "{}"
-    wrk.body = "{}"
+    wrk.method = {}
+    {}
     {}
-    return wrk.format("{}", "{}")
+    return wrk.format({}, {})
 end
         "#,
-            method,
-            body,
-            self.lua_headers(headers)?,
-            method,
-            uri
+            prelude,
+            lua_string(method.as_str()),
+            lua_body,
+            self.lua_headers(&headers, options.redacted_headers)?,
+            lua_string(method.as_str()),
+            lua_string(uri)
         );
-        let buffer = request + LUA_DEFAULT_DONE_FUNCTION;
+        let buffer = request + &response::lua_hooks(&options) + LUA_DEFAULT_DONE_FUNCTION;
         Ok(buffer)
     }
 
+    /// Add a JSON `Content-Type` to requests carrying a JSON body, IE: a `PATCH` document,
+    /// unless the user already set one.
+    fn default_headers(method: &Method, headers: &Headers, body: &str) -> Headers {
+        let mut headers = headers.clone();
+        let has_content_type = headers.keys().any(|k| k.eq_ignore_ascii_case("content-type"));
+        let is_json = matches!(body.trim_start().chars().next(), Some('{') | Some('['))
+            && serde_json::from_str::<serde_json::Value>(body).is_ok();
+        if !BODYLESS_METHODS.contains(method) && !has_content_type && is_json {
+            headers.insert("Content-Type".to_string(), "application/json".to_string());
+        }
+        headers
+    }
+
     fn lua_script_from_user(&mut self, lua_script: &Path) -> Result<String> {
         let file = File::open(lua_script)?;
         let mut reader = BufReader::new(file);
         let mut buffer = String::new();
         reader.read_to_string(&mut buffer)?;
-        let mut lexer = Lexer::new();
-        let tokens = lexer.run(&buffer).map_err(|e| WrkError::Lua(format!("{:?}", e)))?;
         let buffer = buffer + LUA_DEFAULT_DONE_FUNCTION;
         Ok(buffer)
     }
 
-    fn lua_headers(&self, headers: &Headers) -> Result<String> {
+    /// Lex and parse the rendered `script`, returning the line and column of syntax errors.
+    #[cfg(feature = "lua-validate")]
+    fn validate(script: &str) -> Result<()> {
+        // rslua errors only expose their message, which already holds line and column.
+        let message = |error: String| {
+            let message = error
+                .split_once("(\"")
+                .and_then(|(_, message)| message.strip_suffix("\")"))
+                .unwrap_or(&error)
+                .to_string();
+            WrkError::Lua(format!("Invalid generated script: {}", message))
+        };
+        let mut lexer = Lexer::new();
+        let tokens = lexer.run(script).map_err(|e| message(format!("{:?}", e)))?;
+        Parser::new().run(tokens).map_err(|e| message(format!("{:?}", e)))?;
+        Ok(())
+    }
+
+    /// Syntax errors are only reported by wrk without the `lua-validate` feature.
+    #[cfg(not(feature = "lua-validate"))]
+    fn validate(_script: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn lua_headers(&self, headers: &Headers, redacted: &[String]) -> Result<String> {
+        crate::headers::validate(headers).map_err(WrkError::Lua)?;
         let mut result = String::new();
         for (k, v) in headers {
-            result += &format!(r#"wrk.headers["{}"] = "{}"\n"#, k, v);
+            let value = match crate::headers::is_redacted(k, redacted) {
+                true => format!("os.getenv({})", lua_string(&crate::headers::secret_env(k))),
+                false => lua_string(v),
+            };
+            result += &format!("wrk.headers[{}] = {}\n    ", lua_string(k), value);
         }
         Ok(result)
     }
 
-    pub fn render(
-        script_file: &mut NamedTempFile,
+    /// Render the Lua script into `script_file`, either from the user provided script or
+    /// from the request configuration.
+    pub fn render<W: Write>(
+        script_file: &mut W,
+        user_script: Option<&PathBuf>,
+        uri: &str,
+        method: &Method,
+        headers: &Headers,
+        body: &str,
+    ) -> Result<()> {
+        Self::render_request(
+            script_file,
+            user_script,
+            uri,
+            method,
+            headers,
+            body,
+            ScriptOptions::default(),
+        )
+    }
+
+    /// Same as [`LuaScript::render`], applying the extra request `options`.
+    pub(crate) fn render_request<W: Write>(
+        script_file: &mut W,
         user_script: Option<&PathBuf>,
         uri: &str,
-        method: &str,
+        method: &Method,
         headers: &Headers,
         body: &str,
+        options: ScriptOptions,
     ) -> Result<()> {
         let mut this = Self {};
         let script = match user_script {
@@ -124,12 +327,221 @@ end
                     );
                     return Err(WrkError::Lua("Wrk Lua file not found".to_string()));
                 } else {
-                    this.lua_script_from_user(&lua_script)?
+                    if !options.response_checks.is_empty() || !options.success_statuses.is_empty() || options.timeseries
+                    {
+                        warn!("Response checks, success statuses and timeseries are not applied to user provided Lua scripts");
+                    }
+                    this.lua_script_from_user(lua_script)?
                 }
             }
-            None => this.lua_script_from_config(uri, method, headers, body)?,
+            None => this.lua_script_from_config(uri, method, headers, body, options)?,
         };
+        Self::validate(&script)?;
         script_file.write_all(script.as_bytes())?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn method_bodies() {
+        let render = |method: Method, body: &str| {
+            let mut script = Vec::new();
+            LuaScript::render(&mut script, None, "/api", &method, &Headers::new(), body).unwrap();
+            String::from_utf8(script).unwrap()
+        };
+        let head = render(Method::HEAD, "ignored");
+        assert!(!head.contains("wrk.body"));
+        let patch = render(Method::PATCH, r#"{"name": "new"}"#);
+        assert!(patch.contains(r#"wrk.body = "{\"name\": \"new\"}""#));
+        assert!(patch.contains(r#"wrk.headers["Content-Type"] = "application/json""#));
+        assert!(!render(Method::POST, "name=new").contains("Content-Type"));
+    }
+
+    #[test]
+    fn multipart_body() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("avatar.png");
+        fs::write(&file, [0x89, 0x50, 0x4e, 0x47]).unwrap();
+        let multipart = Multipart::new()
+            .boundary("XYZ")
+            .field("user", "jane")
+            .file("avatar", &file, "image/png");
+        let mut script = Vec::new();
+        LuaScript::render_request(
+            &mut script,
+            None,
+            "/upload",
+            &Method::POST,
+            &Headers::new(),
+            "",
+            ScriptOptions {
+                multipart: Some(&multipart),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains(r#"wrk.headers["Content-Type"] = "multipart/form-data; boundary=XYZ""#));
+        assert!(script.contains(&format!("read_file(\"{}\")", file.canonicalize().unwrap().display())));
+        assert!(script.contains(r#""--XYZ--\r\n""#));
+        let missing = Multipart::new().file("avatar", &dir.path().join("missing.png"), "image/png");
+        assert!(LuaScript::render_request(
+            &mut Vec::new(),
+            None,
+            "/",
+            &Method::POST,
+            &Headers::new(),
+            "",
+            ScriptOptions {
+                multipart: Some(&missing),
+                ..Default::default()
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn response_checks() {
+        let checks = [
+            ResponseCheck::Contains("ok".to_string()),
+            ResponseCheck::JsonField {
+                name: "total".to_string(),
+                value: serde_json::json!(1.5),
+            },
+        ];
+        let mut script = Vec::new();
+        LuaScript::render_request(
+            &mut script,
+            None,
+            "/api",
+            &Method::GET,
+            &Headers::new(),
+            "",
+            ScriptOptions {
+                response_checks: &checks,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains(r#"(string.find(body, "ok", 1, true) ~= nil)"#));
+        assert!(script.contains(r#"(string.find(body, "\"total\"%s*:%s*1%.5") ~= nil)"#));
+        assert!(script.contains("wrk_api_bench_mismatches = wrk_api_bench_mismatches + 1"));
+    }
+
+    #[test]
+    fn success_statuses() {
+        let mut script = Vec::new();
+        LuaScript::render_request(
+            &mut script,
+            None,
+            "/missing",
+            &Method::GET,
+            &Headers::new(),
+            "",
+            ScriptOptions {
+                success_statuses: &[404],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("wrk_api_bench_success_statuses = { [404] = true }"));
+        assert!(script.contains("wrk_api_bench_status_errors = wrk_api_bench_status_errors + 1"));
+        assert!(!script.contains("wrk_api_bench_mismatches = 0"));
+    }
+
+    #[test]
+    fn timeseries() {
+        let mut script = Vec::new();
+        LuaScript::render_request(
+            &mut script,
+            None,
+            "/api",
+            &Method::GET,
+            &Headers::new(),
+            "",
+            ScriptOptions {
+                timeseries: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("wrk_api_bench_timeseries[second] = (wrk_api_bench_timeseries[second] or 0) + 1"));
+        assert!(!script.contains("status >= 400"));
+    }
+
+    #[test]
+    fn escaped_header_values() {
+        assert!(!LUA_DEFAULT_DONE_FUNCTION.contains("%q"));
+        assert!(LUA_DEFAULT_DONE_FUNCTION.contains("json_string(server)"));
+        // Output of done() for a Server header with a control byte and a version with obs-text.
+        let output = include_str!("../tests/corpus/wrk-4.1.0.txt").replace(
+            "\"errors_mismatch\": 0.00\n",
+            "\"errors_mismatch\": 0.00,\n    \"server\": \"nginx\\u0001\\\\\\n\",\n    \"app_version\": \"caf\\u00e9\"\n",
+        );
+        let json = crate::output::json_result(&output).unwrap();
+        let result: crate::WrkResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(result.server().as_deref(), Some("nginx\u{1}\\\n"));
+        assert_eq!(result.app_version().as_deref(), Some("caf\u{e9}"));
+    }
+
+    #[test]
+    fn server_headers() {
+        let mut script = Vec::new();
+        LuaScript::render_request(
+            &mut script,
+            None,
+            "/api",
+            &Method::GET,
+            &Headers::new(),
+            "",
+            ScriptOptions {
+                capture_server: true,
+                version_header: Some("X-App-Version"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains(r#"wrk_api_bench_server = wrk_api_bench_header(headers, "Server")"#));
+        assert!(script.contains(r#"wrk_api_bench_version = wrk_api_bench_header(headers, "X-App-Version")"#));
+        #[cfg(feature = "lua-validate")]
+        LuaScript::validate(&script).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "lua-validate")]
+    fn validate_rendered_script() {
+        let headers = Headers::from([("X-Quote".to_string(), r#"say "hi""#.to_string())]);
+        let mut script = Vec::new();
+        LuaScript::render(&mut script, None, "/api", &Method::POST, &headers, "{}").unwrap();
+        assert!(String::from_utf8(script)
+            .unwrap()
+            .contains(r#"wrk.headers["X-Quote"] = "say \"hi\"""#));
+
+        let dir = tempfile::tempdir().unwrap();
+        let user_script = dir.path().join("broken.lua");
+        fs::write(&user_script, "request = function()\n  return wrk.format(\nend\n").unwrap();
+        let error = LuaScript::render(
+            &mut Vec::new(),
+            Some(&user_script),
+            "/api",
+            &Method::GET,
+            &Headers::new(),
+            "",
+        )
+        .unwrap_err();
+        match error {
+            WrkError::Lua(message) => assert!(message.contains("line [3:"), "{}", message),
+            e => panic!("unexpected error {}", e),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Serde and validation helpers for [`http::Method`].
+use http::Method;
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+/// Methods wrk is able to send. Extension methods are accepted by [`http::Method`], which means
+/// typos like `GTE` would end up in the generated Lua script.
+const STANDARD_METHODS: [Method; 9] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::DELETE,
+    Method::HEAD,
+    Method::OPTIONS,
+    Method::CONNECT,
+    Method::PATCH,
+    Method::TRACE,
+];
+
+pub(crate) fn validate(method: &Method) -> std::result::Result<(), String> {
+    if STANDARD_METHODS.contains(method) {
+        Ok(())
+    } else {
+        Err(format!("Unsupported HTTP method {}", method))
+    }
+}
+
+pub(crate) fn parse(method: &str) -> std::result::Result<Method, String> {
+    let method = Method::from_bytes(method.as_bytes()).map_err(|e| format!("Invalid HTTP method {}: {}", method, e))?;
+    validate(&method)?;
+    Ok(method)
+}
+
+pub(crate) fn serialize<S: Serializer>(method: &Method, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(method.as_str())
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Method, D::Error> {
+    let method = String::deserialize(deserializer)?;
+    parse(&method).map_err(D::Error::custom)
+}
+
+/// Serde helpers for optional methods.
+pub(crate) mod option {
+    use http::Method;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(method: &Option<Method>, serializer: S) -> Result<S::Ok, S::Error> {
+        match method {
+            Some(method) => super::serialize(method, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Method>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(method) => super::parse(&method).map(Some).map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Measurements of a [`WrkResult`] used to rank and score results.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::WrkResult;
+
+/// Single measurement of a [`WrkResult`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// Requests per second.
+    RequestsSec,
+    /// Total number of requests.
+    Requests,
+    /// Total number of errors.
+    Errors,
+    /// Total number of successful requests.
+    Successes,
+    /// Average latency in milliseconds.
+    AvgLatency,
+    /// Min latency in milliseconds.
+    MinLatency,
+    /// Max latency in milliseconds.
+    MaxLatency,
+    /// Standard deviation of the latency in milliseconds.
+    StdevLatency,
+    /// 50th percentile latency in milliseconds.
+    P50Latency,
+    /// 75th percentile latency in milliseconds.
+    P75Latency,
+    /// 90th percentile latency in milliseconds.
+    P90Latency,
+    /// 99th percentile latency in milliseconds.
+    P99Latency,
+    /// Total transferred data in MB.
+    Transfer,
+    /// Ratio of errors over the total number of requests.
+    ErrorRate,
+    /// Ratio of successes over the total number of requests.
+    SuccessRate,
+    /// Transferred data per second in MiB.
+    Throughput,
+    /// Average bytes transferred per request.
+    BytesPerRequest,
+    /// Connect errors.
+    ErrorsConnect,
+    /// Read errors.
+    ErrorsRead,
+    /// Write errors.
+    ErrorsWrite,
+    /// Responses with a status other than 2xx/3xx.
+    ErrorsStatus,
+    /// Timeout errors.
+    ErrorsTimeout,
+    /// Responses not matching the expected ones.
+    ErrorsMismatch,
+}
+
+impl Metric {
+    /// Every metric, in report order.
+    pub const ALL: [Metric; 23] = [
+        Self::RequestsSec,
+        Self::Requests,
+        Self::Errors,
+        Self::Successes,
+        Self::AvgLatency,
+        Self::MinLatency,
+        Self::MaxLatency,
+        Self::StdevLatency,
+        Self::P50Latency,
+        Self::P75Latency,
+        Self::P90Latency,
+        Self::P99Latency,
+        Self::Transfer,
+        Self::ErrorRate,
+        Self::SuccessRate,
+        Self::Throughput,
+        Self::BytesPerRequest,
+        Self::ErrorsConnect,
+        Self::ErrorsRead,
+        Self::ErrorsWrite,
+        Self::ErrorsStatus,
+        Self::ErrorsTimeout,
+        Self::ErrorsMismatch,
+    ];
+
+    /// Iterate over every metric, in report order.
+    pub fn iter() -> impl Iterator<Item = Metric> {
+        Self::ALL.into_iter()
+    }
+
+    /// Value of this metric in `result`.
+    pub fn value(&self, result: &WrkResult) -> f64 {
+        match self {
+            Self::RequestsSec => *result.requests_sec(),
+            Self::Requests => *result.requests(),
+            Self::Errors => *result.errors(),
+            Self::Successes => *result.successes(),
+            Self::AvgLatency => *result.avg_latency_ms(),
+            Self::MinLatency => *result.min_latency_ms(),
+            Self::MaxLatency => *result.max_latency_ms(),
+            Self::StdevLatency => *result.stdev_latency_ms(),
+            Self::P50Latency => *result.p50_latency_ms(),
+            Self::P75Latency => *result.p75_latency_ms(),
+            Self::P90Latency => *result.p90_latency_ms(),
+            Self::P99Latency => *result.p99_latency_ms(),
+            Self::Transfer => *result.transfer_mb(),
+            Self::ErrorRate => result.error_rate(),
+            Self::SuccessRate => result.success_rate(),
+            Self::Throughput => result.throughput_mib_s(),
+            Self::BytesPerRequest => result.avg_bytes_per_request(),
+            Self::ErrorsConnect => *result.errors_connect(),
+            Self::ErrorsRead => *result.errors_read(),
+            Self::ErrorsWrite => *result.errors_write(),
+            Self::ErrorsStatus => *result.errors_status(),
+            Self::ErrorsTimeout => *result.errors_timeout(),
+            Self::ErrorsMismatch => *result.errors_mismatch(),
+        }
+    }
+
+    /// Whether higher values of this metric are better.
+    pub fn higher_is_better(&self) -> bool {
+        matches!(
+            self,
+            Self::RequestsSec | Self::Requests | Self::Successes | Self::SuccessRate | Self::Throughput
+        )
+    }
+
+    /// Name of this metric, without unit.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RequestsSec => "Requests/sec",
+            Self::Requests => "Total requests",
+            Self::Errors => "Total errors",
+            Self::Successes => "Total successes",
+            Self::AvgLatency => "Average latency",
+            Self::MinLatency => "Min latency",
+            Self::MaxLatency => "Max latency",
+            Self::StdevLatency => "Stdev latency",
+            Self::P50Latency => "P50 latency",
+            Self::P75Latency => "P75 latency",
+            Self::P90Latency => "P90 latency",
+            Self::P99Latency => "P99 latency",
+            Self::Transfer => "Transfer",
+            Self::ErrorRate => "Error rate",
+            Self::SuccessRate => "Success rate",
+            Self::Throughput => "Throughput",
+            Self::BytesPerRequest => "Average bytes per request",
+            Self::ErrorsConnect => "Connect errors",
+            Self::ErrorsRead => "Read errors",
+            Self::ErrorsWrite => "Write errors",
+            Self::ErrorsStatus => "Status errors (not 2xx/3xx)",
+            Self::ErrorsTimeout => "Timeout errors",
+            Self::ErrorsMismatch => "Mismatched responses",
+        }
+    }
+
+    /// Unit of this metric, if any.
+    pub fn unit(&self) -> Option<&'static str> {
+        match self {
+            _ if self.is_latency() => Some("ms"),
+            Self::Transfer => Some("MB"),
+            Self::Throughput => Some("MiB/s"),
+            _ => None,
+        }
+    }
+
+    /// Whether this metric is computed from other measurements rather than read by wrk, so
+    /// its variance is not stored in [`crate::Variance::variance`].
+    pub fn is_derived(&self) -> bool {
+        matches!(
+            self,
+            Self::ErrorRate | Self::SuccessRate | Self::Throughput | Self::BytesPerRequest
+        )
+    }
+
+    /// Whether this metric is a latency in milliseconds.
+    pub fn is_latency(&self) -> bool {
+        matches!(
+            self,
+            Self::AvgLatency
+                | Self::MinLatency
+                | Self::MaxLatency
+                | Self::StdevLatency
+                | Self::P50Latency
+                | Self::P75Latency
+                | Self::P90Latency
+                | Self::P99Latency
+        )
+    }
+
+    /// Whether this metric is a total over the whole run, only comparable between runs of the
+    /// same duration.
+    pub fn is_total(&self) -> bool {
+        matches!(
+            self,
+            Self::Requests
+                | Self::Errors
+                | Self::Successes
+                | Self::Transfer
+                | Self::ErrorsConnect
+                | Self::ErrorsRead
+                | Self::ErrorsWrite
+                | Self::ErrorsStatus
+                | Self::ErrorsTimeout
+                | Self::ErrorsMismatch
+        )
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.unit() {
+            Some(unit) => write!(f, "{} ({})", self.name(), unit),
+            None => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WrkResultBuilder;
+
+    #[test]
+    fn get_every_metric() {
+        let result = WrkResultBuilder::default()
+            .requests(100.0)
+            .errors(10.0)
+            .p75_latency_ms(3.0)
+            .build()
+            .unwrap();
+        assert_eq!(Metric::iter().count(), Metric::ALL.len());
+        assert_eq!(result.get(Metric::Requests), 100.0);
+        assert_eq!(result.get(Metric::ErrorRate), 0.1);
+        assert_eq!(result.get(Metric::P75Latency), 3.0);
+        assert!(Metric::iter().filter(|m| m.is_latency()).all(|m| !m.is_total()));
+    }
+}
@@ -0,0 +1,132 @@
+//! `multipart/form-data` request bodies, to benchmark upload endpoints.
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{headers::lua_string, Result, WrkError};
+
+/// Single part of a [`Multipart`] body.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Part {
+    /// Text field.
+    Field {
+        /// Field name.
+        name: String,
+        /// Field value.
+        value: String,
+    },
+    /// File, read by the Lua script when wrk starts, so binary content is sent untouched.
+    File {
+        /// Field name.
+        name: String,
+        /// Path of the file to upload.
+        path: PathBuf,
+        /// Content type of the file, IE: `image/png`.
+        content_type: String,
+    },
+}
+
+/// `multipart/form-data` body made of text fields and file parts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Multipart {
+    boundary: String,
+    parts: Vec<Part>,
+}
+
+impl Default for Multipart {
+    fn default() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let hash = format!("{:x}", Sha256::digest(nanos.to_string()));
+        Self {
+            boundary: format!("wrk-api-bench-{}", &hash[..24]),
+            parts: Vec::new(),
+        }
+    }
+}
+
+impl Multipart {
+    /// Empty body with a random boundary.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the given boundary, which must not appear in any part.
+    pub fn boundary(mut self, boundary: &str) -> Self {
+        self.boundary = boundary.to_string();
+        self
+    }
+
+    /// Append a text field.
+    pub fn field(mut self, name: &str, value: &str) -> Self {
+        self.parts.push(Part::Field {
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+        self
+    }
+
+    /// Append a file part.
+    pub fn file(mut self, name: &str, path: &Path, content_type: &str) -> Self {
+        self.parts.push(Part::File {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            content_type: content_type.to_string(),
+        });
+        self
+    }
+
+    /// Parts of the body.
+    pub fn parts(&self) -> &[Part] {
+        &self.parts
+    }
+
+    /// Value of the `Content-Type` header announcing the boundary.
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Lua expression building the body, reading file parts through `read_file()`.
+    pub(crate) fn lua_body(&self) -> Result<String> {
+        let mut chunks = Vec::new();
+        for part in &self.parts {
+            match part {
+                Part::Field { name, value } => {
+                    if value.contains(&self.boundary) {
+                        return Err(WrkError::Lua(format!("Multipart field {} contains the boundary", name)));
+                    }
+                    chunks.push(lua_string(&format!(
+                        "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                        self.boundary, name, value
+                    )));
+                }
+                Part::File {
+                    name,
+                    path,
+                    content_type,
+                } => {
+                    if !path.is_file() {
+                        return Err(WrkError::Lua(format!("Multipart file {} not found", path.display())));
+                    }
+                    let filename = path.file_name().unwrap_or_default().to_string_lossy();
+                    chunks.push(lua_string(&format!(
+                        "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                        self.boundary, name, filename, content_type
+                    )));
+                    let path = path.canonicalize()?;
+                    chunks.push(format!("read_file({})", lua_string(&path.to_string_lossy())));
+                    chunks.push(lua_string("\r\n"));
+                }
+            }
+        }
+        chunks.push(lua_string(&format!("--{}--\r\n", self.boundary)));
+        Ok(chunks.join(" .. "))
+    }
+}
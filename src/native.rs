@@ -0,0 +1,383 @@
+//! Load generator written in Rust, selected with [`crate::Engine::Native`] where the wrk binary
+//! is not available, IE: CI containers or Windows.
+//!
+//! Like wrk, every connection sends its next request as soon as the previous response is
+//! received. The output mimics the one of wrk with the JSON summary of the Lua script, so it
+//! goes through the same parsing.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use http::Method;
+use hyper::{body::HttpBody, client::HttpConnector, Body, Client, Request};
+use serde_json::json;
+use tokio::time::{timeout_at, Instant as TokioInstant};
+
+use crate::{Headers, ProcessOutput, Result, WrkError};
+
+/// Program recorded in the audit log for native runs.
+pub(crate) const PROGRAM: &str = "wrk-api-bench-native";
+
+/// Load applied by a native run.
+#[derive(Debug, Clone)]
+pub(crate) struct Load {
+    pub(crate) url: String,
+    pub(crate) method: Method,
+    pub(crate) headers: Headers,
+    pub(crate) body: String,
+    pub(crate) threads: u16,
+    pub(crate) connections: u16,
+    pub(crate) duration: Duration,
+    pub(crate) timeout: Duration,
+    /// Statuses counted as successful, any 2xx or 3xx one when empty.
+    pub(crate) success_statuses: Vec<u16>,
+    pub(crate) capture_server: bool,
+    pub(crate) version_header: Option<String>,
+    /// Sidecar file receiving the responses of every second.
+    pub(crate) timeseries: Option<PathBuf>,
+}
+
+/// Outcomes of the requests sent by a single connection.
+#[derive(Debug, Default)]
+struct Stats {
+    latencies_us: Vec<u64>,
+    successes: u64,
+    bytes: u64,
+    connect: u64,
+    read: u64,
+    status: u64,
+    timeout: u64,
+    per_second: BTreeMap<i64, u64>,
+    server: Option<String>,
+    version: Option<String>,
+    first_error: Option<String>,
+}
+
+impl Stats {
+    fn merge(&mut self, other: Stats) {
+        self.latencies_us.extend(other.latencies_us);
+        self.successes += other.successes;
+        self.bytes += other.bytes;
+        self.connect += other.connect;
+        self.read += other.read;
+        self.status += other.status;
+        self.timeout += other.timeout;
+        for (second, count) in other.per_second {
+            *self.per_second.entry(second).or_default() += count;
+        }
+        self.server = self.server.take().or(other.server);
+        self.version = self.version.take().or(other.version);
+        self.first_error = self.first_error.take().or(other.first_error);
+    }
+
+    fn errors(&self) -> u64 {
+        self.connect + self.read + self.status + self.timeout
+    }
+}
+
+/// Apply `load` on a runtime with one worker per thread, returning an output shaped like the
+/// one of wrk. The runtime lives on its own thread, so callers may already be inside one.
+pub(crate) fn run(load: Load) -> Result<ProcessOutput> {
+    if load.url.starts_with("https") {
+        return Err(WrkError::Config(
+            "The native engine only supports plain HTTP targets".to_string(),
+        ));
+    }
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(load.threads.max(1) as usize)
+            .enable_all()
+            .build()?;
+        runtime.block_on(generate(load))
+    })
+    .join()
+    .map_err(|_| WrkError::Exec("The native load generator panicked".to_string()))?
+}
+
+async fn generate(load: Load) -> Result<ProcessOutput> {
+    let start = Instant::now();
+    let deadline = TokioInstant::now() + load.duration;
+    let mut tasks = Vec::new();
+    for _ in 0..load.connections.max(1) {
+        tasks.push(tokio::spawn(connection(load.clone(), deadline)));
+    }
+    let mut stats = Stats::default();
+    for task in tasks {
+        stats.merge(
+            task.await
+                .map_err(|e| WrkError::Exec(format!("Native connection failed: {}", e)))?,
+        );
+    }
+    let elapsed = start.elapsed();
+    if let Some(path) = &load.timeseries {
+        let lines: String = stats
+            .per_second
+            .iter()
+            .map(|(second, count)| format!("{} {}\n", second, count))
+            .collect();
+        fs::write(path, lines)?;
+    }
+    let stderr = stats.first_error.clone().map(|e| e + "\n").unwrap_or_default();
+    Ok(ProcessOutput::new(Some(0), report(&load, &mut stats, elapsed), stderr))
+}
+
+/// Send requests on a single connection until `deadline`. Requests still in flight at the
+/// deadline are discarded, as wrk does.
+async fn connection(load: Load, deadline: TokioInstant) -> Stats {
+    let mut connector = HttpConnector::new();
+    connector.set_connect_timeout(Some(load.timeout));
+    let client: Client<HttpConnector> = Client::builder().pool_max_idle_per_host(1).build(connector);
+    let mut stats = Stats::default();
+    while TokioInstant::now() < deadline {
+        let mut request = Request::builder().method(load.method.clone()).uri(&load.url);
+        for (name, value) in &load.headers {
+            request = request.header(name, value);
+        }
+        let request = match request.body(Body::from(load.body.clone())) {
+            Ok(request) => request,
+            Err(e) => {
+                stats
+                    .first_error
+                    .get_or_insert_with(|| format!("invalid request: {}", e));
+                stats.connect += 1;
+                break;
+            }
+        };
+        let sent = Instant::now();
+        let request_deadline = deadline.min(TokioInstant::now() + load.timeout);
+        let response = timeout_at(request_deadline, async {
+            let response = client.request(request).await?;
+            let (parts, mut body) = response.into_parts();
+            let mut bytes = 0;
+            while let Some(chunk) = body.data().await {
+                bytes += chunk?.len() as u64;
+            }
+            Ok::<_, hyper::Error>((parts, bytes))
+        })
+        .await;
+        match response {
+            Err(_) if TokioInstant::now() >= deadline => break,
+            Err(_) => stats.timeout += 1,
+            Ok(Err(e)) => {
+                let host = url_authority(&load.url);
+                if e.is_connect() {
+                    stats.connect += 1;
+                    stats
+                        .first_error
+                        .get_or_insert_with(|| format!("unable to connect to {} {}", host, e));
+                } else {
+                    stats.read += 1;
+                    stats
+                        .first_error
+                        .get_or_insert_with(|| format!("read error on {} {}", host, e));
+                }
+            }
+            Ok(Ok((parts, body_bytes))) => {
+                stats.latencies_us.push(sent.elapsed().as_micros() as u64);
+                *stats.per_second.entry(Utc::now().timestamp()).or_default() += 1;
+                let status = parts.status.as_u16();
+                // Status line and headers, as wrk counts every byte read.
+                let headers: usize = parts.headers.iter().map(|(n, v)| n.as_str().len() + v.len() + 4).sum();
+                stats.bytes += body_bytes + headers as u64 + 19;
+                let success = if load.success_statuses.is_empty() {
+                    status < 400
+                } else {
+                    load.success_statuses.contains(&status)
+                };
+                if success {
+                    stats.successes += 1;
+                } else {
+                    stats.status += 1;
+                }
+                let header = |name: &str| {
+                    parts
+                        .headers
+                        .get(name)
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string)
+                };
+                if load.capture_server && stats.server.is_none() {
+                    stats.server = header("server");
+                }
+                if let (Some(name), None) = (&load.version_header, &stats.version) {
+                    stats.version = header(name);
+                }
+            }
+        }
+    }
+    stats
+}
+
+fn url_authority(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split('/').next().unwrap_or(rest)
+}
+
+/// Summary printed by wrk, followed by the JSON emitted by the Lua script.
+fn report(load: &Load, stats: &mut Stats, elapsed: Duration) -> String {
+    stats.latencies_us.sort_unstable();
+    let latencies = &stats.latencies_us;
+    let ms = |us: f64| us / 1000.0;
+    let count = latencies.len() as f64;
+    let mean = if latencies.is_empty() {
+        0.0
+    } else {
+        latencies.iter().sum::<u64>() as f64 / count
+    };
+    let stdev = if latencies.len() < 2 {
+        0.0
+    } else {
+        (latencies.iter().map(|l| (*l as f64 - mean).powi(2)).sum::<f64>() / (count - 1.0)).sqrt()
+    };
+    let percentile = |p: f64| match latencies.len() {
+        0 => 0.0,
+        n => latencies[((p / 100.0 * n as f64).ceil() as usize).clamp(1, n) - 1] as f64,
+    };
+    let requests = latencies.len() as u64 + stats.connect + stats.read + stats.timeout;
+    let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+    let transfer_mb = stats.bytes as f64 / 1_048_576.0;
+    let json = json!({
+        "requests": requests as f64,
+        "errors": stats.errors() as f64,
+        "successes": stats.successes as f64,
+        "requests_sec": requests as f64 / secs,
+        "avg_latency_ms": ms(mean),
+        "min_latency_ms": ms(latencies.first().copied().unwrap_or_default() as f64),
+        "max_latency_ms": ms(latencies.last().copied().unwrap_or_default() as f64),
+        "stdev_latency_ms": ms(stdev),
+        "p50_latency_ms": ms(percentile(50.0)),
+        "p75_latency_ms": ms(percentile(75.0)),
+        "p90_latency_ms": ms(percentile(90.0)),
+        "p99_latency_ms": ms(percentile(99.0)),
+        "transfer_mb": transfer_mb,
+        "errors_connect": stats.connect as f64,
+        "errors_read": stats.read as f64,
+        "errors_write": 0.0,
+        "errors_status": stats.status as f64,
+        "errors_timeout": stats.timeout as f64,
+        "errors_mismatch": 0.0,
+        "server": stats.server.clone().unwrap_or_default(),
+        "app_version": stats.version.clone().unwrap_or_default(),
+    });
+    let mut output = format!(
+        "Running {}s native test @ {}\n  {} threads and {} connections\n  {} requests in {:.2}s, {:.2}MB read\n",
+        load.duration.as_secs(),
+        load.url,
+        load.threads,
+        load.connections,
+        requests,
+        secs,
+        transfer_mb
+    );
+    output += &format!(
+        "  Socket errors: connect {}, read {}, write 0, timeout {}\n",
+        stats.connect, stats.read, stats.timeout
+    );
+    if load.success_statuses.is_empty() {
+        output += &format!("  Non-2xx or 3xx responses: {}\n", stats.status);
+    }
+    output += &format!("Requests/sec: {:.2}\n", requests as f64 / secs);
+    output += &format!("JSON{}\n", serde_json::to_string_pretty(&json).unwrap_or_default());
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, net::SocketAddr};
+
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Response, Server,
+    };
+
+    use super::*;
+
+    fn serve() -> SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            tokio::runtime::Runtime::new().unwrap().block_on(async move {
+                let make = make_service_fn(|_| async {
+                    Ok::<_, Infallible>(service_fn(|request: Request<Body>| async move {
+                        let status = if request.uri().path() == "/missing" { 404 } else { 200 };
+                        Ok::<_, Infallible>(
+                            Response::builder()
+                                .status(status)
+                                .header("server", "native-test")
+                                .body(Body::from("hello"))
+                                .unwrap(),
+                        )
+                    }))
+                });
+                Server::from_tcp(listener).unwrap().serve(make).await.unwrap();
+            })
+        });
+        addr
+    }
+
+    fn load(url: String) -> Load {
+        Load {
+            url,
+            method: Method::GET,
+            headers: Headers::new(),
+            body: String::new(),
+            threads: 2,
+            connections: 4,
+            duration: Duration::from_millis(500),
+            timeout: Duration::from_secs(1),
+            success_statuses: Vec::new(),
+            capture_server: true,
+            version_header: None,
+            timeseries: None,
+        }
+    }
+
+    #[test]
+    fn native_load() {
+        let addr = serve();
+        let output = run(load(format!("http://{}/api", addr))).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&crate::output::json_result(output.stdout()).unwrap()).unwrap();
+        assert!(json["requests"].as_f64().unwrap() > 0.0, "{}", output.stdout());
+        assert_eq!(json["errors"], 0.0);
+        assert_eq!(json["successes"], json["requests"]);
+        assert!(json["p99_latency_ms"].as_f64().unwrap() >= json["p50_latency_ms"].as_f64().unwrap());
+        assert!(json["transfer_mb"].as_f64().unwrap() > 0.0);
+        assert_eq!(json["server"], "native-test");
+
+        let output = run(load(format!("http://{}/missing", addr))).unwrap();
+        assert_eq!(crate::output::error_summary(output.stdout()).status, {
+            let json = crate::output::json_result(output.stdout()).unwrap();
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()["errors_status"]
+                .as_f64()
+                .unwrap()
+        });
+        assert!(run(load("https://127.0.0.1/".to_string())).is_err());
+    }
+
+    #[test]
+    fn native_engine() {
+        let addr = serve();
+        let wrk = crate::WrkBuilder::default()
+            .url(format!("http://{}/api", addr))
+            .engine(crate::Engine::Native)
+            .capture_server(true)
+            .history_store(crate::HistoryStore::in_memory())
+            .build()
+            .unwrap();
+        let mut session = crate::BenchSession::from(wrk.clone());
+        session.bench(&[crate::Benchmark::new(1, 2, 1)]).unwrap();
+        let results = session.benchmarks();
+        assert!(*results[0].success(), "{}", results[0].error());
+        assert!(*results[0].requests_sec() > 0.0);
+        assert!(*results[0].p50_latency_ms() > 0.0);
+        assert_eq!(results[0].server().as_deref(), Some("native-test"));
+        assert!(results[0].warnings().iter().all(|w| !w.contains("wrk reported")));
+        assert_eq!(wrk.audit_log().unwrap()[0].command(), PROGRAM);
+    }
+}
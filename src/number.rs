@@ -0,0 +1,170 @@
+//! Locale aware formatting of the numbers shown in reports.
+use std::env;
+
+use serde::{Deserialize, Serialize};
+
+/// Separators and precision used to display numbers in reports.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NumberFormat {
+    /// Separator of the groups of thousands, `None` to leave them ungrouped.
+    pub thousands_separator: Option<char>,
+    /// Separator of the decimals.
+    pub decimal_separator: char,
+    /// Number of decimals of non integer measurements.
+    pub precision: usize,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            thousands_separator: None,
+            decimal_separator: '.',
+            precision: 2,
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Separators of the locale `tag`, IE: `en_US.UTF-8` or `de-DE`. Unknown locales, `C` and
+    /// `POSIX` leave numbers ungrouped.
+    pub fn locale(tag: &str) -> Self {
+        let language = tag
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let (thousands_separator, decimal_separator) = match language.as_str() {
+            "en" | "ja" | "ko" | "zh" | "he" | "th" => (Some(','), '.'),
+            "de" | "it" | "es" | "nl" | "pt" | "da" | "id" | "tr" | "el" => (Some('.'), ','),
+            "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "no" | "uk" | "hu" => (Some('\u{a0}'), ','),
+            _ => return Self::default(),
+        };
+        Self {
+            thousands_separator,
+            decimal_separator,
+            ..Default::default()
+        }
+    }
+
+    /// Separators of the locale configured by `LC_ALL`, `LC_NUMERIC` or `LANG`.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|tag| !tag.is_empty())
+            .map_or_else(Self::default, |tag| Self::locale(&tag))
+    }
+
+    /// Display non integer measurements with the given number of decimals.
+    pub fn with_precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Format `value` with `precision` decimals. NaN is `n/a` and infinities are `∞` and `-∞`.
+    pub fn format(&self, value: f64, precision: usize) -> String {
+        if !value.is_finite() {
+            return non_finite(value, "");
+        }
+        let fixed = format!("{:.*}", precision, value.abs());
+        let (integer, decimals) = fixed.split_once('.').unwrap_or((&fixed, ""));
+        let mut result = String::with_capacity(fixed.len() + integer.len() / 3 + 1);
+        if value.is_sign_negative() && fixed.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+            result.push('-');
+        }
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                if let Some(separator) = self.thousands_separator {
+                    result.push(separator);
+                }
+            }
+            result.push(digit);
+        }
+        if !decimals.is_empty() {
+            result.push(self.decimal_separator);
+            result += decimals;
+        }
+        result
+    }
+
+    /// Format `value` with [`NumberFormat::precision`] decimals, or none when it is integer.
+    pub fn auto(&self, value: f64) -> String {
+        if value.fract() == 0.0 {
+            self.format(value, 0)
+        } else {
+            self.format(value, self.precision)
+        }
+    }
+
+    /// Format the percentage `value` with 2 decimals, IE: `-3.50%`. NaN is `n/a` and
+    /// infinities, from comparisons with a zero baseline, are `+∞` and `-∞`.
+    pub fn percent(&self, value: f64) -> String {
+        if value.is_finite() {
+            format!("{}%", self.format(value, 2))
+        } else {
+            non_finite(value, "+")
+        }
+    }
+
+    /// Format the percentage `value` like [`NumberFormat::percent`], with an explicit sign,
+    /// IE: `+3.50%`.
+    pub fn signed_percent(&self, value: f64) -> String {
+        match self.percent(value) {
+            percent if value > 0.0 && value.is_finite() => format!("+{}", percent),
+            percent => percent,
+        }
+    }
+
+    /// Format a size of `bytes` with the largest unit keeping it above 1, IE: `1.50 MB`. Units
+    /// are powers of 1024, like the ones reported by wrk.
+    pub fn bytes(&self, bytes: f64) -> String {
+        const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+        let (mut value, mut unit) = (bytes, 0);
+        while value.abs() >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{} {}", self.auto(value), UNITS[unit])
+        } else {
+            format!("{} {}", self.format(value, self.precision), UNITS[unit])
+        }
+    }
+}
+
+/// Text of the non finite `value`, with `plus` before positive infinity.
+fn non_finite(value: f64, plus: &str) -> String {
+    if value.is_nan() {
+        "n/a".to_string()
+    } else if value > 0.0 {
+        format!("{}∞", plus)
+    } else {
+        "-∞".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separators() {
+        let en = NumberFormat::locale("en_US.UTF-8");
+        assert_eq!(en.format(1234567.0, 0), "1,234,567");
+        assert_eq!(en.format(-1234.567, 2), "-1,234.57");
+        assert_eq!(en.format(-0.001, 2), "0.00");
+        assert_eq!(en.auto(123.0), "123");
+        assert_eq!(NumberFormat::locale("de-DE").format(1234567.891, 1), "1.234.567,9");
+        assert_eq!(NumberFormat::locale("C").format(1234567.0, 0), "1234567");
+        assert_eq!(NumberFormat::default().with_precision(3).auto(1.5), "1.500");
+        assert_eq!(en.bytes(512.0), "512 B");
+        assert_eq!(en.bytes(1536.0 * 1024.0 * 1024.0), "1.50 GB");
+        assert_eq!(en.percent(-1234.5), "-1,234.50%");
+        assert_eq!(en.percent(f64::INFINITY), "+∞");
+        assert_eq!(en.percent(f64::NAN), "n/a");
+        assert_eq!(en.format(f64::NEG_INFINITY, 2), "-∞");
+        assert_eq!(en.signed_percent(3.5), "+3.50%");
+        assert_eq!(en.signed_percent(-3.5), "-3.50%");
+        assert_eq!(en.signed_percent(f64::INFINITY), "+∞");
+    }
+}
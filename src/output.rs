@@ -0,0 +1,216 @@
+//! Parsers for the human readable wrk output.
+
+/// Parse a number, tolerating the comma decimal separator of some locales, IE: `1,04`.
+pub(crate) fn number(value: &str) -> Option<f64> {
+    let value = value.trim();
+    value.parse().ok().or_else(|| {
+        if value.contains('.') || value.matches(',').count() != 1 {
+            return None;
+        }
+        value.replace(',', ".").parse().ok()
+    })
+}
+
+/// Replace the comma decimal separators in the values of the one field per line JSON written
+/// by the Lua script, IE: `"requests_sec": 1234,56,` becomes `"requests_sec": 1234.56,`.
+pub(crate) fn normalize_json_numbers(json: &str) -> String {
+    json.lines()
+        .map(|line| {
+            let Some((key, value)) = line.split_once("\": ") else {
+                return line.to_string();
+            };
+            let (value, separator) = match value.strip_suffix(',') {
+                Some(value) => (value, ","),
+                None => (value, ""),
+            };
+            match number(value) {
+                Some(number) if value.contains(',') => format!("{}\": {}{}", key, number, separator),
+                _ => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Extract the JSON result written by the Lua `done` hook after the `JSON` marker, skipping
+/// unrelated lines some wrk builds print to stdout, IE: LuaJIT warnings, even in the middle
+/// of it.
+pub(crate) fn json_result(output: &str) -> Option<String> {
+    let lines: Vec<&str> = output.lines().collect();
+    let start = lines.iter().rposition(|line| line.trim_start().starts_with("JSON{"))?;
+    let mut json = String::from("{\n");
+    for line in &lines[start + 1..] {
+        match line.trim() {
+            "}" => return Some(json + "}\n"),
+            field if field.starts_with('"') => {
+                json.push_str(line);
+                json.push('\n');
+            }
+            _ => debug!("Skipping unrelated line in wrk JSON output: {}", line),
+        }
+    }
+    None
+}
+
+/// Convert a wrk formatted time (IE: `635.91us`, `1.04ms`, `2.00s`) to milliseconds.
+fn time_ms(value: &str) -> Option<f64> {
+    let split = value.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, unit) = value.split_at(split);
+    let number = self::number(number)?;
+    let factor = match unit {
+        "us" => 0.001,
+        "ms" => 1.0,
+        "s" => 1000.0,
+        "m" => 60_000.0,
+        "h" => 3_600_000.0,
+        _ => return None,
+    };
+    Some(number * factor)
+}
+
+/// Parse the `Latency Distribution` section printed by `wrk --latency` into a list of
+/// (percentile, latency in milliseconds) pairs. The HdrHistogram section of wrk2 is supported.
+pub(crate) fn latency_distribution(output: &str) -> Vec<(f64, f64)> {
+    output
+        .lines()
+        .skip_while(|line| !line.trim().starts_with("Latency Distribution"))
+        .skip(1)
+        .map_while(|line| {
+            let mut fields = line.split_whitespace();
+            let percentile = number(fields.next()?.strip_suffix('%')?)?;
+            let latency = time_ms(fields.next()?)?;
+            Some((percentile, latency))
+        })
+        .collect()
+}
+
+/// Error counters printed by wrk in its human readable summary. Lines are omitted by wrk when
+/// all their counters are zero.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct ErrorSummary {
+    pub(crate) connect: f64,
+    pub(crate) read: f64,
+    pub(crate) write: f64,
+    pub(crate) timeout: f64,
+    pub(crate) status: f64,
+}
+
+/// Parse the `Socket errors: connect x, read y, write z, timeout w` and
+/// `Non-2xx or 3xx responses: n` lines of the wrk summary.
+pub(crate) fn error_summary(output: &str) -> ErrorSummary {
+    let mut summary = ErrorSummary::default();
+    for line in output.lines().map(str::trim) {
+        if let Some(errors) = line.strip_prefix("Socket errors:") {
+            for error in errors.split(',') {
+                let mut fields = error.split_whitespace();
+                let (Some(kind), Some(Some(count))) = (fields.next(), fields.next().map(number)) else {
+                    continue;
+                };
+                match kind {
+                    "connect" => summary.connect = count,
+                    "read" => summary.read = count,
+                    "write" => summary.write = count,
+                    "timeout" => summary.timeout = count,
+                    _ => {}
+                }
+            }
+        } else if let Some(count) = line.strip_prefix("Non-2xx or 3xx responses:") {
+            summary.status = number(count).unwrap_or_default();
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_latency_distribution() {
+        let output = r#"Running 10s test @ http://127.0.0.1:8080/
+  2 threads and 10 connections
+  Thread Stats   Avg      Stdev     Max   +/- Stdev
+    Latency   635.91us    0.89ms  12.92ms   93.69%
+    Req/Sec     8.68k     1.10k   11.40k    68.50%
+  Latency Distribution
+     50%  520.00us
+     75%  610.00us
+     90%  720.00us
+     99%    4.14ms
+  172867 requests in 10.00s, 19.95MB read
+Requests/sec:  17286.03
+Transfer/sec:      1.99MB
+"#;
+        let distribution = latency_distribution(output);
+        assert_eq!(distribution.len(), 4);
+        assert_eq!(distribution[0].0, 50.0);
+        assert!((distribution[0].1 - 0.52).abs() < f64::EPSILON);
+        assert_eq!(distribution[3], (99.0, 4.14));
+        assert!(latency_distribution("Requests/sec: 1.0").is_empty());
+    }
+
+    #[test]
+    fn parse_localized_numbers() {
+        assert_eq!(number("1,04"), Some(1.04));
+        assert_eq!(number(" 17286.03 "), Some(17286.03));
+        assert_eq!(number("1,000.5"), None);
+        let output = "  Latency Distribution\n     50%  520,00us\n     99%    4,14ms\n";
+        assert_eq!(latency_distribution(output), vec![(50.0, 0.52), (99.0, 4.14)]);
+
+        let json = "{\n    \"requests\": 1234,00,\n    \"requests_sec\": 123,40,\n    \"errors_mismatch\": 0,00\n}\n";
+        let normalized = normalize_json_numbers(json);
+        let value: serde_json::Value = serde_json::from_str(&normalized).unwrap();
+        assert_eq!(value["requests"], 1234.0);
+        assert_eq!(value["requests_sec"], 123.4);
+        assert_eq!(value["errors_mismatch"], 0.0);
+        assert_eq!(
+            normalize_json_numbers("{\n    \"requests\": 1.00\n}"),
+            "{\n    \"requests\": 1.00\n}"
+        );
+    }
+
+    #[test]
+    fn parse_error_summary() {
+        let output = r#"  172867 requests in 10.00s, 19.95MB read
+  Socket errors: connect 3, read 12, write 0, timeout 7
+  Non-2xx or 3xx responses: 42
+Requests/sec:  17286.03
+"#;
+        let summary = error_summary(output);
+        assert_eq!(
+            summary,
+            ErrorSummary {
+                connect: 3.0,
+                read: 12.0,
+                write: 0.0,
+                timeout: 7.0,
+                status: 42.0
+            }
+        );
+        assert_eq!(error_summary("Requests/sec:  17286.03"), ErrorSummary::default());
+    }
+
+    #[test]
+    fn parse_output_corpus() {
+        let corpus = [
+            (include_str!("../tests/corpus/wrk-4.0.2.txt"), 17286.03, 4),
+            (include_str!("../tests/corpus/wrk-4.1.0.txt"), 24278.51, 4),
+            (
+                include_str!("../tests/corpus/wrk-4.2.0-luajit-warnings.txt"),
+                14337.06,
+                4,
+            ),
+            (include_str!("../tests/corpus/wrk2-4.0.0.txt"), 1998.12, 8),
+        ];
+        for (output, requests_sec, percentiles) in corpus {
+            let json = json_result(output).unwrap();
+            let result: crate::WrkResult = serde_json::from_str(&json).unwrap();
+            assert_eq!(*result.requests_sec(), requests_sec);
+            assert_eq!(latency_distribution(output).len(), percentiles);
+        }
+        let summary = error_summary(include_str!("../tests/corpus/wrk-4.1.0.txt"));
+        assert_eq!((summary.timeout, summary.status), (12.0, 37.0));
+        assert_eq!(json_result("Running 1s test\nJSON{\n    \"requests\": 1.00,\n"), None);
+        assert_eq!(json_result("Running 1s test"), None);
+    }
+}
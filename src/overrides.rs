@@ -0,0 +1,157 @@
+//! Overrides of the configuration read from `WRK_API_BENCH_*` environment variables when
+//! benchmarks start, so CI can retarget them without code changes.
+use std::{env, path::PathBuf, sync::Arc, time::Duration};
+
+use crate::{Benchmark, Result, Wrk, WrkError};
+
+/// Prefix of the environment variables read by [`Overrides::from_env`].
+const PREFIX: &str = "WRK_API_BENCH_";
+
+/// Configuration values overridden by the environment.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Overrides {
+    url: Option<String>,
+    history_dir: Option<PathBuf>,
+    project: Option<String>,
+    max_error_percentage: Option<u8>,
+    threads: Option<u16>,
+    connections: Option<u16>,
+    duration: Option<Duration>,
+}
+
+impl Overrides {
+    /// Read `WRK_API_BENCH_URL`, `_HISTORY_DIR`, `_PROJECT`, `_MAX_ERROR_PERCENTAGE`, `_THREADS`,
+    /// `_CONNECTIONS` and `_DURATION` from the process environment.
+    pub(crate) fn from_env() -> Result<Self> {
+        Self::from_lookup(|name| env::var(name).ok())
+    }
+
+    fn from_lookup<F: Fn(&str) -> Option<String>>(lookup: F) -> Result<Self> {
+        let var = |name: &str| {
+            lookup(&format!("{}{}", PREFIX, name))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let number = |name: &str| -> Result<Option<u16>> {
+            var(name)
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|_| WrkError::Config(format!("{}{} is not a number: {}", PREFIX, name, value)))
+                })
+                .transpose()
+        };
+        let max_error_percentage = number("MAX_ERROR_PERCENTAGE")?
+            .map(|value| {
+                u8::try_from(value)
+                    .ok()
+                    .filter(|value| *value <= 100)
+                    .ok_or_else(|| WrkError::Config(format!("{}MAX_ERROR_PERCENTAGE must be at most 100", PREFIX)))
+            })
+            .transpose()?;
+        Ok(Self {
+            url: var("URL"),
+            history_dir: var("HISTORY_DIR").map(PathBuf::from),
+            project: var("PROJECT"),
+            max_error_percentage,
+            threads: number("THREADS")?,
+            connections: number("CONNECTIONS")?,
+            duration: var("DURATION").map(|value| duration(&value)).transpose()?,
+        })
+    }
+
+    /// Apply the overrides to `wrk` and `benchmarks`, sharing `wrk` when nothing is overridden.
+    pub(crate) fn apply(&self, wrk: &Arc<Wrk>, benchmarks: &[Benchmark]) -> (Arc<Wrk>, Vec<Benchmark>) {
+        let wrk = if self.url.is_some()
+            || self.history_dir.is_some()
+            || self.project.is_some()
+            || self.max_error_percentage.is_some()
+        {
+            let mut wrk = wrk.as_ref().clone();
+            if let Some(url) = &self.url {
+                info!("Overriding the target URL with {}", url);
+                wrk.set_url(url.clone());
+            }
+            if let Some(history_dir) = &self.history_dir {
+                wrk.set_history_dir(history_dir.clone());
+            }
+            if let Some(project) = &self.project {
+                wrk.set_project(Some(project.clone()));
+            }
+            if let Some(max_error_percentage) = self.max_error_percentage {
+                wrk.set_max_error_percentage(max_error_percentage);
+            }
+            Arc::new(wrk)
+        } else {
+            wrk.clone()
+        };
+        let benchmarks = benchmarks
+            .iter()
+            .cloned()
+            .map(|mut benchmark| {
+                if let Some(threads) = self.threads {
+                    benchmark.set_threads(threads);
+                }
+                if let Some(connections) = self.connections {
+                    benchmark.set_connections(connections);
+                }
+                if let Some(duration) = self.duration {
+                    benchmark.set_duration(duration);
+                }
+                benchmark
+            })
+            .collect();
+        (wrk, benchmarks)
+    }
+}
+
+/// Parse a duration in seconds, optionally suffixed by its unit, IE: `30`, `30s`, `2m` or `1h`.
+fn duration(value: &str) -> Result<Duration> {
+    let (number, factor) = match value.char_indices().last() {
+        Some((i, 's')) => (&value[..i], 1),
+        Some((i, 'm')) => (&value[..i], 60),
+        Some((i, 'h')) => (&value[..i], 3600),
+        _ => (value, 1),
+    };
+    number
+        .parse::<u64>()
+        .map(|number| Duration::from_secs(number * factor))
+        .map_err(|_| WrkError::Config(format!("{}DURATION is not a valid duration: {}", PREFIX, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::WrkBuilder;
+
+    #[test]
+    fn env_overrides() {
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("WRK_API_BENCH_URL", "http://staging:8080/api"),
+            ("WRK_API_BENCH_DURATION", "2m"),
+            ("WRK_API_BENCH_CONNECTIONS", " 64 "),
+            ("WRK_API_BENCH_PROJECT", ""),
+        ]);
+        let overrides = Overrides::from_lookup(|name| env.get(name).map(|v| v.to_string())).unwrap();
+        let wrk = Arc::new(
+            WrkBuilder::default()
+                .url("http://127.0.0.1:13742".to_string())
+                .build()
+                .unwrap(),
+        );
+        let (overridden, benchmarks) = overrides.apply(&wrk, &[Benchmark::new(2, 8, 10)]);
+        assert_eq!(overridden.url(), "http://staging:8080/api");
+        assert_eq!(overridden.project(), &None);
+        assert_eq!(benchmarks, vec![Benchmark::new(2, 64, 120)]);
+
+        let (shared, _) = Overrides::default().apply(&wrk, &[]);
+        assert!(Arc::ptr_eq(&shared, &wrk));
+        assert!(Overrides::from_lookup(|_| Some("many".to_string())).is_err());
+        assert!(Overrides::from_lookup(
+            |name| (name == "WRK_API_BENCH_MAX_ERROR_PERCENTAGE").then(|| "101".to_string())
+        )
+        .is_err());
+    }
+}
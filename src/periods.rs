@@ -0,0 +1,162 @@
+//! Comparison of a run against the best comparable run of several history periods, giving
+//! short-term and long-term context in a single report.
+use std::fmt;
+
+use crate::table::{format, Attr, Cell, Row, Table};
+
+use crate::{HistoryPeriod, LatencyUnit, Metric, NumberFormat, Variance, WrkResult};
+
+/// New run compared with every period of [`crate::Wrk::variance_multi`].
+#[derive(Debug, Clone)]
+pub struct MultiVariance {
+    new: WrkResult,
+    periods: Vec<(HistoryPeriod, Option<WrkResult>)>,
+    latency_unit: LatencyUnit,
+    number_format: NumberFormat,
+}
+
+impl MultiVariance {
+    pub(crate) fn new(new: WrkResult, periods: Vec<(HistoryPeriod, Option<WrkResult>)>) -> Self {
+        Self {
+            new,
+            periods,
+            latency_unit: LatencyUnit::default(),
+            number_format: NumberFormat::default(),
+        }
+    }
+
+    /// Display latencies using the given unit.
+    pub fn with_latency_unit(mut self, latency_unit: LatencyUnit) -> Self {
+        self.latency_unit = latency_unit;
+        self
+    }
+
+    /// Display numbers using the given separators and precision.
+    pub fn with_number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
+    }
+
+    /// Best result of the new run.
+    pub fn new_run(&self) -> &WrkResult {
+        &self.new
+    }
+
+    /// Best comparable result of every period, missing when the period holds none.
+    pub fn periods(&self) -> &[(HistoryPeriod, Option<WrkResult>)] {
+        &self.periods
+    }
+
+    /// Variance of the new run against every period, missing when the period holds no
+    /// comparable run.
+    pub fn variances(&self) -> Vec<(HistoryPeriod, Option<Variance>)> {
+        self.periods
+            .iter()
+            .map(|(period, old)| {
+                let variance = old.as_ref().map(|old| Variance::new(self.new.clone(), old.clone()));
+                (period.clone(), variance)
+            })
+            .collect()
+    }
+
+    /// Formatting of the displayed values.
+    fn display(&self) -> Variance {
+        Variance {
+            latency_unit: self.latency_unit,
+            number_format: self.number_format,
+            ..Default::default()
+        }
+    }
+
+    /// Cell of `metric` of the best run of a period, with its variance.
+    fn cell(&self, display: &Variance, metric: Metric, old: &Option<WrkResult>) -> String {
+        match old {
+            Some(old) => format!(
+                "{} ({})",
+                display.value(metric, old),
+                self.number_format
+                    .signed_percent(Variance::calculate(&self.new.get(metric), &old.get(metric)))
+            ),
+            None => "-".to_string(),
+        }
+    }
+
+    /// Render the comparison as a GitHub flavoured markdown table.
+    pub fn to_github_markdown(&self) -> String {
+        let mut result = String::from("### Rust Wrk benchmark report by period:\n");
+        if let Some(benchmark) = self.new.benchmark() {
+            result += &format!("#### Benchmark: {}\n\n", benchmark.name());
+        }
+        result += "|Measurement|Current|";
+        for (period, _) in &self.periods {
+            result += &format!("{:?}|", period);
+        }
+        result += "\n|-|-|";
+        result += &"-|".repeat(self.periods.len());
+        result += "\n";
+        let display = self.display();
+        for metric in Metric::iter() {
+            result += &format!("|{}|{}|", display.label(metric), display.value(metric, &self.new));
+            for (_, old) in &self.periods {
+                result += &format!("{}|", self.cell(&display, metric, old));
+            }
+            result += "\n";
+        }
+        result
+    }
+}
+
+impl fmt::Display for MultiVariance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_CLEAN);
+        let mut titles = vec!["Measurement".to_string(), "Current".to_string()];
+        titles.extend(self.periods.iter().map(|(period, _)| format!("{:?}", period)));
+        table.add_row(Row::new(
+            titles.iter().map(|t| Cell::new(t).with_style(Attr::Bold)).collect(),
+        ));
+        let display = self.display();
+        for metric in Metric::iter() {
+            let mut cells = vec![
+                Cell::new(&display.label(metric)).with_style(Attr::Bold),
+                Cell::new(&display.value(metric, &self.new)),
+            ];
+            cells.extend(
+                self.periods
+                    .iter()
+                    .map(|(_, old)| Cell::new(&self.cell(&display, metric, old))),
+            );
+            table.add_row(Row::new(cells));
+        }
+        writeln!(f, "## Rust Wrk benchmark report by period:")?;
+        write!(f, "{}", table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::result_builder;
+
+    #[test]
+    fn render_periods() {
+        let result = |requests_sec: f64| result_builder(requests_sec, 2.5).build().unwrap();
+        let multi = MultiVariance::new(
+            result(110.0),
+            vec![(HistoryPeriod::Day, Some(result(100.0))), (HistoryPeriod::Week, None)],
+        );
+        let markdown = multi.to_github_markdown();
+        assert!(markdown.contains("|Measurement|Current|Day|Week|\n|-|-|-|-|\n"));
+        assert!(markdown.contains("|Requests/sec|110|100 (+10.00%)|-|"));
+        assert!(markdown.contains("|P99 latency|2.50 ms|2.50 ms (0.00%)|-|"));
+        assert!(multi.variances()[1].1.is_none());
+        assert!(multi.to_string().contains("100 (+10.00%)"));
+
+        let localized = multi
+            .with_latency_unit(LatencyUnit::Millis)
+            .with_number_format(NumberFormat::locale("de_DE"));
+        assert!(localized
+            .to_github_markdown()
+            .contains("|P99 latency (ms)|2,50|2,50 (0,00%)|-|"));
+    }
+}
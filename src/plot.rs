@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     io::Write,
     path::{Path, PathBuf},
     process::{Command, Stdio},
@@ -6,8 +7,42 @@ use std::{
 
 use tempfile::NamedTempFile;
 
-use crate::{wrk::Benchmarks, Result, WrkError};
+use crate::{wrk::Benchmarks, Result, WrkError, WrkResult};
 
+/// Format of the dates written in the data files, matching the `timefmt` of the scripts.
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Field of a row of a comma separated data file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field<'a> {
+    /// Quoted string, IE: a date or a label.
+    Text(&'a str),
+    /// Unquoted number.
+    Number(f64),
+}
+
+/// Serialize a row of a data file read with `set datafile separator ","`. Double quotes can
+/// not be escaped inside gnuplot data fields, so they are replaced by single quotes.
+fn data_row(fields: &[Field]) -> String {
+    let fields: Vec<_> = fields
+        .iter()
+        .map(|field| match field {
+            Field::Text(text) => format!("\"{}\"", text.replace('"', "'").replace(['\n', '\r'], " ")),
+            Field::Number(number) => number.to_string(),
+        })
+        .collect();
+    fields.join(",") + "\n"
+}
+
+/// Double quoted gnuplot string literal of `value`.
+fn quote(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+    )
+}
+
+/// Gnuplot wrapper used to plot benchmarks history.
 #[derive(Debug, Clone)]
 pub struct Gnuplot {
     title: String,
@@ -15,6 +50,7 @@ pub struct Gnuplot {
 }
 
 impl Gnuplot {
+    /// Create a new plotter writing a PNG image to `output`.
     pub fn new(title: &str, output: &Path) -> Self {
         Self {
             title: title.to_string(),
@@ -22,7 +58,14 @@ impl Gnuplot {
         }
     }
 
+    /// Plot requests per second over time for the given benchmarks.
     pub fn plot(&self, benchmarks: &Benchmarks) -> Result<()> {
+        let (data, script) = self.history(benchmarks)?;
+        self.run(&data, &script)
+    }
+
+    /// Data file and script of [`Gnuplot::plot`].
+    fn history(&self, benchmarks: &Benchmarks) -> Result<(String, String)> {
         if benchmarks.len() < 2 {
             return Err(WrkError::Plot(format!(
                 "There are {} availble datapoints. Unable to plot history with less than 2 datapoints",
@@ -31,7 +74,7 @@ impl Gnuplot {
         }
         let dates: Vec<_> = benchmarks
             .iter()
-            .map(|b| b.date().format("%Y-%m-%d-%H:%M:%S").to_string())
+            .map(|b| b.date().format(DATE_FORMAT).to_string())
             .collect();
         let serie: Vec<_> = benchmarks.iter().map(|b| *b.requests_sec() as u64).collect();
         let min_x = dates.iter().min().unwrap();
@@ -40,29 +83,168 @@ impl Gnuplot {
         let min_y = (min_y - (min_y * 0.15)) as u64;
         let max_y = *serie.iter().max().unwrap_or(&1000) as f64;
         let max_y = (max_y + (max_y * 0.15)) as u64;
-        let mut data_file = NamedTempFile::new()?;
-        for (i, b) in benchmarks.iter().enumerate() {
-            data_file.write_all(format!("{} {}\n", dates[i], b.requests_sec()).as_bytes())?;
+        // One series per label, falling back to the key, so differently labelled runs are not
+        // drawn as a single line. A single unlabelled series keeps no legend.
+        let mut series: BTreeMap<String, Vec<(&WrkResult, &String)>> = BTreeMap::new();
+        for (result, date) in benchmarks.iter().zip(&dates) {
+            let name = result.benchmark().as_ref().map_or_else(String::new, |b| b.name());
+            series.entry(name).or_default().push((result, date));
         }
-        let gnuplot = format!(
+        let labelled = benchmarks
+            .iter()
+            .any(|b| b.benchmark().as_ref().is_some_and(|b| b.label().is_some()));
+        let mut data = String::new();
+        let mut plots = Vec::new();
+        for (index, (name, results)) in series.iter().enumerate() {
+            for (result, date) in results {
+                data += &data_row(&[Field::Text(date), Field::Number(*result.requests_sec())]);
+            }
+            // Two blank lines separate the datasets addressed by `index`.
+            data += "\n\n";
+            let legend = if (series.len() > 1 || labelled) && !name.is_empty() {
+                format!("title {}", quote(name))
+            } else {
+                "notitle".to_string()
+            };
+            plots.push(format!(
+                "data index {} using 1:2 {} with linespoints linetype {} linewidth 2",
+                index,
+                legend,
+                6 + index
+            ));
+        }
+        let script = format!(
             r#"set xdata time
-set timefmt "%Y-%m-%d-%H:%M:%S"
+set timefmt "{}"
 set format x "%m/%y/%d %H:%M:%S"
 set xrange ["{}":"{}"]
 set yrange [{}:{}]
-set key off
+set key top left
 set xtics rotate by -45
-set title "{}"
-set terminal png
-set output "{}"
-plot "{}" using 1:2 with linespoints linetype 6 linewidth 2"#,
+set title {}
+plot {}
+"#,
+            DATE_FORMAT,
             min_x,
             max_x,
             min_y,
             max_y,
-            self.title,
-            self.output.display(),
-            data_file.path().display()
+            quote(&self.title),
+            plots.join(", \\\n     ")
+        );
+        Ok((data, script))
+    }
+
+    /// Plot the p50, p90 and p99 latency bands over time of every benchmark configuration, one
+    /// chart per [`crate::Benchmark::key`], to monitor tail latency drift.
+    pub fn percentiles(&self, benchmarks: &Benchmarks) -> Result<()> {
+        let (data, script) = self.percentile_bands(benchmarks)?;
+        self.run(&data, &script)
+    }
+
+    /// Data file and script of [`Gnuplot::percentiles`].
+    fn percentile_bands(&self, benchmarks: &Benchmarks) -> Result<(String, String)> {
+        let series = percentile_series(benchmarks);
+        if series.is_empty() {
+            return Err(WrkError::Plot(
+                "Unable to plot percentiles without a benchmark with 2 datapoints".to_string(),
+            ));
+        }
+        let mut data = String::new();
+        let mut plots = Vec::new();
+        for (index, (key, results)) in series.iter().enumerate() {
+            for result in results {
+                data += &data_row(&[
+                    Field::Text(&result.date().format(DATE_FORMAT).to_string()),
+                    Field::Number(*result.p50_latency_ms()),
+                    Field::Number(*result.p90_latency_ms()),
+                    Field::Number(*result.p99_latency_ms()),
+                ]);
+            }
+            // Two blank lines separate the datasets addressed by `index`.
+            data += "\n\n";
+            plots.push(format!(
+                r#"set title {}
+plot data index {i} using 1:3:4 with filledcurves fillstyle transparent solid 0.3 title "p90-p99", \
+     data index {i} using 1:2:3 with filledcurves fillstyle transparent solid 0.5 title "p50-p90", \
+     data index {i} using 1:2 with linespoints linewidth 2 title "p50""#,
+                quote(key),
+                i = index
+            ));
+        }
+        let script = format!(
+            r#"set xdata time
+set timefmt "{}"
+set format x "%m/%d %H:%M"
+set xtics rotate by -45
+set key top left
+set ylabel "Latency (ms)"
+set terminal png size 1024,{}
+set multiplot layout {},1 title {}
+{}
+unset multiplot
+"#,
+            DATE_FORMAT,
+            series.len() * 320,
+            series.len(),
+            quote(&self.title),
+            plots.join("\n")
+        );
+        Ok((data, script))
+    }
+
+    /// Plot a clustered bar chart with a group per row, IE: a benchmark configuration, and a
+    /// bar per column, IE: a target.
+    pub fn bars(&self, columns: &[String], rows: &[(String, Vec<f64>)]) -> Result<()> {
+        let (data, script) = self.clustered_bars(columns, rows)?;
+        self.run(&data, &script)
+    }
+
+    /// Data file and script of [`Gnuplot::bars`].
+    fn clustered_bars(&self, columns: &[String], rows: &[(String, Vec<f64>)]) -> Result<(String, String)> {
+        if rows.is_empty() || columns.is_empty() {
+            return Err(WrkError::Plot("Unable to plot bars without datapoints".to_string()));
+        }
+        let header: Vec<_> = std::iter::once("benchmark")
+            .chain(columns.iter().map(String::as_str))
+            .map(Field::Text)
+            .collect();
+        let mut data = data_row(&header);
+        for (row, values) in rows {
+            let fields: Vec<_> = std::iter::once(Field::Text(row))
+                .chain(values.iter().map(|v| Field::Number(*v)))
+                .collect();
+            data += &data_row(&fields);
+        }
+        let plots: Vec<_> = (0..columns.len())
+            .map(|i| format!("data using {}:xtic(1) title columnheader", i + 2))
+            .collect();
+        let script = format!(
+            r#"set style data histograms
+set style histogram clustered gap 1
+set style fill solid border -1
+set yrange [0:*]
+set key outside
+set xtics rotate by -45
+set title {}
+plot {}
+"#,
+            quote(&self.title),
+            plots.join(", ")
+        );
+        Ok((data, script))
+    }
+
+    /// Write `data` to a temporary file and run `script` reading it through the `data`
+    /// variable.
+    fn run(&self, data: &str, script: &str) -> Result<()> {
+        let mut data_file = NamedTempFile::new()?;
+        data_file.write_all(data.as_bytes())?;
+        let gnuplot = format!(
+            "set datafile separator \",\"\ndata = {}\nset terminal png\nset output {}\n{}",
+            quote(&data_file.path().to_string_lossy()),
+            quote(&self.output.to_string_lossy()),
+            script
         );
         let mut child = Command::new("gnuplot").stdin(Stdio::piped()).spawn()?;
         if let Some(mut stdin) = child.stdin.take() {
@@ -81,3 +263,116 @@ plot "{}" using 1:2 with linespoints linetype 6 linewidth 2"#,
         }
     }
 }
+
+/// Successful results with latency percentiles grouped by benchmark key, oldest first, leaving
+/// out the keys with less than 2 datapoints.
+fn percentile_series(benchmarks: &Benchmarks) -> Vec<(String, Vec<&WrkResult>)> {
+    let mut series: BTreeMap<String, Vec<&WrkResult>> = BTreeMap::new();
+    for result in benchmarks.iter().filter(|r| *r.success() && *r.p99_latency_ms() > 0.0) {
+        if let Some(benchmark) = result.benchmark() {
+            series.entry(benchmark.key()).or_default().push(result);
+        }
+    }
+    series
+        .into_iter()
+        .filter(|(_, results)| results.len() >= 2)
+        .map(|(key, mut results)| {
+            results.sort_by_key(|r| *r.date());
+            (key, results)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{Benchmark, WrkResultBuilder};
+
+    fn result(threads: u16, requests_sec: f64, p99_latency_ms: f64, minute: u32) -> WrkResult {
+        WrkResultBuilder::default()
+            .success(true)
+            .requests_sec(requests_sec)
+            .p50_latency_ms(p99_latency_ms / 4.0)
+            .p90_latency_ms(p99_latency_ms / 2.0)
+            .p99_latency_ms(p99_latency_ms)
+            .date(Utc.with_ymd_and_hms(2024, 3, 1, 12, minute, 0).unwrap())
+            .benchmark(Benchmark::new(threads, 1, 1))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn data_rows() {
+        assert_eq!(
+            data_row(&[Field::Text("say \"hi\"\nnow"), Field::Number(1.5), Field::Number(2.0)]),
+            "\"say 'hi' now\",1.5,2\n"
+        );
+        assert_eq!(quote(r#"C:\ "quoted""#), r#""C:\\ \"quoted\"""#);
+    }
+
+    #[test]
+    fn percentile_series_by_key() {
+        let benchmarks = vec![
+            result(1, 100.0, 3.0, 5),
+            result(1, 100.0, 2.0, 1),
+            result(1, 100.0, 0.0, 3),
+            result(2, 100.0, 4.0, 1),
+        ];
+        let series = percentile_series(&benchmarks);
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].0, "1-1-1");
+        let p99: Vec<_> = series[0].1.iter().map(|r| *r.p99_latency_ms()).collect();
+        assert_eq!(p99, vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn history_series_per_label() {
+        let gnuplot = Gnuplot::new("history", Path::new("history.png"));
+        let (data, script) = gnuplot
+            .history(&vec![result(1, 1000.0, 4.0, 1), result(1, 1100.0, 4.0, 2)])
+            .unwrap();
+        assert_eq!(data.matches("\n\n\n").count(), 1);
+        assert!(script.contains("plot data index 0 using 1:2 notitle with"));
+
+        let mut stress = result(2, 3000.0, 9.0, 3);
+        stress
+            .benchmark_mut()
+            .as_mut()
+            .unwrap()
+            .set_label(Some("stress".to_string()));
+        let (data, script) = gnuplot
+            .history(&vec![result(1, 1000.0, 4.0, 1), stress, result(1, 1100.0, 4.0, 2)])
+            .unwrap();
+        assert_eq!(data.matches("\n\n\n").count(), 2);
+        assert!(script.contains("data index 0 using 1:2 title \"1-1-1\""));
+        assert!(script.contains("data index 1 using 1:2 title \"stress\""));
+    }
+
+    #[test]
+    fn golden_scripts() {
+        let gnuplot = Gnuplot::new("Wrk \"history\"", Path::new("history.png"));
+        let mut labelled = result(1, 1200.0, 8.0, 2);
+        labelled
+            .benchmark_mut()
+            .as_mut()
+            .unwrap()
+            .set_label(Some("get users".to_string()));
+        let (data, script) = gnuplot.history(&vec![result(1, 1000.0, 4.0, 1), labelled]).unwrap();
+        assert_eq!(data, include_str!("../tests/golden/history.csv"));
+        assert_eq!(script, include_str!("../tests/golden/history.gp"));
+
+        let benchmarks = vec![result(1, 100.0, 4.0, 1), result(1, 100.0, 8.0, 2)];
+        let (data, script) = gnuplot.percentile_bands(&benchmarks).unwrap();
+        assert_eq!(data, include_str!("../tests/golden/percentiles.csv"));
+        assert_eq!(script, include_str!("../tests/golden/percentiles.gp"));
+
+        let columns = vec!["actix web".to_string(), "axum".to_string()];
+        let rows = vec![("1-1-1".to_string(), vec![100.0, 120.5])];
+        let (data, script) = gnuplot.clustered_bars(&columns, &rows).unwrap();
+        assert_eq!(data, include_str!("../tests/golden/bars.csv"));
+        assert_eq!(script, include_str!("../tests/golden/bars.gp"));
+    }
+}
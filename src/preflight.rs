@@ -0,0 +1,80 @@
+//! System prerequisite checks run before starting wrk.
+use std::{fs, process::Command, sync::OnceLock};
+
+use crate::{Benchmark, Result, WrkError};
+
+/// File descriptors needed on top of the open connections (stdio, script, sockets for DNS).
+const FD_MARGIN: u64 = 32;
+/// Linux ephemeral port range.
+const PORT_RANGE: &str = "/proc/sys/net/ipv4/ip_local_port_range";
+
+/// Check the benchmarks can run on this system, failing early with a description of the fix
+/// instead of letting wrk silently produce connect errors.
+pub(crate) fn check(benchmarks: &[Benchmark]) -> Result<()> {
+    for benchmark in benchmarks {
+        if benchmark.connections() < benchmark.threads() {
+            return Err(WrkError::Exec(format!(
+                "Benchmark {} has {} connections for {} threads: connections must be greater or equal to threads",
+                benchmark.key(),
+                benchmark.connections(),
+                benchmark.threads()
+            )));
+        }
+    }
+    let connections = match benchmarks.iter().map(|b| *b.connections() as u64).max() {
+        Some(connections) => connections,
+        None => return Ok(()),
+    };
+    match open_files_limit() {
+        Some(limit) if connections + FD_MARGIN > limit => {
+            return Err(WrkError::Exec(format!(
+                "Open files limit {} is too low for {} connections, raise it with `ulimit -n {}`",
+                limit,
+                connections,
+                connections + FD_MARGIN
+            )));
+        }
+        Some(_) => {}
+        None => warn!("Unable to read the open files limit, skipping check"),
+    }
+    if let Some((low, high)) = ephemeral_port_range() {
+        let ports = high.saturating_sub(low) + 1;
+        if connections > ports {
+            warn!(
+                "Ephemeral port range {}-{} allows {} ports, less than {} connections. Widen it with \
+                 `sysctl -w net.ipv4.ip_local_port_range=\"1024 65535\"`",
+                low, high, ports, connections
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Soft limit of open file descriptors, `None` when unlimited or unknown. The shell is only
+/// spawned by the first check of the process.
+fn open_files_limit() -> Option<u64> {
+    static LIMIT: OnceLock<Option<u64>> = OnceLock::new();
+    *LIMIT.get_or_init(|| {
+        let output = Command::new("sh").args(["-c", "ulimit -n"]).output().ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    })
+}
+
+/// Local ephemeral port range, only available on Linux.
+fn ephemeral_port_range() -> Option<(u64, u64)> {
+    let range = fs::read_to_string(PORT_RANGE).ok()?;
+    let mut ports = range.split_whitespace().map(|p| p.parse().ok());
+    Some((ports.next()??, ports.next()??))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connections_lower_than_threads() {
+        assert!(matches!(check(&[Benchmark::new(8, 4, 1)]), Err(WrkError::Exec(_))));
+        assert!(check(&[Benchmark::new(1, 1, 1)]).is_ok());
+        assert!(check(&[]).is_ok());
+    }
+}
@@ -0,0 +1,122 @@
+//! Ready-made configurations for common benchmarking contexts, applied with
+//! [`crate::WrkBuilder::preset`] and [`crate::BenchmarkBuilder::preset`].
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Benchmark, BenchmarkBuilder, Enforcement, Guidance, Metric, MetricRule, RecordPolicy, Thresholds, WrkBuilder,
+};
+
+/// Context a suite runs in, selecting its duration, benchmark matrix, thresholds, record policy
+/// and output mode. Every setting remains overridable after applying the preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Profile {
+    /// Continuous integration: a small matrix of 30 seconds runs, recorded only when all of
+    /// them succeed, quiet output and strict regression thresholds.
+    Ci,
+    /// Local iteration: a single short run, never recorded, with the wrk output on the console.
+    Local,
+    /// Stress testing: the [`BenchmarkBuilder::exponential`] matrix of 1 minute runs with a
+    /// pause between them, all recorded.
+    Stress,
+}
+
+impl Profile {
+    /// Benchmark matrix of the preset.
+    pub fn benchmarks(&self) -> Vec<Benchmark> {
+        match self {
+            Self::Ci => [(2, 32, "ci-low-concurrency"), (4, 64, "ci-medium-concurrency")]
+                .into_iter()
+                .map(|(threads, connections, label)| {
+                    let mut benchmark = Benchmark::new(threads, connections, 30);
+                    benchmark.set_label(Some(label.to_string()));
+                    benchmark
+                })
+                .collect(),
+            Self::Local => vec![Benchmark::new(1, 16, 10)],
+            Self::Stress => BenchmarkBuilder::exponential(Some(Duration::from_secs(60))),
+        }
+    }
+
+    /// Regression thresholds of the preset.
+    pub fn thresholds(&self) -> Thresholds {
+        match self {
+            Self::Ci => Thresholds::max_regression(5.0),
+            Self::Local => Thresholds::max_regression(10.0),
+            Self::Stress => {
+                let mut thresholds = Thresholds::max_regression(10.0);
+                thresholds.rules.insert(
+                    Metric::ErrorRate,
+                    MetricRule {
+                        ceiling: Some(0.05),
+                        ..Default::default()
+                    },
+                );
+                thresholds
+            }
+        }
+    }
+
+    /// Policy recording the results of the preset.
+    pub fn record_policy(&self) -> RecordPolicy {
+        match self {
+            Self::Ci => RecordPolicy::OnSuccess,
+            Self::Local => RecordPolicy::Never,
+            Self::Stress => RecordPolicy::Always,
+        }
+    }
+
+    pub(crate) fn apply(&self, builder: &mut WrkBuilder) {
+        let guidance = |enforcement| {
+            let mut guidance = Guidance::default();
+            guidance.set_enforcement(enforcement);
+            guidance
+        };
+        builder.record_policy(self.record_policy());
+        match self {
+            Self::Ci => builder
+                .tee_output(false)
+                .bootstrap_baseline(true)
+                .guidance(guidance(Enforcement::Reject))
+                .cooldown(Duration::from_secs(5)),
+            Self::Local => builder
+                .tee_output(true)
+                .bootstrap_baseline(true)
+                .guidance(guidance(Enforcement::Off))
+                .cooldown(Duration::ZERO),
+            Self::Stress => builder
+                .tee_output(true)
+                .max_error_percentage(5)
+                .guidance(guidance(Enforcement::Warn))
+                .cooldown(Duration::from_secs(10)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13743".to_string())
+            .preset(Profile::Ci)
+            .cooldown(Duration::from_secs(1))
+            .build()
+            .unwrap();
+        assert_eq!(*wrk.record_policy(), RecordPolicy::OnSuccess);
+        assert_eq!(*wrk.guidance().enforcement(), Enforcement::Reject);
+        assert_eq!(*wrk.cooldown(), Duration::from_secs(1));
+        assert!(!wrk.tee_output());
+
+        let benchmarks = BenchmarkBuilder::preset(Profile::Ci);
+        assert_eq!(benchmarks.len(), 2);
+        assert!(benchmarks.iter().all(|b| *b.duration() == Duration::from_secs(30)));
+        assert_eq!(BenchmarkBuilder::preset(Profile::Stress).len(), 16);
+        assert_eq!(Profile::Local.record_policy(), RecordPolicy::Never);
+        assert!(Profile::Stress.thresholds().rules.contains_key(&Metric::ErrorRate));
+    }
+}
@@ -0,0 +1,26 @@
+use std::fmt::Debug;
+
+use crate::WrkResult;
+
+/// Post-processing step applied to every [`WrkResult`] before it is recorded or reported.
+///
+/// Processors are chained on [`crate::Wrk`] and applied in order, allowing downstream users to
+/// normalize, enrich or filter results.
+pub trait ResultProcessor: Debug + Send + Sync {
+    /// Process a result, returning `None` to filter it out.
+    fn process(&self, result: WrkResult) -> Option<WrkResult>;
+}
+
+/// Filter out failed results.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SuccessfulOnly;
+
+impl ResultProcessor for SuccessfulOnly {
+    fn process(&self, result: WrkResult) -> Option<WrkResult> {
+        if *result.success() {
+            Some(result)
+        } else {
+            None
+        }
+    }
+}
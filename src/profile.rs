@@ -0,0 +1,119 @@
+//! Load shapes built by sequencing multiple [`Benchmark`]s.
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Benchmark;
+
+/// Shape of the load applied to the target, materialized via
+/// [`crate::BenchmarkBuilder::profile`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadProfile {
+    /// Connections increasing linearly from `start` to `end` over `steps` benchmarks.
+    RampUp {
+        /// Number of wrk threads.
+        threads: u16,
+        /// Connections of the first step.
+        start: u16,
+        /// Connections of the last step.
+        end: u16,
+        /// Number of steps.
+        steps: u16,
+        /// Duration of every step.
+        step_duration: Duration,
+    },
+    /// Short high-concurrency burst between two runs at the baseline concurrency.
+    Spike {
+        /// Number of wrk threads.
+        threads: u16,
+        /// Baseline connections.
+        connections: u16,
+        /// Connections during the burst.
+        spike_connections: u16,
+        /// Duration of the baseline runs.
+        duration: Duration,
+        /// Duration of the burst.
+        spike_duration: Duration,
+    },
+    /// Long constant load.
+    Soak {
+        /// Number of wrk threads.
+        threads: u16,
+        /// Number of wrk connections.
+        connections: u16,
+        /// Duration of the whole soak.
+        duration: Duration,
+    },
+}
+
+impl LoadProfile {
+    /// Sequence of benchmarks creating this load shape.
+    pub fn benchmarks(&self) -> Vec<Benchmark> {
+        let benchmark = |threads: u16, connections: u16, duration: Duration, label: String| {
+            let mut benchmark = Benchmark::new(threads, connections.max(threads), 0);
+            benchmark.set_duration(duration);
+            benchmark.set_label(Some(label));
+            benchmark
+        };
+        match *self {
+            LoadProfile::RampUp {
+                threads,
+                start,
+                end,
+                steps,
+                step_duration,
+            } => {
+                let steps = steps.max(1);
+                (0..steps)
+                    .map(|step| {
+                        let connections = if steps == 1 {
+                            end
+                        } else {
+                            let delta = (end as i32 - start as i32) * step as i32 / (steps - 1) as i32;
+                            (start as i32 + delta) as u16
+                        };
+                        let label = format!("ramp-up-{}-of-{}", step + 1, steps);
+                        benchmark(threads, connections, step_duration, label)
+                    })
+                    .collect()
+            }
+            LoadProfile::Spike {
+                threads,
+                connections,
+                spike_connections,
+                duration,
+                spike_duration,
+            } => vec![
+                benchmark(threads, connections, duration, "spike-before".to_string()),
+                benchmark(threads, spike_connections, spike_duration, "spike".to_string()),
+                benchmark(threads, connections, duration, "spike-after".to_string()),
+            ],
+            LoadProfile::Soak {
+                threads,
+                connections,
+                duration,
+            } => vec![benchmark(threads, connections, duration, "soak".to_string())],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ramp_up() {
+        let benchmarks = LoadProfile::RampUp {
+            threads: 2,
+            start: 10,
+            end: 100,
+            steps: 4,
+            step_duration: Duration::from_secs(5),
+        }
+        .benchmarks();
+        let connections: Vec<_> = benchmarks.iter().map(|b| *b.connections()).collect();
+        assert_eq!(connections, vec![10, 40, 70, 100]);
+        assert_eq!(benchmarks[3].label().as_deref(), Some("ramp-up-4-of-4"));
+    }
+}
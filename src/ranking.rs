@@ -0,0 +1,173 @@
+//! Leaderboard of results ranked by a [`Metric`], for tuning sessions comparing many
+//! configurations at once.
+use std::fmt;
+
+use crate::table::{format, Attr, Cell, Row, Table};
+
+use crate::{Metric, WrkResult};
+
+/// Ranking of results, implemented for [`crate::Benchmarks`].
+pub trait Rank {
+    /// Successful results ordered from the best to the worst `metric`. Failed runs are left out.
+    fn rank_by(&self, metric: Metric) -> Leaderboard;
+}
+
+impl Rank for [WrkResult] {
+    fn rank_by(&self, metric: Metric) -> Leaderboard {
+        let mut results: Vec<_> = self.iter().filter(|r| *r.success()).cloned().collect();
+        results.sort_by(|a, b| {
+            let order = a.get(metric).total_cmp(&b.get(metric));
+            if metric.higher_is_better() {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        let mut entries: Vec<Ranked> = Vec::with_capacity(results.len());
+        for (position, result) in results.into_iter().enumerate() {
+            let value = result.get(metric);
+            // Ties share the rank of the first result with the same value.
+            let rank = match entries.last() {
+                Some(previous) if previous.value == value => previous.rank,
+                _ => position + 1,
+            };
+            entries.push(Ranked { rank, value, result });
+        }
+        Leaderboard { metric, entries }
+    }
+}
+
+/// Result with its position in a [`Leaderboard`].
+#[derive(Debug, Clone)]
+pub struct Ranked {
+    /// Position, starting from 1 and shared by equal values.
+    pub rank: usize,
+    /// Value of the ranking metric.
+    pub value: f64,
+    /// Ranked result.
+    pub result: WrkResult,
+}
+
+/// Results ordered by a [`Metric`], returned by [`Rank::rank_by`].
+#[derive(Debug, Clone)]
+pub struct Leaderboard {
+    metric: Metric,
+    entries: Vec<Ranked>,
+}
+
+impl Leaderboard {
+    /// Metric used for the ranking.
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// Ranked results, best first.
+    pub fn entries(&self) -> &[Ranked] {
+        &self.entries
+    }
+
+    /// Best ranked result.
+    pub fn leader(&self) -> Option<&Ranked> {
+        self.entries.first()
+    }
+
+    /// Percentage difference of `value` from the leader.
+    fn gap(&self, value: f64) -> f64 {
+        match self.leader() {
+            Some(leader) if leader.value != 0.0 => (value - leader.value) / leader.value * 100.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Name of the benchmark configuration of a ranked result.
+    fn name(result: &WrkResult) -> String {
+        result
+            .benchmark()
+            .as_ref()
+            .map_or_else(|| "unknown".to_string(), |b| b.name())
+    }
+
+    /// Render the leaderboard as a GitHub flavoured markdown table.
+    pub fn to_github_markdown(&self) -> String {
+        let mut result = format!("### Rust Wrk leaderboard by {}:\\n", self.metric);
+        result += &format!(
+            "|Rank|Benchmark|{}|Gap|Requests/sec|P99 latency (ms)|Errors|\\n|-|-|-|-|-|-|-|\\n",
+            self.metric
+        );
+        for entry in &self.entries {
+            result += &format!(
+                "|{}|{}|{:.2}|{:+.2}%|{:.2}|{:.2}|{}|\\n",
+                entry.rank,
+                Self::name(&entry.result),
+                entry.value,
+                self.gap(entry.value),
+                entry.result.requests_sec(),
+                entry.result.p99_latency_ms(),
+                entry.result.errors()
+            );
+        }
+        result
+    }
+}
+
+impl fmt::Display for Leaderboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_BOX_CHARS);
+        table.set_titles(Row::new(
+            [
+                "Rank".to_string(),
+                "Benchmark".to_string(),
+                self.metric.to_string(),
+                "Gap".to_string(),
+                "Requests/sec".to_string(),
+                "P99 latency".to_string(),
+                "Errors".to_string(),
+            ]
+            .iter()
+            .map(|title| Cell::new(title).with_style(Attr::Bold))
+            .collect(),
+        ));
+        for entry in &self.entries {
+            table.add_row(Row::new(vec![
+                Cell::new(&entry.rank.to_string()),
+                Cell::new(&Self::name(&entry.result)).with_style(Attr::Bold),
+                Cell::new(&format!("{:.2}", entry.value)),
+                Cell::new(&format!("{:+.2}%", self.gap(entry.value))),
+                Cell::new(&format!("{:.2}", entry.result.requests_sec())),
+                Cell::new(&format!("{:.2}ms", entry.result.p99_latency_ms())),
+                Cell::new(&entry.result.errors().to_string()),
+            ]));
+        }
+        write!(f, "## Rust Wrk leaderboard by {}:\n{}", self.metric, table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::result_builder, Benchmark, Benchmarks};
+
+    #[test]
+    fn rank_by() {
+        let result = |success: bool, threads: u16, requests_sec: f64, p99: f64| {
+            result_builder(requests_sec, p99)
+                .success(success)
+                .benchmark(Benchmark::new(threads, 1, 1))
+                .build()
+                .unwrap()
+        };
+        let results: Benchmarks = vec![
+            result(true, 1, 100.0, 9.0),
+            result(true, 2, 200.0, 5.0),
+            result(false, 3, 900.0, 1.0),
+            result(true, 4, 200.0, 7.0),
+        ];
+        let by_rps = results.rank_by(Metric::RequestsSec);
+        let ranks: Vec<_> = by_rps.entries().iter().map(|e| (e.rank, e.value)).collect();
+        assert_eq!(ranks, vec![(1, 200.0), (1, 200.0), (3, 100.0)]);
+        assert!(by_rps.to_github_markdown().contains("|3|1-1-1|100.00|-50.00%|"));
+        let by_p99 = results.rank_by(Metric::P99Latency);
+        assert_eq!(by_p99.leader().unwrap().value, 5.0);
+    }
+}
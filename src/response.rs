@@ -0,0 +1,147 @@
+//! Validation of response bodies inside the Lua `response()` hook, so servers answering
+//! fast but wrong are caught by the benchmark.
+use serde::{Deserialize, Serialize};
+
+use crate::{headers::lua_string, lua::ScriptOptions};
+
+/// Expectation on every response body. Responses failing any check are counted as
+/// `errors_mismatch` in the [`crate::WrkResult`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseCheck {
+    /// Body contains the given substring.
+    Contains(String),
+    /// Body matches the given [Lua pattern](https://www.lua.org/manual/5.1/manual.html#5.4.1).
+    Matches(String),
+    /// Body contains the top level JSON field `name` set to `value`, serialized compactly.
+    JsonField {
+        /// Field name.
+        name: String,
+        /// Expected value.
+        value: serde_json::Value,
+    },
+}
+
+impl ResponseCheck {
+    /// Lua boolean expression evaluating the check against the `body` variable.
+    fn lua_condition(&self) -> String {
+        match self {
+            Self::Contains(text) => format!("string.find(body, {}, 1, true) ~= nil", lua_string(text)),
+            Self::Matches(pattern) => format!("string.find(body, {}) ~= nil", lua_string(pattern)),
+            Self::JsonField { name, value } => {
+                let field = serde_json::to_string(name).unwrap_or_default();
+                let value = serde_json::to_string(value).unwrap_or_default();
+                let pattern = format!("{}%s*:%s*{}", lua_pattern_escape(&field), lua_pattern_escape(&value));
+                format!("string.find(body, {}) ~= nil", lua_string(&pattern))
+            }
+        }
+    }
+}
+
+/// Case insensitive lookup of a response header, empty when missing.
+const LUA_HEADER_FUNCTION: &str = r#"local function wrk_api_bench_header(headers, name)
+    name = string.lower(name)
+    for key, value in pairs(headers) do
+        if string.lower(key) == name then
+            return value
+        end
+    end
+    return ""
+end"#;
+
+/// Lua `setup()` and `response()` hooks keeping, in globals of every thread, the responses
+/// per second, the server headers of the first response, the responses with a status outside
+/// the success statuses and the successful ones failing the checks.
+///
+/// Without success statuses, wrk's default of any status below 400 is kept.
+pub(crate) fn lua_hooks(options: &ScriptOptions) -> String {
+    let mut globals = Vec::new();
+    let mut hook = Vec::new();
+    if options.timeseries {
+        globals.push("wrk_api_bench_timeseries = {}".to_string());
+        hook.push("local second = os.time()");
+        hook.push("wrk_api_bench_timeseries[second] = (wrk_api_bench_timeseries[second] or 0) + 1");
+    }
+    let version = options.version_header.map(|header| {
+        format!(
+            "    wrk_api_bench_version = wrk_api_bench_header(headers, {})",
+            lua_string(header)
+        )
+    });
+    if options.capture_server || version.is_some() {
+        globals.push(LUA_HEADER_FUNCTION.to_string());
+        hook.push("if not wrk_api_bench_captured then");
+        hook.push("    wrk_api_bench_captured = true");
+        if options.capture_server {
+            hook.push(r#"    wrk_api_bench_server = wrk_api_bench_header(headers, "Server")"#);
+        }
+        if let Some(version) = &version {
+            hook.push(version);
+        }
+        hook.push("end");
+    }
+    if !options.success_statuses.is_empty() {
+        let statuses = options
+            .success_statuses
+            .iter()
+            .map(|s| format!("[{}] = true", s))
+            .collect::<Vec<_>>()
+            .join(", ");
+        globals.push(format!("wrk_api_bench_success_statuses = {{ {} }}", statuses));
+        globals.push("wrk_api_bench_status_errors = 0".to_string());
+        hook.push("if not wrk_api_bench_success_statuses[status] then");
+        hook.push("    wrk_api_bench_status_errors = wrk_api_bench_status_errors + 1");
+        hook.push("    return");
+        hook.push("end");
+    } else if !options.response_checks.is_empty() {
+        hook.push("if status >= 400 then");
+        hook.push("    return");
+        hook.push("end");
+    }
+    let conditions = options
+        .response_checks
+        .iter()
+        .map(|c| format!("({})", c.lua_condition()))
+        .collect::<Vec<_>>()
+        .join("\n        and ");
+    let check = format!("if not ({}) then", conditions);
+    if !options.response_checks.is_empty() {
+        globals.push("wrk_api_bench_mismatches = 0".to_string());
+        hook.push("body = body or \"\"");
+        hook.push(&check);
+        hook.push("    wrk_api_bench_mismatches = wrk_api_bench_mismatches + 1");
+        hook.push("end");
+    }
+    if hook.is_empty() {
+        return String::new();
+    }
+    format!(
+        r#"
+-- Threads are collected by setup() so done() can sum their counters.
+wrk_api_bench_threads = {{}}
+{}
+
+setup = function(thread)
+    table.insert(wrk_api_bench_threads, thread)
+end
+
+response = function(status, headers, body)
+    {}
+end
+"#,
+        globals.join("\n"),
+        hook.join("\n    ")
+    )
+}
+
+/// Escape the magic characters of a Lua pattern.
+fn lua_pattern_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if "^$()%.[]*+-?".contains(c) {
+            escaped.push('%');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
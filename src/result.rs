@@ -1,80 +1,236 @@
 use std::fmt;
 
+use crate::table::{format, Attr, Cell, Row, Table};
 use chrono::{DateTime, Utc};
 use getset::{Getters, MutGetters, Setters};
-use prettytable::{format, Attr, Cell, Row, Table};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use uuid::Uuid;
 
-use crate::{Benchmark, BenchmarkBuilder};
+use crate::{Benchmark, Diagnosis, Metric, NumberFormat, Rollup, Sample, Stall, Thresholds};
 
+/// Result of a single wrk benchmark run.
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Getters, Setters, MutGetters, Builder)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct WrkResult {
+    /// Unique identifier of the run, nil for results recorded before identifiers existed.
+    #[builder(default = "Uuid::new_v4()")]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    run_id: Uuid,
+    /// Identifier shared by the runs of the same suite, IE: a single [`crate::BenchSession::bench`]
+    /// call, nil for results recorded before identifiers existed.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    suite_id: Uuid,
+    /// Whether the run completed with an acceptable error rate.
     #[builder(default)]
     #[serde(default)]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     success: bool,
+    /// Error message of a failed run.
     #[builder(default = "String::new()")]
     #[serde(default)]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     error: String,
-    #[builder(default)]
-    #[serde(default)]
+    /// Benchmark configuration which produced this result. Failed runs and imported data
+    /// can lack one.
+    #[builder(default, setter(strip_option))]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_benchmark"
+    )]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
-    benchmark: Benchmark,
+    benchmark: Option<Benchmark>,
+    /// Date and time of the run.
     #[builder(default = "Utc::now()")]
     #[serde(default = "Utc::now")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     date: DateTime<Utc>,
+    /// Total number of requests.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     requests: f64,
+    /// Total number of errors.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     errors: f64,
+    /// Total number of successful requests.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     successes: f64,
+    /// Requests per second.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     requests_sec: f64,
+    /// Average latency in milliseconds.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     avg_latency_ms: f64,
+    /// Minimum latency in milliseconds.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     min_latency_ms: f64,
+    /// Maximum latency in milliseconds.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     max_latency_ms: f64,
+    /// Latency standard deviation in milliseconds.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     stdev_latency_ms: f64,
+    /// 50th percentile latency in milliseconds.
+    #[builder(default = "0.0")]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    p50_latency_ms: f64,
+    /// 75th percentile latency in milliseconds.
+    #[builder(default = "0.0")]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    p75_latency_ms: f64,
+    /// 90th percentile latency in milliseconds.
+    #[builder(default = "0.0")]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    p90_latency_ms: f64,
+    /// 99th percentile latency in milliseconds.
+    #[builder(default = "0.0")]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    p99_latency_ms: f64,
+    /// Total transferred data in megabytes.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     transfer_mb: f64,
+    /// Number of connection errors.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     errors_connect: f64,
+    /// Number of read errors.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     errors_read: f64,
+    /// Number of write errors.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     errors_write: f64,
+    /// Number of responses with status not in 2xx/3xx, or outside [`crate::Wrk::success_statuses`].
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     errors_status: f64,
+    /// Number of timeouts.
     #[builder(default = "0.0")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     errors_timeout: f64,
+    /// Number of responses failing the [`crate::ResponseCheck`]s.
+    #[builder(default = "0.0")]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    errors_mismatch: f64,
+    /// Date of the original run when this result comes from [`crate::Wrk::rerun`].
+    #[builder(default, setter(strip_option))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    rerun_of: Option<DateTime<Utc>>,
+    /// SHA-256 of the Lua script of the run, archived in the history so [`crate::Wrk::rerun`]
+    /// replays the exact same script.
+    #[builder(default, setter(strip_option))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    script_sha256: Option<String>,
+    /// Inconsistencies detected while collecting the result, IE: mismatches between the
+    /// wrk summary and the JSON produced by the Lua script.
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    warnings: Vec<String>,
+    /// Known problems wrk reported on stderr, also explained in [`WrkResult::warnings`] or
+    /// [`WrkResult::error`].
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    diagnoses: Vec<Diagnosis>,
+    /// Responses received every second, recorded when [`crate::Wrk::timeseries`] is enabled.
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    timeseries: Vec<Sample>,
+    /// Throughput dips found in [`WrkResult::timeseries`] by [`crate::Wrk::stall_detection`].
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    stalls: Vec<Stall>,
+    /// Composite score computed by [`crate::Wrk::score`].
+    #[builder(default, setter(strip_option))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    score: Option<f64>,
+    /// `Server` header of the first response, captured with [`crate::Wrk::capture_server`].
+    #[builder(default, setter(strip_option))]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_header"
+    )]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    server: Option<String>,
+    /// Deployed version read from the [`crate::Wrk::version_header`] of the first response.
+    #[builder(default, setter(strip_option))]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_header"
+    )]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    app_version: Option<String>,
+    /// Daily aggregate this result was kept as when [`crate::Wrk::compact_history`] replaced
+    /// the files of its day.
+    #[builder(default, setter(strip_option))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    rollup: Option<Rollup>,
+    /// Whether the run was the first after a restart of the target, see
+    /// [`crate::BenchSession::target_restarted`]. Cold starts are stored in a separate series.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    cold_start: bool,
+    /// Highest constant rate in requests/sec meeting the latency SLO, found by
+    /// [`crate::Wrk::find_max_rate`].
+    #[builder(default, setter(strip_option))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    capacity: Option<u32>,
+    /// Whether the wrk process used all the CPU available to its threads, meaning the results
+    /// are bound by the load generator rather than by the target.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    generator_saturated: bool,
+}
+
+/// Deserialize a captured header, mapping the empty string emitted by the Lua script when it
+/// is missing to `None`.
+fn deserialize_header<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.filter(|h| !h.is_empty()))
+}
+
+/// Deserialize the benchmark of a result, mapping the zeroed configuration stored by older
+/// versions for failed runs to `None`.
+fn deserialize_benchmark<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Benchmark>, D::Error> {
+    Ok(Option::<Benchmark>::deserialize(deserializer)?.filter(|b| *b != Benchmark::default()))
 }
 
 impl Default for WrkResult {
     fn default() -> Self {
         Self {
+            run_id: Uuid::new_v4(),
+            suite_id: Uuid::nil(),
             success: false,
             error: String::new(),
-            benchmark: Benchmark::default(),
+            benchmark: None,
             date: Utc::now(),
             requests: 0.0,
             errors: 0.0,
@@ -84,49 +240,229 @@ impl Default for WrkResult {
             min_latency_ms: 0.0,
             max_latency_ms: 0.0,
             stdev_latency_ms: 0.0,
+            p50_latency_ms: 0.0,
+            p75_latency_ms: 0.0,
+            p90_latency_ms: 0.0,
+            p99_latency_ms: 0.0,
             transfer_mb: 0.0,
             errors_connect: 0.0,
             errors_read: 0.0,
             errors_write: 0.0,
             errors_status: 0.0,
             errors_timeout: 0.0,
+            errors_mismatch: 0.0,
+            rerun_of: None,
+            script_sha256: None,
+            warnings: Vec::new(),
+            timeseries: Vec::new(),
+            stalls: Vec::new(),
+            diagnoses: Vec::new(),
+            score: None,
+            server: None,
+            app_version: None,
+            rollup: None,
+            cold_start: false,
+            capacity: None,
+            generator_saturated: false,
         }
     }
 }
 
 impl WrkResult {
+    /// Value of `metric` in this result.
+    pub fn get(&self, metric: Metric) -> f64 {
+        metric.value(self)
+    }
+
+    /// Create a failed result carrying the error message.
     pub fn fail(error: String) -> Self {
         Self {
             error,
             ..Default::default()
         }
     }
+
+    /// Stable identity of the run: its [`WrkResult::run_id`] or, for results recorded before
+    /// identifiers existed, its date and benchmark key.
+    pub(crate) fn identity(&self) -> String {
+        if self.run_id.is_nil() {
+            let key = self.benchmark.as_ref().map(|b| b.key()).unwrap_or_default();
+            format!("{}-{}", self.date.timestamp_millis(), key)
+        } else {
+            self.run_id.to_string()
+        }
+    }
+
+    /// Deployed target described by its captured version and `Server` header, IE: `1.4.2 (nginx)`.
+    pub fn deployed(&self) -> Option<String> {
+        match (&self.app_version, &self.server) {
+            (Some(version), Some(server)) => Some(format!("{} ({})", version, server)),
+            (Some(version), None) => Some(version.clone()),
+            (None, Some(server)) => Some(server.clone()),
+            (None, None) => None,
+        }
+    }
+
+    /// Ratio of errors over the total number of requests, between 0 and 1.
+    pub fn error_rate(&self) -> f64 {
+        if self.requests > 0.0 {
+            self.errors / self.requests
+        } else {
+            0.0
+        }
+    }
+
+    /// Ratio of successes over the total number of requests, between 0 and 1.
+    pub fn success_rate(&self) -> f64 {
+        if self.requests > 0.0 {
+            self.successes / self.requests
+        } else {
+            0.0
+        }
+    }
+
+    /// Transferred data per second in MiB.
+    pub fn throughput_mib_s(&self) -> f64 {
+        let duration = self.benchmark.as_ref().map_or(0.0, |b| b.duration().as_secs_f64());
+        if duration > 0.0 {
+            self.transfer_mb / duration
+        } else {
+            0.0
+        }
+    }
+
+    /// Average transferred bytes per request.
+    pub fn avg_bytes_per_request(&self) -> f64 {
+        if self.requests > 0.0 {
+            self.transfer_mb * MIB / self.requests
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Bytes in a MiB, the unit wrk reports transferred data in.
+const MIB: f64 = 1048576.0;
+
+/// Unit used to display latencies in reports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum LatencyUnit {
+    /// Microseconds.
+    Micros,
+    /// Milliseconds.
+    Millis,
+    /// Seconds.
+    Secs,
+    /// Unit picked for every latency, so it is displayed with at most three integer digits.
+    #[default]
+    Auto,
+}
+
+impl LatencyUnit {
+    /// Convert a latency expressed in milliseconds to this unit. [`LatencyUnit::Auto`] keeps
+    /// milliseconds, use [`LatencyUnit::resolve`] first.
+    pub fn from_ms(&self, ms: f64) -> f64 {
+        match self {
+            Self::Micros => ms * 1000.0,
+            Self::Millis | Self::Auto => ms,
+            Self::Secs => ms / 1000.0,
+        }
+    }
+
+    /// Unit suffix.
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Self::Micros => "µs",
+            Self::Millis | Self::Auto => "ms",
+            Self::Secs => "s",
+        }
+    }
+
+    /// Unit used to display `ms`, picking one for [`LatencyUnit::Auto`].
+    pub fn resolve(&self, ms: f64) -> Self {
+        match self {
+            Self::Auto if ms.abs() < 1.0 => Self::Micros,
+            Self::Auto if ms.abs() < 1000.0 => Self::Millis,
+            Self::Auto => Self::Secs,
+            unit => *unit,
+        }
+    }
 }
 
-#[derive(Debug, Default, Clone)]
-pub struct Deviation {
-    pub deviation: WrkResult,
+/// Percentage variance between a new benchmark result and an old one.
+#[derive(Debug, Clone)]
+pub struct Variance {
+    /// Percentage variance of every measurement.
+    pub variance: WrkResult,
+    /// New benchmark result.
     pub new: WrkResult,
+    /// Old benchmark result.
     pub old: WrkResult,
+    /// Unit used to display latencies.
+    pub latency_unit: LatencyUnit,
+    /// Number of decimals used to display latencies.
+    pub latency_precision: usize,
+    /// Separators and precision used to display the other numbers.
+    pub number_format: NumberFormat,
+    /// Display transferred data with a unit picked for every value, IE: KB, MB or GB, instead
+    /// of raw MB.
+    pub scale_sizes: bool,
+    /// The new result is a baseline without history to compare with, so `old` is `new`.
+    pub baseline: bool,
+    /// The results ran for different durations, so the variances of totals, IE: requests,
+    /// errors and transferred data, compare their rates per second.
+    pub normalized: bool,
+}
+
+impl Default for Variance {
+    fn default() -> Self {
+        Self {
+            variance: WrkResult::default(),
+            new: WrkResult::default(),
+            old: WrkResult::default(),
+            latency_unit: LatencyUnit::default(),
+            latency_precision: 2,
+            number_format: NumberFormat::default(),
+            scale_sizes: true,
+            baseline: false,
+            normalized: false,
+        }
+    }
 }
 
-impl Deviation {
+impl Variance {
+    /// Calculate the variance between `new` and `old`.
     pub fn new(new: WrkResult, old: WrkResult) -> Self {
+        let duration = |result: &WrkResult| result.benchmark().as_ref().map(|b| b.duration().as_secs_f64());
+        let durations = match (duration(&new), duration(&old)) {
+            (Some(new), Some(old)) if new != old && new > 0.0 && old > 0.0 => Some((new, old)),
+            _ => None,
+        };
+        // Totals of runs of different durations are only comparable as rates.
+        let total = |new: &f64, old: &f64| match durations {
+            Some((new_duration, old_duration)) => Self::calculate(&(new / new_duration), &(old / old_duration)),
+            None => Self::calculate(new, old),
+        };
         let requests_sec = Self::calculate(new.requests_sec(), old.requests_sec());
-        let requests = Self::calculate(new.requests(), old.requests());
-        let successes = Self::calculate(new.successes(), old.successes());
-        let errors = Self::calculate(new.errors(), old.errors());
+        let requests = total(new.requests(), old.requests());
+        let successes = total(new.successes(), old.successes());
+        let errors = total(new.errors(), old.errors());
         let avg_latency_ms = Self::calculate(new.avg_latency_ms(), old.avg_latency_ms());
         let min_latency_ms = Self::calculate(new.min_latency_ms(), old.min_latency_ms());
         let max_latency_ms = Self::calculate(new.max_latency_ms(), old.max_latency_ms());
         let stdev_latency_ms = Self::calculate(new.stdev_latency_ms(), old.stdev_latency_ms());
-        let transfer_mb = Self::calculate(new.transfer_mb(), old.transfer_mb());
-        let errors_connect = Self::calculate(new.errors_connect(), old.errors_connect());
-        let errors_read = Self::calculate(new.errors_read(), old.errors_read());
-        let errors_write = Self::calculate(new.errors_write(), old.errors_write());
-        let errors_status = Self::calculate(new.errors_status(), old.errors_status());
-        let errors_timeout = Self::calculate(new.errors_timeout(), old.errors_timeout());
-        let deviation = WrkResultBuilder::default()
+        let p50_latency_ms = Self::calculate(new.p50_latency_ms(), old.p50_latency_ms());
+        let p75_latency_ms = Self::calculate(new.p75_latency_ms(), old.p75_latency_ms());
+        let p90_latency_ms = Self::calculate(new.p90_latency_ms(), old.p90_latency_ms());
+        let p99_latency_ms = Self::calculate(new.p99_latency_ms(), old.p99_latency_ms());
+        let transfer_mb = total(new.transfer_mb(), old.transfer_mb());
+        let errors_connect = total(new.errors_connect(), old.errors_connect());
+        let errors_read = total(new.errors_read(), old.errors_read());
+        let errors_write = total(new.errors_write(), old.errors_write());
+        let errors_status = total(new.errors_status(), old.errors_status());
+        let errors_timeout = total(new.errors_timeout(), old.errors_timeout());
+        let errors_mismatch = total(new.errors_mismatch(), old.errors_mismatch());
+        let variance = WrkResultBuilder::default()
             .date(*new.date())
             .requests(requests)
             .errors(errors)
@@ -136,212 +472,387 @@ impl Deviation {
             .min_latency_ms(min_latency_ms)
             .max_latency_ms(max_latency_ms)
             .stdev_latency_ms(stdev_latency_ms)
+            .p50_latency_ms(p50_latency_ms)
+            .p75_latency_ms(p75_latency_ms)
+            .p90_latency_ms(p90_latency_ms)
+            .p99_latency_ms(p99_latency_ms)
             .transfer_mb(transfer_mb)
             .errors_connect(errors_connect)
             .errors_read(errors_read)
             .errors_write(errors_write)
             .errors_status(errors_status)
             .errors_timeout(errors_timeout)
+            .errors_mismatch(errors_mismatch)
             .build()
             .unwrap();
-        Self { deviation, new, old }
+        Self {
+            variance,
+            new,
+            old,
+            normalized: durations.is_some(),
+            ..Default::default()
+        }
+    }
+
+    /// Baseline variance of `new`, recorded without any history to compare with. Every
+    /// measurement varies by zero.
+    pub fn baseline(new: WrkResult) -> Self {
+        let variance = WrkResultBuilder::default().date(*new.date()).build().unwrap();
+        Self {
+            variance,
+            old: new.clone(),
+            new,
+            baseline: true,
+            ..Default::default()
+        }
+    }
+
+    /// Fail with [`crate::WrkError::Regression`] when requests/sec dropped or p99 latency grew by
+    /// more than `max_percent`.
+    pub fn check_regression(&self, max_percent: f64) -> crate::Result<()> {
+        self.check_thresholds(&Thresholds::max_regression(max_percent))
     }
 
-    fn calculate(new: &f64, old: &f64) -> f64 {
-        (new - old) / old * 100.0
+    /// Fail with [`crate::WrkError::Regression`] when any rule of `thresholds` is broken.
+    pub fn check_thresholds(&self, thresholds: &Thresholds) -> crate::Result<()> {
+        thresholds.check(self)
     }
 
+    /// Display latencies using the given unit.
+    pub fn with_latency_unit(mut self, latency_unit: LatencyUnit) -> Self {
+        self.latency_unit = latency_unit;
+        self
+    }
+
+    /// Display latencies using the given number of decimals.
+    pub fn with_latency_precision(mut self, latency_precision: usize) -> Self {
+        self.latency_precision = latency_precision;
+        self
+    }
+
+    /// Display numbers using the given separators and precision.
+    pub fn with_number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
+    }
+
+    /// Display transferred data in raw MB, or with a unit picked for every value.
+    pub fn with_scaled_sizes(mut self, scale_sizes: bool) -> Self {
+        self.scale_sizes = scale_sizes;
+        self
+    }
+
+    /// Percentage variance of `metric`.
+    pub fn percent(&self, metric: Metric) -> f64 {
+        if metric.is_derived() {
+            Self::calculate(&self.new.get(metric), &self.old.get(metric))
+        } else {
+            self.variance.get(metric)
+        }
+    }
+
+    /// Name of `metric` with the unit its values are displayed in, if not in every value.
+    pub(crate) fn label(&self, metric: Metric) -> String {
+        if metric.is_latency() && self.latency_unit == LatencyUnit::Auto || self.scaled(metric) {
+            metric.name().to_string()
+        } else if metric.is_latency() {
+            format!("{} ({})", metric.name(), self.latency_unit.suffix())
+        } else {
+            metric.to_string()
+        }
+    }
+
+    /// Value of `metric` in `result`, displayed with the latency unit and number format.
+    pub(crate) fn value(&self, metric: Metric, result: &WrkResult) -> String {
+        let value = result.get(metric);
+        match metric {
+            _ if metric.is_latency() && self.latency_unit == LatencyUnit::Auto => {
+                let unit = self.latency_unit.resolve(value);
+                format!(
+                    "{} {}",
+                    self.number_format.format(unit.from_ms(value), self.latency_precision),
+                    unit.suffix()
+                )
+            }
+            _ if metric.is_latency() => self
+                .number_format
+                .format(self.latency_unit.from_ms(value), self.latency_precision),
+            Metric::Transfer if self.scale_sizes => self.number_format.bytes(value * MIB),
+            Metric::Throughput if self.scale_sizes => format!("{}/s", self.number_format.bytes(value * MIB)),
+            Metric::BytesPerRequest if self.scale_sizes => self.number_format.bytes(value),
+            Metric::ErrorRate | Metric::SuccessRate => self.number_format.format(value, 4),
+            _ => self.number_format.auto(value),
+        }
+    }
+
+    fn scaled(&self, metric: Metric) -> bool {
+        self.scale_sizes && matches!(metric, Metric::Transfer | Metric::Throughput | Metric::BytesPerRequest)
+    }
+
+    fn percent_cell(&self, metric: Metric) -> String {
+        self.number_format.percent(self.percent(metric))
+    }
+
+    /// Percentage change from `old` to `new`: zero when they are equal, IE: no errors before
+    /// and after, and infinite when only `old` is zero.
+    pub(crate) fn calculate(new: &f64, old: &f64) -> f64 {
+        if new == old {
+            0.0
+        } else {
+            (new - old) / old * 100.0
+        }
+    }
+
+    /// Render the variance as a GitHub flavoured markdown table with `\\n` escaped newlines,
+    /// ready to be set as a GitHub Actions output.
     pub fn to_github_markdown(&self) -> String {
-        let mut result = String::from("### Rust Wrk benchmark report:\\n");
-        result += &format!(
-            "#### Duration: {} sec, Connections: {}, Threads: {}\\n\\n",
-            self.new.benchmark().duration().as_secs(),
-            self.new.benchmark().connections(),
-            self.new.benchmark().threads()
-        );
-        result += "|Measurement|Deviation|Current|Old|\\n|-|-|-|-|\\n";
-        result += &format!(
-            "|Requests/sec|{:.2}%|{}|{}|\\n",
-            self.deviation.requests_sec(),
-            self.new.requests_sec(),
-            self.old.requests_sec()
-        );
-        result += &format!(
-            "|Total requests|{:.2}%|{}|{}|\\n",
-            self.deviation.requests(),
-            self.new.requests(),
-            self.old.requests()
-        );
-        result += &format!(
-            "|Total errors|{:.2}%|{}|{}|\\n",
-            self.deviation.errors(),
-            self.new.errors(),
-            self.old.errors()
-        );
-        result += &format!(
-            "|Total successes|{:.2}%|{}|{}|\\n",
-            self.deviation.successes(),
-            self.new.successes(),
-            self.old.successes()
-        );
-        result += &format!(
-            "|Average latency ms|{:.2}%|{}|{}|\\n",
-            self.deviation.avg_latency_ms(),
-            self.new.avg_latency_ms(),
-            self.old.avg_latency_ms()
-        );
-        result += &format!(
-            "|Minimum latency ms|{:.2}%|{}|{}|\\n",
-            self.deviation.min_latency_ms(),
-            self.new.min_latency_ms(),
-            self.old.min_latency_ms()
-        );
-        result += &format!(
-            "|Maximum latency ms|{:.2}%|{}|{}|\\n",
-            self.deviation.max_latency_ms(),
-            self.new.max_latency_ms(),
-            self.old.max_latency_ms()
-        );
-        result += &format!(
-            "|Stdev latency ms|{:.2}%|{}|{}|\\n",
-            self.deviation.stdev_latency_ms(),
-            self.new.stdev_latency_ms(),
-            self.old.stdev_latency_ms()
-        );
-        result += &format!(
-            "|Transfer Mb|{:.2}%|{}|{}|\\n",
-            self.deviation.transfer_mb(),
-            self.new.transfer_mb(),
-            self.old.transfer_mb()
-        );
-        result += &format!(
-            "|Connect errors|{:.2}%|{}|{}|\\n",
-            self.deviation.errors_connect(),
-            self.new.errors_connect(),
-            self.old.errors_connect()
-        );
-        result += &format!(
-            "|Read errors|{:.2}%|{}|{}|\\n",
-            self.deviation.errors_read(),
-            self.new.errors_read(),
-            self.old.errors_read()
-        );
-        result += &format!(
-            "|Write errors|{:.2}%|{}|{}|\\n",
-            self.deviation.errors_write(),
-            self.new.errors_write(),
-            self.old.errors_write()
-        );
-        result += &format!(
-            "|Status errors (not 2xx/3xx)|{:.2}%|{}|{}|\\n",
-            self.deviation.errors_status(),
-            self.new.errors_status(),
-            self.old.errors_status()
-        );
-        result += &format!(
-            "|Timeout errors|{:.2}%|{}|{}|\\n",
-            self.deviation.errors_timeout(),
-            self.new.errors_timeout(),
-            self.old.errors_timeout()
-        );
+        self.to_markdown().replace('\n', "\\n")
+    }
+
+    /// Render the variance as a GitHub flavoured markdown table, IE: as the body of a pull
+    /// request comment.
+    pub fn to_markdown(&self) -> String {
+        let mut result = String::from("### Rust Wrk benchmark report:\n");
+        if let Some(benchmark) = self.new.benchmark() {
+            if let Some(label) = benchmark.label() {
+                result += &format!("#### Benchmark: {}\n", label);
+            }
+            result += &format!(
+                "#### Duration: {} sec, Connections: {}, Threads: {}\n\n",
+                benchmark.duration().as_secs(),
+                benchmark.connections(),
+                benchmark.threads()
+            );
+        }
+        if !self.new.run_id().is_nil() {
+            result += &format!("Run `{}` of suite `{}`\n\n", self.new.run_id(), self.new.suite_id());
+        }
+        if let Some(deployed) = self.new.deployed() {
+            result += &format!("Target `{}`\n\n", deployed);
+        }
+        if self.baseline {
+            result += "Baseline run, no history to compare with\n\n";
+        }
+        if self.normalized {
+            result += "Runs of different durations, totals are compared per second\n\n";
+        }
+        result += "|Measurement|Variance|Current|Old|\n|-|-|-|-|\n";
+        for metric in Metric::iter() {
+            result += &format!(
+                "|{}|{}|{}|{}|\n",
+                self.label(metric),
+                self.percent_cell(metric),
+                self.value(metric, &self.new),
+                self.value(metric, &self.old)
+            );
+        }
+        for warning in self.new.warnings() {
+            result += &format!("\n> Warning: {}\n", warning);
+        }
+        for stall in self.new.stalls() {
+            result += &format!("\n> Stall: {}\n", stall);
+        }
         result
     }
 }
 
-impl fmt::Display for Deviation {
+impl fmt::Display for Variance {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut table = Table::new();
         table.set_format(*format::consts::FORMAT_CLEAN);
         table.add_row(Row::new(vec![
             Cell::new("Measurement").with_style(Attr::Bold),
-            Cell::new("Deviation").with_style(Attr::Bold),
+            Cell::new("Variance").with_style(Attr::Bold),
             Cell::new("Current").with_style(Attr::Bold),
             Cell::new("Old").with_style(Attr::Bold),
         ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Requests per second").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.requests_sec())),
-            Cell::new(&self.new.requests_sec().to_string()),
-            Cell::new(&self.old.requests_sec().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Total requests").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.requests())),
-            Cell::new(&self.new.requests().to_string()),
-            Cell::new(&self.old.requests().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Total errors").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.errors())),
-            Cell::new(&self.new.errors().to_string()),
-            Cell::new(&self.old.errors().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Total successes").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.successes())),
-            Cell::new(&self.new.successes().to_string()),
-            Cell::new(&self.old.successes().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Average latency ms").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.avg_latency_ms())),
-            Cell::new(&self.new.avg_latency_ms().to_string()),
-            Cell::new(&self.old.avg_latency_ms().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Minimum latency ms").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.min_latency_ms())),
-            Cell::new(&self.new.min_latency_ms().to_string()),
-            Cell::new(&self.old.min_latency_ms().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Maximum latency ms").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.max_latency_ms())),
-            Cell::new(&self.new.max_latency_ms().to_string()),
-            Cell::new(&self.old.max_latency_ms().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Stdev latency ms").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.stdev_latency_ms())),
-            Cell::new(&self.new.stdev_latency_ms().to_string()),
-            Cell::new(&self.old.stdev_latency_ms().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Transfer Mb").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.transfer_mb())),
-            Cell::new(&self.new.transfer_mb().to_string()),
-            Cell::new(&self.old.transfer_mb().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Connect errors").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.errors_connect())),
-            Cell::new(&self.new.errors_connect().to_string()),
-            Cell::new(&self.old.errors_connect().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Read errors").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.errors_read())),
-            Cell::new(&self.new.errors_read().to_string()),
-            Cell::new(&self.old.errors_read().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Write errors").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.errors_write())),
-            Cell::new(&self.new.errors_write().to_string()),
-            Cell::new(&self.old.errors_write().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Status errors (not 2xx/3xx)").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.errors_status())),
-            Cell::new(&self.new.errors_status().to_string()),
-            Cell::new(&self.old.errors_status().to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Timeout errors").with_style(Attr::Bold),
-            Cell::new(&format!("{:.2}%", self.deviation.errors_timeout())),
-            Cell::new(&self.new.errors_timeout().to_string()),
-            Cell::new(&self.old.errors_timeout().to_string()),
-        ]));
-        write!(f, "## Rust Wrk benchmark report:\n{}", table)
+        for metric in Metric::iter() {
+            table.add_row(Row::new(vec![
+                Cell::new(&self.label(metric)).with_style(Attr::Bold),
+                Cell::new(&self.percent_cell(metric)),
+                Cell::new(&self.value(metric, &self.new)),
+                Cell::new(&self.value(metric, &self.old)),
+            ]));
+        }
+        writeln!(f, "## Rust Wrk benchmark report:")?;
+        if let Some(label) = self.new.benchmark().as_ref().and_then(|b| b.label().as_ref()) {
+            writeln!(f, "### Benchmark: {}", label)?;
+        }
+        if self.baseline {
+            writeln!(f, "Baseline run, no history to compare with")?;
+        }
+        if self.normalized {
+            writeln!(f, "Runs of different durations, totals are compared per second")?;
+        }
+        if let Some(deployed) = self.new.deployed() {
+            writeln!(f, "Target: {}", deployed)?;
+        }
+        write!(f, "{}", table)?;
+        for warning in self.new.warnings() {
+            writeln!(f, "Warning: {}", warning)?;
+        }
+        for stall in self.new.stalls() {
+            writeln!(f, "Stall: {}", stall)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::Method;
+
+    use super::*;
+    use crate::{test_utils::result_builder, BenchmarkBuilder, Headers};
+
+    #[test]
+    fn regression_gate() {
+        let result =
+            |requests_sec: f64, p99_latency_ms: f64| result_builder(requests_sec, p99_latency_ms).build().unwrap();
+        assert!(Variance::new(result(96.0, 10.4), result(100.0, 10.0))
+            .check_regression(5.0)
+            .is_ok());
+        assert!(Variance::new(result(90.0, 10.0), result(100.0, 10.0))
+            .check_regression(5.0)
+            .is_err());
+        assert!(Variance::new(result(100.0, 11.0), result(100.0, 10.0))
+            .check_regression(5.0)
+            .is_err());
+        assert!(Variance::new(result(100.0, 11.0), result(100.0, 0.0))
+            .check_regression(5.0)
+            .is_ok());
+    }
+
+    #[test]
+    fn zero_baseline() {
+        let result = |requests_sec: f64, errors: f64| result_builder(requests_sec, 0.0).errors(errors).build().unwrap();
+        let variance = Variance::new(result(100.0, 0.0), result(0.0, 0.0));
+        assert_eq!(variance.percent(Metric::RequestsSec), f64::INFINITY);
+        assert_eq!(variance.percent(Metric::Errors), 0.0);
+        let markdown = variance.to_github_markdown();
+        assert!(markdown.contains("|Requests/sec|+∞|100|0|"), "{}", markdown);
+        assert!(markdown.contains("|Total errors|0.00%|0|0|"), "{}", markdown);
+        assert!(!markdown.contains("inf") && !markdown.contains("NaN"), "{}", markdown);
+        let json = serde_json::to_value(&variance.variance).unwrap();
+        assert_eq!(json["requests_sec"], serde_json::Value::Null);
+        assert_eq!(json["errors"], 0.0);
+        assert!(Variance::new(result(0.0, 0.0), result(100.0, 0.0))
+            .check_regression(5.0)
+            .is_err());
+        let unknown = Variance::new(result(f64::NAN, 0.0), result(100.0, 0.0));
+        assert!(unknown.to_github_markdown().contains("|Requests/sec|n/a|n/a|100|"));
+    }
+
+    #[test]
+    fn normalized_totals() {
+        let result = |secs: u64, requests: f64| {
+            result_builder(0.0, 0.0)
+                .benchmark(
+                    BenchmarkBuilder::default()
+                        .duration(Duration::from_secs(secs))
+                        .build()
+                        .unwrap(),
+                )
+                .requests(requests)
+                .build()
+                .unwrap()
+        };
+        let variance = Variance::new(result(5, 1000.0), result(30, 6000.0));
+        assert!(variance.normalized);
+        assert_eq!(*variance.variance.requests(), 0.0);
+        let variance = Variance::new(result(30, 1000.0), result(30, 2000.0));
+        assert!(!variance.normalized);
+        assert_eq!(*variance.variance.requests(), -50.0);
+    }
+
+    #[test]
+    fn report_every_metric() {
+        let result = WrkResultBuilder::default()
+            .requests(200.0)
+            .errors(2.0)
+            .p99_latency_ms(1.5)
+            .build()
+            .unwrap();
+        let variance = Variance::new(result.clone(), result).with_latency_unit(LatencyUnit::Micros);
+        let markdown = variance.to_github_markdown();
+        assert_eq!(markdown.matches("%|").count(), Metric::ALL.len());
+        assert!(markdown.contains("|P99 latency (µs)|0.00%|1500.00|1500.00|"));
+        assert!(markdown.contains("|Error rate|0.00%|0.0100|0.0100|"));
+        assert!(!markdown.contains('\n'));
+        let comment = variance.to_markdown();
+        assert!(comment.contains("\n|Measurement|Variance|Current|Old|\n") && !comment.contains("\\n"));
+        assert_eq!(variance.to_string().matches('%').count(), Metric::ALL.len());
+    }
+
+    #[test]
+    fn number_format() {
+        let result = WrkResultBuilder::default()
+            .requests(1234567.0)
+            .requests_sec(41152.23)
+            .build()
+            .unwrap();
+        let markdown = Variance::new(result.clone(), result)
+            .with_number_format(NumberFormat::locale("en_US"))
+            .to_github_markdown();
+        assert!(markdown.contains("|Total requests|0.00%|1,234,567|1,234,567|"));
+        assert!(markdown.contains("|Requests/sec|0.00%|41,152.23|41,152.23|"));
+    }
+
+    #[test]
+    fn human_units() {
+        let result = |p99_latency_ms: f64| {
+            result_builder(0.0, p99_latency_ms)
+                .requests(1024.0)
+                .transfer_mb(1.5)
+                .build()
+                .unwrap()
+        };
+        let markdown = Variance::new(result(0.25), result(1250.0)).to_github_markdown();
+        assert!(markdown.contains("|P99 latency|-99.98%|250.00 µs|1.25 s|"));
+        assert!(markdown.contains("|Transfer|0.00%|1.50 MB|1.50 MB|"));
+        assert!(markdown.contains("|Average bytes per request|0.00%|1.50 KB|1.50 KB|"));
+        let markdown = Variance::new(result(2.0), result(2.0))
+            .with_latency_unit(LatencyUnit::Millis)
+            .with_scaled_sizes(false)
+            .to_github_markdown();
+        assert!(markdown.contains("|P99 latency (ms)|0.00%|2.00|2.00|"));
+        assert!(markdown.contains("|Transfer (MB)|0.00%|1.50|1.50|"));
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let benchmark = BenchmarkBuilder::default()
+            .threads(2)
+            .duration(Duration::from_secs(5))
+            .method(Method::POST)
+            .body("{}".to_string())
+            .headers(Headers::from([(
+                "Content-Type".to_string(),
+                "application/json".to_string(),
+            )]))
+            .build()
+            .unwrap();
+        let result = WrkResultBuilder::default()
+            .success(true)
+            .benchmark(benchmark)
+            .requests(1000.0)
+            .requests_sec(200.0)
+            .avg_latency_ms(0.123456)
+            .p99_latency_ms(1.5)
+            .build()
+            .unwrap();
+        let json = serde_json::to_string(&vec![result.clone()]).unwrap();
+        let decoded: Vec<WrkResult> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, vec![result]);
+        let failed = WrkResult::fail("boom".to_string());
+        let decoded: WrkResult = serde_json::from_str(&serde_json::to_string(&failed).unwrap()).unwrap();
+        assert_eq!(decoded, failed);
+        let legacy = r#"{"benchmark": {"threads": 0, "connections": 0, "duration": {"secs": 0, "nanos": 0}},
+            "requests": 0, "errors": 0, "successes": 0, "requests_sec": 0, "avg_latency_ms": 0, "min_latency_ms": 0,
+            "max_latency_ms": 0, "stdev_latency_ms": 0, "transfer_mb": 0, "errors_connect": 0, "errors_read": 0,
+            "errors_write": 0, "errors_status": 0, "errors_timeout": 0}"#;
+        assert_eq!(*serde_json::from_str::<WrkResult>(legacy).unwrap().benchmark(), None);
     }
 }
@@ -0,0 +1,133 @@
+//! Compaction of old history files into daily rollups, keeping the best and the median result
+//! of every benchmark configuration.
+use std::{collections::BTreeMap, path::PathBuf};
+
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{encryption::HistoryKey, history, Benchmarks, Result, Wrk, WrkResult};
+
+/// Daily aggregate a result was kept as by [`crate::Wrk::compact_history`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Rollup {
+    /// Best result of its benchmark configuration that day. Also the median when the day has
+    /// fewer than three successful runs.
+    Best,
+    /// Median result by requests/sec of its benchmark configuration that day.
+    Median,
+}
+
+/// Replace the history files of the days ending more than `days` days before `now` with a
+/// single rollup file per day. Returns the number of removed files.
+pub(crate) fn compact(wrk: &Wrk, days: u32, now: DateTime<Utc>) -> Result<usize> {
+    let cutoff = (now - ChronoDuration::days(days.into())).date_naive();
+    let mut by_day: BTreeMap<NaiveDate, Vec<PathBuf>> = BTreeMap::new();
+    for (date, _, path) in wrk.history_files(None)? {
+        let day = date.to_utc().date_naive();
+        if day < cutoff {
+            by_day.entry(day).or_default().push(path);
+        }
+    }
+    let mut removed = 0;
+    for (day, paths) in by_day {
+        let date = day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+        let rollup = wrk.history_file(&date, HistoryKey::from_env()?.is_some());
+        if paths == [rollup.clone()] {
+            continue;
+        }
+        let mut results = Benchmarks::new();
+        for path in &paths {
+            let format = history::parse_filename(path)
+                .map(|(_, format)| format)
+                .unwrap_or_default();
+            results.append(&mut wrk.read(path, format)?);
+        }
+        info!("Compacting {} history files of {} into daily rollups", paths.len(), day);
+        wrk.dump(date, &aggregate(wrk, crate::wrk::dedup(results)))?;
+        for path in paths.iter().filter(|path| **path != rollup) {
+            wrk.history_store().remove(path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Best and median successful result of every benchmark configuration, without time series.
+fn aggregate(wrk: &Wrk, results: Benchmarks) -> Benchmarks {
+    let mut configs: BTreeMap<String, Benchmarks> = BTreeMap::new();
+    for result in results.into_iter().filter(|r| *r.success()) {
+        let key = result.benchmark().as_ref().map(|b| b.key()).unwrap_or_default();
+        configs.entry(key).or_default().push(result);
+    }
+    let mut rollups = Benchmarks::new();
+    for mut results in configs.into_values() {
+        let Ok(best) = wrk.best_benchmark(&results) else {
+            continue;
+        };
+        results.sort_by(|a, b| a.requests_sec().total_cmp(b.requests_sec()));
+        let median = results.swap_remove((results.len() - 1) / 2);
+        let same = median.identity() == best.identity();
+        rollups.push(kept(best, Rollup::Best));
+        if !same {
+            rollups.push(kept(median, Rollup::Median));
+        }
+    }
+    rollups
+}
+
+fn kept(mut result: WrkResult, rollup: Rollup) -> WrkResult {
+    result.set_rollup(Some(rollup));
+    result.timeseries_mut().clear();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BenchmarkBuilder, WrkBuilder, WrkResultBuilder};
+
+    #[test]
+    fn compact_into_daily_rollups() {
+        let dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://localhost:1234".to_string())
+            .history_dir(dir.path().to_path_buf())
+            .build()
+            .unwrap();
+        let now = Utc::now();
+        let old = (now - ChronoDuration::days(10))
+            .date_naive()
+            .and_hms_opt(8, 0, 0)
+            .unwrap()
+            .and_utc();
+        let benchmark = BenchmarkBuilder::default().build().unwrap();
+        for (hour, requests_sec) in [(0, 100.0), (1, 300.0), (2, 200.0)] {
+            let date = old + ChronoDuration::hours(hour);
+            let result = WrkResultBuilder::default()
+                .success(true)
+                .benchmark(benchmark.clone())
+                .date(date)
+                .requests_sec(requests_sec)
+                .build()
+                .unwrap();
+            wrk.dump(date, &vec![result]).unwrap();
+        }
+        wrk.dump(now, &vec![WrkResultBuilder::default().success(true).build().unwrap()])
+            .unwrap();
+
+        assert_eq!(compact(&wrk, 7, now).unwrap(), 3);
+        assert_eq!(wrk.history_files(None).unwrap().len(), 2);
+        let rollups: Vec<(f64, Option<Rollup>)> = wrk
+            .history(&crate::HistoryPeriod::Forever, Some(&now))
+            .unwrap()
+            .iter()
+            .map(|r| (*r.requests_sec(), *r.rollup()))
+            .collect();
+        assert_eq!(
+            rollups,
+            vec![(300.0, Some(Rollup::Best)), (200.0, Some(Rollup::Median))]
+        );
+        assert_eq!(compact(&wrk, 7, now).unwrap(), 0);
+    }
+}
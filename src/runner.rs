@@ -0,0 +1,325 @@
+//! Execution of the engine command lines, replaceable to run them remotely or to fake them in
+//! tests.
+use std::{
+    fmt::Debug,
+    fs,
+    future::Future,
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    pin::Pin,
+    process::{Child, ExitStatus, Stdio},
+    thread,
+    time::Duration,
+};
+
+use getset::Getters;
+
+use crate::{CommandSpec, Result, WrkError};
+
+/// Outcome of an engine execution.
+#[derive(Debug, Clone, PartialEq, Getters)]
+pub struct ProcessOutput {
+    /// Exit status, missing when the program was killed by a signal.
+    #[getset(get = "pub")]
+    exit_status: Option<i32>,
+    /// Standard output.
+    #[getset(get = "pub")]
+    stdout: String,
+    /// Standard error.
+    #[getset(get = "pub")]
+    stderr: String,
+    /// CPU time used by the program, missing when the runner is unable to measure it.
+    #[getset(get = "pub")]
+    cpu_time: Option<Duration>,
+}
+
+impl ProcessOutput {
+    /// Create the outcome of an execution.
+    pub fn new(exit_status: Option<i32>, stdout: String, stderr: String) -> Self {
+        Self {
+            exit_status,
+            stdout,
+            stderr,
+            cpu_time: None,
+        }
+    }
+
+    /// Same outcome with the CPU time used by the program.
+    pub fn with_cpu_time(mut self, cpu_time: Duration) -> Self {
+        self.cpu_time = Some(cpu_time);
+        self
+    }
+
+    /// Whether the program exited successfully.
+    pub fn success(&self) -> bool {
+        self.exit_status == Some(0)
+    }
+}
+
+/// Execution of an engine command awaited by [`ProcessRunner::run_async`].
+pub type RunFuture<'a> = Pin<Box<dyn Future<Output = Result<ProcessOutput>> + Send + 'a>>;
+
+/// Runner of the engine commands built by [`crate::Wrk`].
+pub trait ProcessRunner: Debug + Send + Sync {
+    /// Run `spec` to completion. The Lua script must exist at the path given in its arguments.
+    fn run(&self, spec: &CommandSpec) -> Result<ProcessOutput>;
+
+    /// Same as [`ProcessRunner::run`], also streaming the output to the console line by line,
+    /// prefixed with `prefix`. Runners unable to stream only print the output once done.
+    fn run_tee(&self, spec: &CommandSpec, prefix: &str) -> Result<ProcessOutput> {
+        let output = self.run(spec)?;
+        echo(&output, prefix)?;
+        Ok(output)
+    }
+
+    /// Same as [`ProcessRunner::run`] without blocking the async runtime of the caller.
+    /// Runners unable to wait asynchronously run `spec` to completion when first polled.
+    fn run_async<'a>(&'a self, spec: &'a CommandSpec) -> RunFuture<'a> {
+        Box::pin(async move { self.run(spec) })
+    }
+}
+
+/// Print the output of a completed execution to the console, prefixed with `prefix`.
+pub(crate) fn echo(output: &ProcessOutput, prefix: &str) -> io::Result<()> {
+    tee(output.stdout().as_bytes(), prefix, io::stdout())?;
+    tee(output.stderr().as_bytes(), prefix, io::stderr())?;
+    Ok(())
+}
+
+/// Copy every line of `reader` to `console` prefixed with `prefix`, returning the lines read.
+fn tee<R: Read, W: Write>(reader: R, prefix: &str, mut console: W) -> io::Result<String> {
+    let mut reader = BufReader::new(reader);
+    let mut captured = String::new();
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        let text = String::from_utf8_lossy(&line);
+        writeln!(console, "[{}] {}", prefix, text.trim_end_matches('\n'))?;
+        captured.push_str(&text);
+        line.clear();
+    }
+    Ok(captured)
+}
+
+/// Interval between two samples of the CPU time of a running child.
+const CPU_SAMPLING_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Wait for `child` to exit, sampling its CPU time until then. The CPU time is only available
+/// on Linux, through `/proc`.
+fn wait_sampling_cpu(child: &mut Child) -> io::Result<(ExitStatus, Option<Duration>)> {
+    let mut cpu_time = None;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status, cpu_time));
+        }
+        cpu_time = proc_cpu_time(child.id()).or(cpu_time);
+        thread::sleep(CPU_SAMPLING_INTERVAL);
+    }
+}
+
+/// User and system CPU time of the process `pid` read from `/proc/<pid>/stat`, in clock ticks
+/// of 1/100 of a second.
+fn proc_cpu_time(pid: u32) -> Option<Duration> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name can hold spaces, fields are counted after its closing parenthesis.
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let ticks = fields.get(11)?.parse::<u64>().ok()? + fields.get(12)?.parse::<u64>().ok()?;
+    Some(Duration::from_millis(ticks * 10))
+}
+
+/// Read `reader` to a string in a separate thread.
+fn capture<R: Read + Send + 'static>(mut reader: R) -> thread::JoinHandle<io::Result<String>> {
+    thread::spawn(move || {
+        let mut captured = Vec::new();
+        reader.read_to_end(&mut captured)?;
+        Ok(String::from_utf8_lossy(&captured).to_string())
+    })
+}
+
+fn spawn_error(spec: &CommandSpec, e: io::Error) -> WrkError {
+    match e.kind() {
+        ErrorKind::NotFound => WrkError::Exec(format!("{} not found, install it or add it to PATH", spec.program())),
+        ErrorKind::PermissionDenied => WrkError::Exec(format!("{} is not executable", spec.program())),
+        _ => WrkError::Exec(format!("Unable to run {}: {}", spec.program(), e)),
+    }
+}
+
+/// Destination of the output of a child process, besides the captured copy.
+#[derive(Debug, Clone, Copy)]
+enum Sink<'a> {
+    /// Only capture the output.
+    Capture,
+    /// Also stream the output to the console, prefixed with the given prefix.
+    Tee(&'a str),
+}
+
+impl Sink<'_> {
+    /// Read `reader` to a string in a separate thread, copying it to `console` when teeing.
+    fn read<R, W>(&self, reader: R, console: W) -> thread::JoinHandle<io::Result<String>>
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        match self {
+            Self::Capture => capture(reader),
+            Self::Tee(prefix) => {
+                let prefix = prefix.to_string();
+                thread::spawn(move || tee(reader, &prefix, console))
+            }
+        }
+    }
+}
+
+/// Runner spawning the engine as a local child process.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl SystemRunner {
+    /// Run `spec` to completion, sending its output to `sink` and sampling its CPU time.
+    fn run_to(&self, spec: &CommandSpec, sink: Sink) -> Result<ProcessOutput> {
+        let mut child = spec
+            .command()
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| spawn_error(spec, e))?;
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            return Err(WrkError::Exec(format!(
+                "Unable to capture the output of {}",
+                spec.program()
+            )));
+        };
+        let (stdout, stderr) = (sink.read(stdout, io::stdout()), sink.read(stderr, io::stderr()));
+        let (status, cpu_time) = wait_sampling_cpu(&mut child)?;
+        let joined = |handle: thread::JoinHandle<io::Result<String>>| {
+            handle
+                .join()
+                .map_err(|_| WrkError::Exec(format!("Unable to capture the output of {}", spec.program())))
+        };
+        let output = ProcessOutput::new(status.code(), joined(stdout)??, joined(stderr)??);
+        Ok(match cpu_time {
+            Some(cpu_time) => output.with_cpu_time(cpu_time),
+            None => output,
+        })
+    }
+}
+
+impl ProcessRunner for SystemRunner {
+    fn run(&self, spec: &CommandSpec) -> Result<ProcessOutput> {
+        self.run_to(spec, Sink::Capture)
+    }
+
+    fn run_tee(&self, spec: &CommandSpec, prefix: &str) -> Result<ProcessOutput> {
+        self.run_to(spec, Sink::Tee(prefix))
+    }
+
+    /// Spawn the engine with [`tokio::process::Command`], killing it when the future is dropped
+    /// and sampling its CPU time like [`ProcessRunner::run`].
+    fn run_async<'a>(&'a self, spec: &'a CommandSpec) -> RunFuture<'a> {
+        Box::pin(async move {
+            let child = tokio::process::Command::from(spec.command())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|e| spawn_error(spec, e))?;
+            let pid = child.id();
+            let mut output = Box::pin(child.wait_with_output());
+            let mut cpu_time = None;
+            let output = loop {
+                match tokio::time::timeout(CPU_SAMPLING_INTERVAL, &mut output).await {
+                    Ok(output) => break output.map_err(|e| spawn_error(spec, e))?,
+                    Err(_) => cpu_time = pid.and_then(proc_cpu_time).or(cpu_time),
+                }
+            };
+            let output = ProcessOutput::new(
+                output.status.code(),
+                String::from_utf8_lossy(&output.stdout).to_string(),
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            );
+            Ok(match cpu_time {
+                Some(cpu_time) => output.with_cpu_time(cpu_time),
+                None => output,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn system_runner_errors() {
+        let spec = CommandSpec::new("wrk-api-bench-missing-command", vec![], BTreeMap::new(), String::new());
+        match SystemRunner.run(&spec) {
+            Err(WrkError::Exec(message)) => assert!(message.contains("not found"), "{}", message),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn tee_output() {
+        let mut console = Vec::new();
+        let captured = tee(&b"Running 1s test\nJSON{\n"[..], "1-1-1", &mut console).unwrap();
+        assert_eq!(captured, "Running 1s test\nJSON{\n");
+        assert_eq!(
+            String::from_utf8(console).unwrap(),
+            "[1-1-1] Running 1s test\n[1-1-1] JSON{\n"
+        );
+
+        let spec = CommandSpec::new(
+            "sh",
+            vec!["-c".to_string(), "echo out; echo err >&2; exit 3".to_string()],
+            BTreeMap::new(),
+            String::new(),
+        );
+        let output = SystemRunner.run_tee(&spec, "1-1-1").unwrap();
+        assert_eq!(
+            (output.exit_status(), output.stdout().as_str(), output.stderr().as_str()),
+            (&Some(3), "out\n", "err\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn run_async() {
+        let spec = CommandSpec::new(
+            "sh",
+            vec!["-c".to_string(), "echo out; echo err >&2; exit 3".to_string()],
+            BTreeMap::new(),
+            String::new(),
+        );
+        let output = SystemRunner.run_async(&spec).await.unwrap();
+        assert_eq!(
+            (output.exit_status(), output.stdout().as_str(), output.stderr().as_str()),
+            (&Some(3), "out\n", "err\n")
+        );
+        let missing = CommandSpec::new("wrk-api-bench-missing-command", vec![], BTreeMap::new(), String::new());
+        assert!(matches!(SystemRunner.run_async(&missing).await, Err(WrkError::Exec(_))));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn cpu_time() {
+        let spec = CommandSpec::new(
+            "sh",
+            vec![
+                "-c".to_string(),
+                "i=0; while [ $i -lt 300000 ]; do i=$((i+1)); done; echo done".to_string(),
+            ],
+            BTreeMap::new(),
+            String::new(),
+        );
+        let output = SystemRunner.run(&spec).unwrap();
+        assert_eq!(output.stdout(), "done\n");
+        assert!(output.cpu_time().is_some());
+        let output = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(SystemRunner.run_async(&spec))
+            .unwrap();
+        assert_eq!(output.stdout(), "done\n");
+        assert!(output.cpu_time().is_some());
+    }
+}
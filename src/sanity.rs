@@ -0,0 +1,152 @@
+//! Sanity checks of the methodology of a run, reported in [`crate::WrkResult::warnings`].
+use std::{thread, time::Duration};
+
+use crate::{Benchmark, WrkResult};
+
+/// Latency standard deviation, relative to the average, above which a run is flagged.
+const MAX_LATENCY_VARIATION: f64 = 1.0;
+/// Share of the CPU available to the wrk threads above which the load generator is
+/// considered saturated.
+const SATURATION: f64 = 0.9;
+/// Relative difference between the concurrency derived through Little's Law and the
+/// configured connections above which a run is flagged.
+const CONCURRENCY_TOLERANCE: f64 = 0.5;
+
+/// Run every methodology check of a successful `run` of `benchmark`, returning the warnings.
+/// Runs shorter than `min_duration` are flagged.
+pub(crate) fn check(run: &WrkResult, benchmark: &Benchmark, min_duration: Duration) -> Vec<String> {
+    if !run.success() {
+        return Vec::new();
+    }
+    [
+        littles_law(run, benchmark),
+        high_stdev(run),
+        short_duration(benchmark, min_duration),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Check the latency standard deviation against the average: a very spread distribution makes
+/// the averages, and the variance against the history, unreliable.
+fn high_stdev(run: &WrkResult) -> Option<String> {
+    let variation = run.stdev_latency_ms() / run.avg_latency_ms();
+    (*run.avg_latency_ms() > 0.0 && variation > MAX_LATENCY_VARIATION).then(|| {
+        format!(
+            "Latency standard deviation {:.2}ms is {:.0}% of the average {:.2}ms: results are noisy, \
+             check the target and the host for interference",
+            run.stdev_latency_ms(),
+            variation * 100.0,
+            run.avg_latency_ms()
+        )
+    })
+}
+
+/// Check the run lasted long enough to warm up the target and collect stable statistics.
+fn short_duration(benchmark: &Benchmark, min_duration: Duration) -> Option<String> {
+    (*benchmark.duration() < min_duration).then(|| {
+        format!(
+            "Duration of {}s is shorter than {}s: results may not reflect a warmed up target",
+            benchmark.duration().as_secs(),
+            min_duration.as_secs()
+        )
+    })
+}
+
+/// Check the concurrency derived through Little's Law, requests/sec × average latency, against
+/// the connections of `benchmark`. In a closed loop every connection always has a request in
+/// flight, so a large mismatch usually means the load generator is the bottleneck or suffers
+/// from coordinated omission. Constant rate runs are skipped, as they keep connections idle
+/// on purpose.
+fn littles_law(run: &WrkResult, benchmark: &Benchmark) -> Option<String> {
+    if benchmark.rate().is_some() || *benchmark.connections() == 0 {
+        return None;
+    }
+    let concurrency = run.requests_sec() * run.avg_latency_ms() / 1000.0;
+    let connections = *benchmark.connections() as f64;
+    let difference = (concurrency - connections).abs() / connections;
+    (difference > CONCURRENCY_TOLERANCE).then(|| {
+        format!(
+            "Little's Law concurrency is {:.1} ({:.2} requests/sec × {:.2}ms) for {} connections: \
+             the load generator may be the bottleneck or suffer from coordinated omission",
+            concurrency,
+            run.requests_sec(),
+            run.avg_latency_ms(),
+            connections
+        )
+    })
+}
+
+/// Check whether wrk, using `cpu_time` over `elapsed`, pegged the cores available to the
+/// threads of `benchmark`, in which case the results measure the limits of the load generator
+/// instead of the target.
+pub(crate) fn generator_saturation(cpu_time: Duration, elapsed: Duration, benchmark: &Benchmark) -> Option<String> {
+    let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+    let available = (*benchmark.threads() as usize).clamp(1, cores) as f64;
+    if elapsed.is_zero() {
+        return None;
+    }
+    let usage = cpu_time.as_secs_f64() / elapsed.as_secs_f64();
+    (usage >= available * SATURATION).then(|| {
+        format!(
+            "Load generator saturated: wrk used {:.0}% of the CPU of {} cores, results are bound by the client",
+            usage * 100.0,
+            available
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::result_builder;
+
+    #[test]
+    fn littles_law_mismatch() {
+        let run = |requests_sec: f64, avg_latency_ms: f64| {
+            result_builder(requests_sec, 0.0)
+                .avg_latency_ms(avg_latency_ms)
+                .build()
+                .unwrap()
+        };
+        let benchmark = Benchmark::new(2, 32, 10);
+        // 3200 requests/sec × 10ms = 32 requests in flight.
+        assert_eq!(littles_law(&run(3200.0, 10.0), &benchmark), None);
+        // 1000 requests/sec × 10ms = 10 requests in flight for 32 connections.
+        assert!(littles_law(&run(1000.0, 10.0), &benchmark)
+            .unwrap()
+            .contains("concurrency is 10.0"));
+        let mut constant = benchmark.clone();
+        constant.set_rate(Some(1000));
+        assert_eq!(littles_law(&run(1000.0, 10.0), &constant), None);
+    }
+
+    #[test]
+    fn methodology_checks() {
+        let run = result_builder(3200.0, 0.0)
+            .avg_latency_ms(10.0)
+            .stdev_latency_ms(25.0)
+            .build()
+            .unwrap();
+        let warnings = check(&run, &Benchmark::new(2, 32, 5), Duration::from_secs(10));
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("Latency standard deviation 25.00ms is 250%"));
+        assert!(warnings[1].starts_with("Duration of 5s is shorter than 10s"));
+        assert!(check(
+            &WrkResult::fail("boom".to_string()),
+            &Benchmark::new(2, 32, 5),
+            Duration::from_secs(10)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn generator_saturated() {
+        let benchmark = Benchmark::new(1, 32, 10);
+        let elapsed = Duration::from_secs(10);
+        assert!(generator_saturation(Duration::from_millis(9800), elapsed, &benchmark).is_some());
+        assert!(generator_saturation(Duration::from_secs(3), elapsed, &benchmark).is_none());
+        assert!(generator_saturation(Duration::from_secs(3), Duration::ZERO, &benchmark).is_none());
+    }
+}
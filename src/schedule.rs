@@ -0,0 +1,178 @@
+//! Cron-like schedules driving [`crate::Wrk::run_scheduled`].
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDate, Timelike, Utc};
+
+use crate::{Result, WrkError};
+
+/// Upper bound of the steps searching the next matching minute, enough to find the 29th of
+/// February of the next leap year.
+const MAX_STEPS: usize = 100_000;
+
+/// Cron-like schedule with the five usual fields: minute, hour, day of month, month and day of
+/// week, each one a `*`, a value, a range `a-b`, a step `*/n` or `a-b/n`, or a comma separated
+/// list of them. `@hourly`, `@daily` and `@weekly` are accepted as well. Times are in UTC.
+///
+/// When both the day of month and the day of week are restricted, a day matching either of
+/// them matches, as in cron.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+    retention_days: Option<u32>,
+}
+
+impl Schedule {
+    /// Parse a cron-like `expression`, IE: `*/30 * * * *` to run every half an hour.
+    pub fn cron(expression: &str) -> Result<Self> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            expression => expression,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(WrkError::Config(format!(
+                "Schedule `{}` must have 5 fields: minute, hour, day of month, month and day of week",
+                expression
+            )));
+        };
+        // Sunday is both 0 and 7.
+        let weekday_mask = field(weekdays, 0, 7)?;
+        Ok(Self {
+            minutes: field(minutes, 0, 59)?,
+            hours: field(hours, 0, 23)?,
+            days: field(days, 1, 31)?,
+            months: field(months, 1, 12)?,
+            weekdays: (weekday_mask | weekday_mask >> 7) & 0x7f,
+            any_day: days.starts_with('*'),
+            any_weekday: weekdays.starts_with('*'),
+            retention_days: None,
+        })
+    }
+
+    /// Compact the history of the days ending more than `days` days ago after every run, see
+    /// [`crate::Wrk::compact_history`].
+    pub fn with_retention(mut self, days: u32) -> Self {
+        self.retention_days = Some(days);
+        self
+    }
+
+    /// Days of history kept in full detail, if any.
+    pub fn retention_days(&self) -> Option<u32> {
+        self.retention_days
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & 1 << date.day() != 0;
+        let weekday = self.weekdays & 1 << date.weekday().num_days_from_sunday() != 0;
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => day,
+            (true, false) => weekday,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// First time strictly after `after` matching the schedule, if any.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut time = after.naive_utc().with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        for _ in 0..MAX_STEPS {
+            let date = time.date();
+            if self.months & 1 << date.month() == 0 {
+                let (year, month) = if date.month() == 12 {
+                    (date.year() + 1, 1)
+                } else {
+                    (date.year(), date.month() + 1)
+                };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(date) {
+                time = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & 1 << time.hour() == 0 {
+                time = time.with_minute(0)? + ChronoDuration::hours(1);
+            } else if self.minutes & 1 << time.minute() == 0 {
+                time += ChronoDuration::minutes(1);
+            } else {
+                return Some(time.and_utc());
+            }
+        }
+        None
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = WrkError;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        Self::cron(expression)
+    }
+}
+
+/// Bitmask of the values between `min` and `max` matched by a single cron field.
+fn field(spec: &str, min: u32, max: u32) -> Result<u64> {
+    let invalid = || {
+        WrkError::Config(format!(
+            "Invalid schedule field `{}`, values go from {} to {}",
+            spec, min, max
+        ))
+    };
+    let mut mask = 0;
+    for part in spec.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (
+                    start.parse().map_err(|_| invalid())?,
+                    end.parse().map_err(|_| invalid())?,
+                ),
+                None => {
+                    let value = range.parse().map_err(|_| invalid())?;
+                    (value, if step > 1 { max } else { value })
+                }
+            },
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn next_run() {
+        let at = |y, m, d, h, min| Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 2, 28, 23, 45, 30).unwrap();
+        let every_half_hour: Schedule = "*/30 * * * *".parse().unwrap();
+        assert_eq!(every_half_hour.next_after(now), Some(at(2024, 2, 29, 0, 0)));
+        let leap_day = Schedule::cron("15 3 29 2 *").unwrap();
+        assert_eq!(leap_day.next_after(at(2024, 3, 1, 0, 0)), Some(at(2028, 2, 29, 3, 15)));
+        let weekdays = Schedule::cron("0 9 * * 1-5").unwrap();
+        // 2024-03-02 is a Saturday.
+        assert_eq!(weekdays.next_after(at(2024, 3, 1, 9, 0)), Some(at(2024, 3, 4, 9, 0)));
+        let sunday = Schedule::cron("@weekly").unwrap();
+        assert_eq!(sunday, Schedule::cron("0 0 * * 7").unwrap());
+        assert_eq!(sunday.next_after(now), Some(at(2024, 3, 3, 0, 0)));
+        assert!(Schedule::cron("* * *").is_err());
+        assert!(Schedule::cron("60 * * * *").is_err());
+        assert!(Schedule::cron("*/0 * * * *").is_err());
+    }
+}
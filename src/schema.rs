@@ -0,0 +1,36 @@
+//! JSON Schema of the history format, available with the `schema` feature.
+//!
+//! External consumers (dashboards, other languages) can use these schemas to validate
+//! history dump files and generate bindings for them.
+use schemars::{schema::RootSchema, schema_for};
+
+use crate::{Benchmarks, WrkResult};
+
+/// JSON Schema of a single [`WrkResult`].
+pub fn wrk_result() -> RootSchema {
+    schema_for!(WrkResult)
+}
+
+/// JSON Schema of a history dump file, containing a list of [`WrkResult`].
+pub fn history() -> RootSchema {
+    schema_for!(Benchmarks)
+}
+
+/// JSON Schema of a history dump file, rendered as pretty printed JSON.
+pub fn history_json() -> crate::Result<String> {
+    Ok(serde_json::to_string_pretty(&history())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_schema() {
+        let schema = serde_json::to_value(history()).unwrap();
+        assert_eq!(schema["type"], "array");
+        let definitions = schema["definitions"].as_object().unwrap();
+        assert!(definitions["WrkResult"]["properties"]["requests_sec"].is_object());
+        assert!(definitions["Benchmark"]["properties"]["method"].is_object());
+    }
+}
@@ -0,0 +1,183 @@
+//! Composite score combining throughput, tail latency and errors into a single number, so
+//! teams with specific priorities can trend and gate CI on it.
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+
+use crate::{Result, WrkError, WrkResult};
+
+/// Weighted combination of requests/sec, p99 latency and error rate, each normalized against
+/// a target and capped at 1. Scores range from 0 to 100, reached by a run meeting both targets
+/// without errors.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Getters, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct CompositeScore {
+    /// Expected requests/sec.
+    #[getset(get = "pub")]
+    target_requests_sec: f64,
+    /// Expected p99 latency in milliseconds.
+    #[getset(get = "pub")]
+    target_p99_ms: f64,
+    /// Weight of requests/sec over the target.
+    #[builder(default = "0.5")]
+    #[serde(default)]
+    #[getset(get = "pub")]
+    requests_sec_weight: f64,
+    /// Weight of the target over the p99 latency.
+    #[builder(default = "0.3")]
+    #[serde(default)]
+    #[getset(get = "pub")]
+    p99_weight: f64,
+    /// Weight of the ratio of successful requests.
+    #[builder(default = "0.2")]
+    #[serde(default)]
+    #[getset(get = "pub")]
+    error_rate_weight: f64,
+}
+
+impl CompositeScoreBuilder {
+    fn validate(&self) -> std::result::Result<(), String> {
+        let weights = [
+            self.requests_sec_weight.unwrap_or(0.5),
+            self.p99_weight.unwrap_or(0.3),
+            self.error_rate_weight.unwrap_or(0.2),
+        ];
+        let targets = [self.target_requests_sec, self.target_p99_ms];
+        validate(weights, targets.iter().flatten().copied())
+    }
+}
+
+/// Check that the `weights` are not negative nor all zero and the `targets` are positive.
+fn validate(weights: [f64; 3], mut targets: impl Iterator<Item = f64>) -> std::result::Result<(), String> {
+    if weights.iter().any(|w| *w < 0.0) {
+        return Err("Composite score weights must not be negative".to_string());
+    }
+    if weights.iter().sum::<f64>() <= 0.0 {
+        return Err("Composite score weights must not all be zero".to_string());
+    }
+    if targets.any(|target| target <= 0.0) {
+        return Err("Composite score targets must be positive".to_string());
+    }
+    Ok(())
+}
+
+impl CompositeScore {
+    /// Set the expected requests/sec, which must be positive.
+    pub fn set_target_requests_sec(&mut self, target: f64) -> Result<&mut Self> {
+        self.update(|score| score.target_requests_sec = target)
+    }
+
+    /// Set the expected p99 latency in milliseconds, which must be positive.
+    pub fn set_target_p99_ms(&mut self, target: f64) -> Result<&mut Self> {
+        self.update(|score| score.target_p99_ms = target)
+    }
+
+    /// Set the weight of requests/sec, which must not be negative.
+    pub fn set_requests_sec_weight(&mut self, weight: f64) -> Result<&mut Self> {
+        self.update(|score| score.requests_sec_weight = weight)
+    }
+
+    /// Set the weight of the p99 latency, which must not be negative.
+    pub fn set_p99_weight(&mut self, weight: f64) -> Result<&mut Self> {
+        self.update(|score| score.p99_weight = weight)
+    }
+
+    /// Set the weight of the ratio of successful requests, which must not be negative.
+    pub fn set_error_rate_weight(&mut self, weight: f64) -> Result<&mut Self> {
+        self.update(|score| score.error_rate_weight = weight)
+    }
+
+    /// Apply `update`, keeping this score unchanged when the result is invalid.
+    fn update(&mut self, update: impl FnOnce(&mut Self)) -> Result<&mut Self> {
+        let mut score = *self;
+        update(&mut score);
+        validate(
+            [score.requests_sec_weight, score.p99_weight, score.error_rate_weight],
+            [score.target_requests_sec, score.target_p99_ms].into_iter(),
+        )
+        .map_err(WrkError::Config)?;
+        *self = score;
+        Ok(self)
+    }
+
+    /// Score of `result`, or `None` for failed runs. A missing p99 latency scores zero on
+    /// latency.
+    pub fn score(&self, result: &WrkResult) -> Option<f64> {
+        if !*result.success() {
+            return None;
+        }
+        let requests_sec = (result.requests_sec() / self.target_requests_sec).clamp(0.0, 1.0);
+        let p99 = if *result.p99_latency_ms() > 0.0 {
+            (self.target_p99_ms / result.p99_latency_ms()).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let successes = (1.0 - result.error_rate()).clamp(0.0, 1.0);
+        let weights = self.requests_sec_weight + self.p99_weight + self.error_rate_weight;
+        Some(
+            100.0
+                * (self.requests_sec_weight * requests_sec
+                    + self.p99_weight * p99
+                    + self.error_rate_weight * successes)
+                / weights,
+        )
+    }
+}
+
+impl WrkResult {
+    /// Fail with [`WrkError::Regression`] when the composite score of this result is missing
+    /// or lower than `min_score`.
+    pub fn check_score(&self, min_score: f64) -> Result<()> {
+        match self.score() {
+            Some(score) if *score >= min_score => Ok(()),
+            Some(score) => Err(WrkError::Regression(format!(
+                "composite score {:.2} is lower than {:.2}",
+                score, min_score
+            ))),
+            None => Err(WrkError::Regression("composite score not available".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::result_builder;
+
+    #[test]
+    fn composite_score() {
+        let score = CompositeScoreBuilder::default()
+            .target_requests_sec(1000.0)
+            .target_p99_ms(10.0)
+            .build()
+            .unwrap();
+        let result = |requests_sec: f64, p99: f64, errors: f64| {
+            result_builder(requests_sec, p99)
+                .requests(100.0)
+                .errors(errors)
+                .build()
+                .unwrap()
+        };
+        assert_eq!(score.score(&result(1000.0, 10.0, 0.0)), Some(100.0));
+        // Half the throughput, twice the latency and 50% errors.
+        assert_eq!(score.score(&result(500.0, 20.0, 50.0)), Some(50.0));
+        // Beating the targets does not hide errors.
+        assert_eq!(score.score(&result(4000.0, 1.0, 50.0)), Some(90.0));
+        assert!(CompositeScoreBuilder::default()
+            .target_requests_sec(1000.0)
+            .target_p99_ms(10.0)
+            .p99_weight(-1.0)
+            .build()
+            .is_err());
+        let mut weighted = score;
+        assert!(matches!(weighted.set_p99_weight(-1.0), Err(WrkError::Config(_))));
+        assert!(weighted.set_target_p99_ms(0.0).is_err());
+        assert_eq!(weighted, score);
+        weighted.set_p99_weight(0.0).unwrap();
+        assert_eq!(*weighted.p99_weight(), 0.0);
+
+        let mut scored = result(1000.0, 10.0, 0.0);
+        *scored.score_mut() = score.score(&scored);
+        assert!(scored.check_score(90.0).is_ok());
+        assert!(scored.check_score(110.0).is_err());
+    }
+}
@@ -0,0 +1,543 @@
+//! HTTP API exposing the history of a [`Wrk`] configuration and triggering its benchmarks
+//! remotely, enough to stand up a small performance dashboard.
+//!
+//! Endpoints:
+//! * `GET /history?period=Week`: recorded results as JSON, all of them by default.
+//! * `GET /status`: [`crate::SuiteStatus`] of the current or last suite as JSON.
+//! * `POST /runs`: start the benchmarks in the background, `409 Conflict` if a run is already
+//!   in progress. Only served when a [`Webhook`] is configured, as requests must be signed
+//!   with its secret and dated by the [`TIMESTAMP_HEADER`].
+//! * `GET /plot.png?period=Week`: chart of the recorded results, with the `plot` feature.
+//! * `POST /webhook`: start the benchmarks when the body is signed by the configured
+//!   [`Webhook`], IE: by a deploy pipeline, reporting the variance once they complete. As for
+//!   `POST /runs`, expired or replayed deliveries are rejected.
+use std::{
+    collections::HashMap,
+    fmt,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use axum::{
+    body::Bytes,
+    extract::{Extension, Query},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{BenchSession, Benchmark, Benchmarks, HistoryPeriod, Result, SuiteStatus, Variance, Wrk, WrkError};
+
+/// Header carrying the HMAC-SHA256 signature of the request, as `sha256=<hex digest>`.
+pub const SIGNATURE_HEADER: &str = "x-hub-signature-256";
+/// Header carrying the Unix time, in seconds, at which the request was signed.
+pub const TIMESTAMP_HEADER: &str = "x-wrk-api-bench-timestamp";
+/// Seconds a signed request is accepted for, either side of its timestamp.
+const SIGNATURE_TOLERANCE: i64 = 300;
+
+/// Reporter of the variance of a webhook triggered run.
+type Report = Box<dyn Fn(&WebhookEvent, &Variance) + Send + Sync>;
+
+/// Signed webhook triggering the benchmarks, IE: from a deploy pipeline once the new version
+/// is live. Requests must carry the Unix time they are sent at in the [`TIMESTAMP_HEADER`]
+/// and, in the [`SIGNATURE_HEADER`], the HMAC-SHA256 of that timestamp, a dot and their body,
+/// computed with the shared secret. Requests older than 5 minutes, or with a signature already
+/// seen, are rejected, so a captured request can not be replayed. Once the run completes, its
+/// variance against the previous run, or a baseline for the first one, is passed to the
+/// reporter, IE: to post it as a comment through [`crate::codehost`].
+pub struct Webhook {
+    secret: Vec<u8>,
+    report: Report,
+    /// Digests of the accepted signatures with their timestamp, until they expire.
+    seen: Mutex<HashMap<Vec<u8>, i64>>,
+}
+
+impl fmt::Debug for Webhook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Webhook").finish_non_exhaustive()
+    }
+}
+
+impl Webhook {
+    /// Webhook verified with `secret`, reporting the variance of every run to `report`.
+    pub fn new<S, F>(secret: S, report: F) -> Self
+    where
+        S: Into<Vec<u8>>,
+        F: Fn(&WebhookEvent, &Variance) + Send + Sync + 'static,
+    {
+        Self {
+            secret: secret.into(),
+            report: Box::new(report),
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `signature`, as sent in the [`SIGNATURE_HEADER`], signs `timestamp`, as sent in
+    /// the [`TIMESTAMP_HEADER`], and `body`.
+    pub fn verify(&self, timestamp: &str, body: &[u8], signature: &str) -> bool {
+        let Some(digest) = signature.strip_prefix("sha256=").and_then(from_hex) else {
+            return false;
+        };
+        let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(&self.secret) else {
+            return false;
+        };
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        mac.verify_slice(&digest).is_ok()
+    }
+
+    /// Whether the request signed at `timestamp` is valid at `now` and was not accepted before.
+    fn accept(&self, timestamp: &str, body: &[u8], signature: &str, now: DateTime<Utc>) -> bool {
+        let Ok(signed_at) = timestamp.parse::<i64>() else {
+            return false;
+        };
+        if (now.timestamp() - signed_at).abs() > SIGNATURE_TOLERANCE || !self.verify(timestamp, body, signature) {
+            return false;
+        }
+        let digest = signature.strip_prefix("sha256=").and_then(from_hex).unwrap_or_default();
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.retain(|_, signed_at| (now.timestamp() - *signed_at).abs() <= SIGNATURE_TOLERANCE);
+        seen.insert(digest, signed_at).is_none()
+    }
+}
+
+/// JSON body of a webhook request. Unknown fields are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    /// Freshly deployed version.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Environment the version was deployed to.
+    #[serde(default)]
+    pub environment: Option<String>,
+}
+
+fn from_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) || !value.is_ascii() {
+        return None;
+    }
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+/// Clears the running flag once a run ends, even when it panics.
+struct Running<'a>(&'a AtomicBool);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Configuration and benchmarks shared by the handlers.
+#[derive(Debug)]
+struct State {
+    wrk: Arc<Wrk>,
+    benchmarks: Vec<Benchmark>,
+    running: AtomicBool,
+    webhook: Option<Webhook>,
+}
+
+impl State {
+    /// Run the benchmarks in the background, calling `then` with the session once done.
+    /// Returns `false` when a run is already in progress.
+    fn start<F>(self: Arc<Self>, then: F) -> bool
+    where
+        F: FnOnce(&Self, &mut BenchSession) -> Result<()> + Send + 'static,
+    {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        tokio::task::spawn_blocking(move || {
+            let _running = Running(&self.running);
+            let mut session = BenchSession::new(self.wrk.clone());
+            if let Err(e) = session.bench(&self.benchmarks).and_then(|_| then(&self, &mut session)) {
+                error!("Remotely triggered run failed: {}", e);
+            }
+        });
+        true
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PeriodQuery {
+    period: Option<HistoryPeriod>,
+}
+
+impl PeriodQuery {
+    fn period(&self) -> HistoryPeriod {
+        self.period.clone().unwrap_or(HistoryPeriod::Forever)
+    }
+}
+
+/// Error answered with its status code and message.
+#[derive(Debug)]
+struct ApiError(StatusCode, String);
+
+impl From<WrkError> for ApiError {
+    fn from(error: WrkError) -> Self {
+        let status = match error {
+            WrkError::History(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self(status, error.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, self.1).into_response()
+    }
+}
+
+/// Router serving the API for `wrk`. When a `webhook` is configured, `benchmarks` run on
+/// `POST /runs` and `POST /webhook` requests signed with its secret.
+pub fn router(wrk: Arc<Wrk>, benchmarks: Vec<Benchmark>, webhook: Option<Webhook>) -> Router {
+    let state = Arc::new(State {
+        wrk,
+        benchmarks,
+        running: AtomicBool::new(false),
+        webhook,
+    });
+    let router = Router::new()
+        .route("/history", get(history))
+        .route("/status", get(status))
+        .route("/webhook", post(webhook_trigger));
+    let router = match state.webhook {
+        Some(_) => router.route("/runs", post(trigger)),
+        None => router,
+    };
+    #[cfg(feature = "plot")]
+    let router = router.route("/plot.png", get(plot));
+    router.layer(Extension(state))
+}
+
+/// Serve the API for `wrk` on `addr` until the server fails.
+pub async fn serve(
+    addr: SocketAddr,
+    wrk: Arc<Wrk>,
+    benchmarks: Vec<Benchmark>,
+    webhook: Option<Webhook>,
+) -> Result<()> {
+    info!("Serving the benchmarks API on http://{}", addr);
+    axum::Server::try_bind(&addr)
+        .map_err(|e| WrkError::Exec(format!("Unable to bind {}: {}", addr, e)))?
+        .serve(router(wrk, benchmarks, webhook).into_make_service())
+        .await
+        .map_err(|e| WrkError::Exec(format!("Benchmarks API server failed: {}", e)))
+}
+
+/// Run `f` on the blocking thread pool, as reading the history and running wrk block.
+async fn blocking<T, F>(f: F) -> std::result::Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(ApiError::from)
+}
+
+async fn history(
+    Extension(state): Extension<Arc<State>>,
+    Query(query): Query<PeriodQuery>,
+) -> std::result::Result<Json<Benchmarks>, ApiError> {
+    blocking(move || state.wrk.history(&query.period(), None))
+        .await
+        .map(Json)
+}
+
+async fn status(Extension(state): Extension<Arc<State>>) -> std::result::Result<Json<Option<SuiteStatus>>, ApiError> {
+    blocking(move || state.wrk.suite_status()).await.map(Json)
+}
+
+/// Answer to a request starting a run.
+fn started(started: bool) -> Response {
+    if started {
+        (StatusCode::ACCEPTED, "Run started, follow it on /status").into_response()
+    } else {
+        (StatusCode::CONFLICT, "A run is already in progress").into_response()
+    }
+}
+
+/// Check the request is recent, was not seen before and is signed with the secret of the
+/// configured webhook.
+fn authorize(state: &State, headers: &HeaderMap, body: &[u8]) -> std::result::Result<(), ApiError> {
+    let Some(webhook) = &state.webhook else {
+        return Err(ApiError(StatusCode::NOT_FOUND, "No webhook configured".to_string()));
+    };
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let accepted = match (header(TIMESTAMP_HEADER), header(SIGNATURE_HEADER)) {
+        (Some(timestamp), Some(signature)) => webhook.accept(timestamp, body, signature, state.wrk.now()),
+        _ => false,
+    };
+    if !accepted {
+        warn!("Rejected request with a missing, expired, replayed or invalid signature");
+        return Err(ApiError(StatusCode::UNAUTHORIZED, "Invalid signature".to_string()));
+    }
+    Ok(())
+}
+
+async fn trigger(Extension(state): Extension<Arc<State>>, headers: HeaderMap, body: Bytes) -> Response {
+    if let Err(error) = authorize(&state, &headers, &body) {
+        return error.into_response();
+    }
+    started(state.start(|_, _| Ok(())))
+}
+
+async fn webhook_trigger(Extension(state): Extension<Arc<State>>, headers: HeaderMap, body: Bytes) -> Response {
+    if let Err(error) = authorize(&state, &headers, &body) {
+        return error.into_response();
+    }
+    let event = if body.is_empty() {
+        WebhookEvent::default()
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(event) => event,
+            Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid webhook body: {}", e)).into_response(),
+        }
+    };
+    info!("Webhook triggered a run of version {:?}", event.version);
+    started(state.clone().start(move |state, session| {
+        // The first run of a new deployment target has nothing to compare with.
+        let variance = match session.variance(HistoryPeriod::Last) {
+            Err(WrkError::History(_)) => Variance::baseline(session.best()?),
+            variance => variance?,
+        };
+        if let Some(webhook) = &state.webhook {
+            (webhook.report)(&event, &variance);
+        }
+        Ok(())
+    }))
+}
+
+#[cfg(feature = "plot")]
+async fn plot(
+    Extension(state): Extension<Arc<State>>,
+    Query(query): Query<PeriodQuery>,
+) -> std::result::Result<impl IntoResponse, ApiError> {
+    let image = blocking(move || {
+        let output = tempfile::Builder::new().suffix(".png").tempfile()?;
+        let history = state.wrk.history(&query.period(), None)?;
+        state
+            .wrk
+            .plot(&state.wrk.history_namespace(), output.path(), &history)?;
+        Ok(std::fs::read(output.path())?)
+    })
+    .await?;
+    Ok((
+        axum::response::Headers([(axum::http::header::CONTENT_TYPE, "image/png")]),
+        image,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::{test_utils::CorpusRunner, CommandSpec, ProcessOutput, ProcessRunner, WrkBuilder, WrkResultBuilder};
+
+    /// Runner failing right away, as wrk does against a target which is down.
+    #[derive(Debug)]
+    struct DownRunner;
+
+    impl ProcessRunner for DownRunner {
+        fn run(&self, _spec: &CommandSpec) -> Result<ProcessOutput> {
+            Ok(ProcessOutput::new(
+                Some(1),
+                String::new(),
+                "unable to connect".to_string(),
+            ))
+        }
+    }
+
+    /// Signature header value of `body` signed with `secret` at `timestamp`.
+    fn sign(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(body);
+        let digest: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        format!("sha256={}", digest)
+    }
+
+    #[tokio::test]
+    async fn endpoints() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13739".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .runner(DownRunner)
+            .build()
+            .unwrap();
+        let result = WrkResultBuilder::default()
+            .success(true)
+            .benchmark(Benchmark::new(1, 1, 1))
+            .requests_sec(100.0)
+            .build()
+            .unwrap();
+        wrk.record(&vec![result]).unwrap();
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(router(Arc::new(wrk.clone()), vec![Benchmark::new(1, 1, 1)], None).into_make_service());
+        tokio::spawn(server);
+        let client = hyper::Client::new();
+        let post = |base: &str, signed: Option<(i64, String)>| {
+            let mut request = hyper::Request::post(format!("{}/runs", base));
+            if let Some((timestamp, signature)) = signed {
+                request = request
+                    .header(TIMESTAMP_HEADER, timestamp.to_string())
+                    .header(SIGNATURE_HEADER, signature);
+            }
+            client.request(request.body(hyper::Body::empty()).unwrap())
+        };
+        assert_eq!(post(&base, None).await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let webhook = Webhook::new("s3cr3t", |_, _| {});
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(router(Arc::new(wrk), vec![Benchmark::new(1, 1, 1)], Some(webhook)).into_make_service());
+        tokio::spawn(server);
+        let get = |path: &str| client.get(format!("{}{}", base, path).parse().unwrap());
+
+        let response = get("/history").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let history: Benchmarks = serde_json::from_slice(&body).unwrap();
+        assert_eq!(*history[0].requests_sec(), 100.0);
+        assert_eq!(
+            get("/history?period=Bogus").await.unwrap().status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        assert_eq!(post(&base, None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let expired = Utc::now().timestamp() - 2 * SIGNATURE_TOLERANCE;
+        assert_eq!(
+            post(&base, Some((expired, sign(b"s3cr3t", expired, b""))))
+                .await
+                .unwrap()
+                .status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let now = Utc::now().timestamp();
+        let signed = (now, sign(b"s3cr3t", now, b""));
+        assert_eq!(
+            post(&base, Some(signed.clone())).await.unwrap().status(),
+            StatusCode::ACCEPTED
+        );
+        // A captured request can not start another run.
+        assert_eq!(
+            post(&base, Some(signed)).await.unwrap().status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let status = loop {
+            let body = hyper::body::to_bytes(get("/status").await.unwrap().into_body())
+                .await
+                .unwrap();
+            match serde_json::from_slice::<Option<SuiteStatus>>(&body).unwrap() {
+                Some(status) if status.finished() => break status,
+                _ => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        assert!(!*status.last_result().as_ref().unwrap().success());
+    }
+
+    #[tokio::test]
+    async fn signed_webhook() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13740".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .runner(CorpusRunner)
+            .build()
+            .unwrap();
+        let (reports, reported) = std::sync::mpsc::channel();
+        let reports = std::sync::Mutex::new(reports);
+        let webhook = Webhook::new("s3cr3t", move |event, variance| {
+            let _ = reports.lock().unwrap().send((event.clone(), variance.baseline));
+        });
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let url = format!("http://{}/webhook", listener.local_addr().unwrap());
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(router(Arc::new(wrk), vec![Benchmark::new(1, 1, 1)], Some(webhook)).into_make_service());
+        tokio::spawn(server);
+        let client = hyper::Client::new();
+        let body = r#"{"version": "1.4.2", "environment": "staging"}"#;
+        let now = Utc::now().timestamp();
+        let send = |timestamp: i64, signature: String| {
+            let request = hyper::Request::post(&url)
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, signature)
+                .body(hyper::Body::from(body))
+                .unwrap();
+            client.request(request)
+        };
+
+        let forged = format!("sha256={}", "00".repeat(32));
+        assert_eq!(send(now, forged).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let expired = now - 2 * SIGNATURE_TOLERANCE;
+        assert_eq!(
+            send(expired, sign(b"s3cr3t", expired, body.as_bytes()))
+                .await
+                .unwrap()
+                .status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let delivery = sign(b"s3cr3t", now, body.as_bytes());
+        assert_eq!(
+            send(now, delivery.clone()).await.unwrap().status(),
+            StatusCode::ACCEPTED
+        );
+        // A captured delivery can not start the benchmarks again.
+        assert_eq!(send(now, delivery).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let (event, baseline) = tokio::task::spawn_blocking(move || reported.recv().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(event.version.as_deref(), Some("1.4.2"));
+        assert!(baseline);
+    }
+
+    #[tokio::test]
+    async fn panicking_run() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13739".to_string())
+            .history_store(crate::HistoryStore::in_memory())
+            .runner(DownRunner)
+            .build()
+            .unwrap();
+        let state = Arc::new(State {
+            wrk: Arc::new(wrk),
+            benchmarks: vec![Benchmark::new(1, 1, 1)],
+            running: AtomicBool::new(false),
+            webhook: None,
+        });
+        assert!(state.clone().start(|_, _| panic!("reporter failed")));
+        while state.running.load(Ordering::SeqCst) {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(state.clone().start(|_, _| Ok(())));
+    }
+}
@@ -0,0 +1,406 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use getset::Getters;
+use uuid::Uuid;
+
+use crate::{
+    benchmark::{Benchmark, BenchmarkBuilder},
+    overrides::Overrides,
+    result::{Variance, WrkResult},
+    wrk::{Benchmarks, HistoryPeriod, RecordPolicy, Wrk},
+    Result, SuiteSummary, WrkError,
+};
+
+/// Benchmarking session running a shared, immutable [`Wrk`] configuration.
+///
+/// The session owns the current and historical results, so multiple sessions (IE: parallel
+/// tests or requests served by an async server) can share the same configured [`Wrk`].
+#[derive(Debug, Clone, Getters)]
+pub struct BenchSession {
+    /// Shared benchmark configuration.
+    #[getset(get = "pub")]
+    wrk: Arc<Wrk>,
+    /// Set of benchmarks for the current session.
+    #[getset(get = "pub")]
+    benchmarks: Benchmarks,
+    /// Historical benchmarks data, loaded when calculating the variance.
+    #[getset(get = "pub")]
+    benchmarks_history: Benchmarks,
+    /// Current benchmark date and time.
+    #[getset(get = "pub")]
+    benchmark_date: Option<DateTime<Utc>>,
+    /// Identifier of the current suite, shared by all its results.
+    #[getset(get = "pub")]
+    suite_id: Option<Uuid>,
+    /// Whether the target restarted since the last run, see [`BenchSession::target_restarted`].
+    #[getset(get = "pub")]
+    restarted: bool,
+}
+
+impl From<Wrk> for BenchSession {
+    fn from(wrk: Wrk) -> Self {
+        Self::new(Arc::new(wrk))
+    }
+}
+
+impl BenchSession {
+    /// Create a new session for the given configuration.
+    pub fn new(wrk: Arc<Wrk>) -> Self {
+        Self {
+            wrk,
+            benchmarks: Benchmarks::new(),
+            benchmarks_history: Benchmarks::new(),
+            benchmark_date: None,
+            suite_id: None,
+            restarted: false,
+        }
+    }
+
+    /// Lifecycle hook to call after (re)starting the target, IE: a container or serverless
+    /// function: the next run is recorded as a [`WrkResult::cold_start`], in the cold start
+    /// series of [`Wrk::cold_start_history`], leaving the steady-state history untouched.
+    pub fn target_restarted(&mut self) -> &mut Self {
+        self.restarted = true;
+        self
+    }
+
+    /// Run the given benchmarks and store their results in the history directory according
+    /// to the configured [`RecordPolicy`].
+    ///
+    /// With [`RecordPolicy::Always`] the history file is rewritten after every single run, so
+    /// partial suites survive interruptions.
+    ///
+    /// The environment variables `WRK_API_BENCH_URL`, `WRK_API_BENCH_HISTORY_DIR`,
+    /// `WRK_API_BENCH_PROJECT`, `WRK_API_BENCH_MAX_ERROR_PERCENTAGE`, `WRK_API_BENCH_THREADS`,
+    /// `WRK_API_BENCH_CONNECTIONS` and `WRK_API_BENCH_DURATION` (IE: `30s` or `2m`) override
+    /// the configuration of the session and of every benchmark.
+    pub fn bench(&mut self, benchmarks: &[Benchmark]) -> Result<()> {
+        self.bench_with(benchmarks, |_| {})
+    }
+
+    /// Same as [`BenchSession::bench`], calling `observer` as soon as every single result is
+    /// available, IE: to report the progress of a long suite.
+    pub fn bench_with<F: FnMut(&WrkResult)>(&mut self, benchmarks: &[Benchmark], mut observer: F) -> Result<()> {
+        let (benchmarks, date, suite_id) = self.start(benchmarks)?;
+        let previous = self.benchmarks.len();
+        let wrk = self.wrk.clone();
+        let session_benchmarks = &mut self.benchmarks;
+        let restarted = &mut self.restarted;
+        wrk.run(&benchmarks, date, suite_id, |result| {
+            collect(&wrk, date, session_benchmarks, restarted, result, &mut observer)
+        })?;
+        self.record(previous, date)
+    }
+
+    /// Same as [`BenchSession::bench`], awaiting wrk without blocking the async runtime of the
+    /// caller, see [`crate::ProcessRunner::run_async`]. Results are recorded with blocking file
+    /// operations, which are short.
+    pub async fn bench_async(&mut self, benchmarks: &[Benchmark]) -> Result<()> {
+        let (benchmarks, date, suite_id) = self.start(benchmarks)?;
+        let previous = self.benchmarks.len();
+        let wrk = self.wrk.clone();
+        let session_benchmarks = &mut self.benchmarks;
+        let restarted = &mut self.restarted;
+        wrk.run_async(&benchmarks, date, suite_id, |result| {
+            collect(&wrk, date, session_benchmarks, restarted, result, &mut |_| {})
+        })
+        .await?;
+        self.record(previous, date)
+    }
+
+    /// Apply the environment overrides and start a new suite running `benchmarks`.
+    fn start(&mut self, benchmarks: &[Benchmark]) -> Result<(Vec<Benchmark>, DateTime<Utc>, Uuid)> {
+        let (wrk, benchmarks) = Overrides::from_env()?.apply(&self.wrk, benchmarks);
+        self.wrk = wrk;
+        let date = self.wrk.now();
+        let suite_id = Uuid::new_v4();
+        self.benchmark_date = Some(date);
+        self.suite_id = Some(suite_id);
+        Ok((benchmarks, date, suite_id))
+    }
+
+    /// Record the results of the suite started at `date`, from index `previous` of the session
+    /// results, unless the [`RecordPolicy`] already did or forbids it.
+    fn record(&self, previous: usize, date: DateTime<Utc>) -> Result<()> {
+        let policy = *self.wrk.record_policy();
+        match policy {
+            RecordPolicy::Always => {}
+            RecordPolicy::OnSuccess if self.benchmarks[previous..].iter().all(|r| *r.success()) => {
+                self.wrk.dump(date, &self.benchmarks)?;
+            }
+            _ => info!("Not recording current benchmark, record policy is {:?}", policy),
+        }
+        Ok(())
+    }
+
+    /// Run the benchmarks generated by [`BenchmarkBuilder::exponential`], summarizing their
+    /// results with the configuration to recommend.
+    pub fn bench_exponential(&mut self, duration: Option<Duration>) -> Result<SuiteSummary> {
+        let previous = self.benchmarks.len();
+        self.bench(&BenchmarkBuilder::exponential(duration))?;
+        Ok(SuiteSummary::new(
+            self.benchmarks[previous..].to_vec(),
+            *self.wrk.p99_ceiling_ms(),
+        ))
+    }
+
+    /// Run a soak test re-invoking `benchmark` in consecutive windows of `window` duration
+    /// until its whole duration is covered.
+    ///
+    /// Every window gets its own date and, according to the configured [`RecordPolicy`], its
+    /// own history file. The returned results can be plotted with [`Wrk::plot`] to surface
+    /// degradations of the target over the soak. After [`BenchSession::target_restarted`], the
+    /// first window is recorded as a cold start.
+    pub fn bench_soak(&mut self, benchmark: &Benchmark, window: Duration) -> Result<Benchmarks> {
+        let (wrk, mut benchmarks) = Overrides::from_env()?.apply(&self.wrk, std::slice::from_ref(benchmark));
+        self.wrk = wrk;
+        let benchmark = &benchmarks.remove(0);
+        if window.as_secs() == 0 {
+            return Err(WrkError::Exec(
+                "Soak window must be at least one second long".to_string(),
+            ));
+        }
+        let policy = *self.wrk.record_policy();
+        // All the windows belong to the same suite.
+        let suite_id = Uuid::new_v4();
+        self.suite_id = Some(suite_id);
+        let mut windows = Benchmarks::new();
+        let mut elapsed = Duration::ZERO;
+        let mut index = 1;
+        while elapsed < *benchmark.duration() {
+            let duration = window.min(*benchmark.duration() - elapsed);
+            let mut window_benchmark = benchmark.clone();
+            window_benchmark.set_duration(duration);
+            window_benchmark.set_label(Some(format!("{}-window-{}", benchmark.name(), index)));
+            let date = self.wrk.now();
+            self.benchmark_date = Some(date);
+            let mut results = self.wrk.run(&[window_benchmark], date, suite_id, |_| Ok(()))?;
+            if std::mem::take(&mut self.restarted) {
+                for result in &mut results {
+                    result.set_cold_start(true);
+                }
+            }
+            let record = match policy {
+                RecordPolicy::Always => true,
+                RecordPolicy::OnSuccess => results.iter().all(|r| *r.success()),
+                _ => false,
+            };
+            if record && !results.is_empty() {
+                self.wrk.dump(date, &results)?;
+            }
+            windows.extend(results);
+            elapsed += duration;
+            index += 1;
+        }
+        self.benchmarks.extend(windows.iter().cloned());
+        Ok(windows)
+    }
+
+    fn load(&mut self, period: HistoryPeriod) -> Result<()> {
+        self.benchmarks_history = self.wrk.history(&period, self.benchmark_date.as_ref())?;
+        Ok(())
+    }
+
+    /// Best steady-state result of the session, leaving out the cold starts.
+    pub(crate) fn best(&self) -> Result<WrkResult> {
+        let steady: Benchmarks = self.benchmarks().iter().filter(|r| !*r.cold_start()).cloned().collect();
+        self.wrk.best_benchmark(&steady)
+    }
+
+    fn historical_best(&self, benchmark: &Benchmark) -> Result<WrkResult> {
+        let comparable: Benchmarks = self
+            .benchmarks_history()
+            .iter()
+            .filter(|v| v.benchmark().as_ref().is_some_and(|b| b.is_comparable(benchmark)))
+            .cloned()
+            .collect();
+        if comparable.is_empty() {
+            return Err(WrkError::History(format!(
+                "No historical run comparable with {} threads, {} connections, {} seconds benchmark",
+                benchmark.threads(),
+                benchmark.connections(),
+                benchmark.duration().as_secs()
+            )));
+        }
+        self.wrk.best_benchmark(&comparable)
+    }
+
+    /// Historical and current benchmarks, without duplicated runs.
+    pub fn all_benchmarks(&self) -> Benchmarks {
+        let mut history = self.benchmarks_history().clone();
+        history.append(&mut self.benchmarks().clone());
+        crate::wrk::dedup(history)
+    }
+
+    /// Calculate the variance between the best current benchmark and the best
+    /// historical one in the given period run with the same [`Benchmark`] configuration.
+    /// Cold starts are never used as the current benchmark.
+    ///
+    /// When no history was recorded yet and [`Wrk::bootstrap_baseline`] is set, the best
+    /// current benchmark is returned as a [`Variance::baseline`].
+    pub fn variance(&mut self, period: HistoryPeriod) -> Result<Variance> {
+        if *self.wrk.bootstrap_baseline() && self.wrk.history_files(self.benchmark_date.as_ref())?.is_empty() {
+            info!("No history recorded yet, current run is the baseline");
+            return Ok(Variance::baseline(self.best()?));
+        }
+        self.load(period)?;
+        let new = self.best()?;
+        let benchmark = new.benchmark().as_ref().ok_or_else(|| {
+            WrkError::Stats("Best current run has no benchmark configuration to compare with".to_string())
+        })?;
+        let old = self.historical_best(benchmark)?;
+        Ok(Variance::new(new, old))
+    }
+}
+
+/// Add a single `result` of the suite started at `date` to the session `benchmarks`.
+fn collect<F: FnMut(&WrkResult)>(
+    wrk: &Wrk,
+    date: DateTime<Utc>,
+    benchmarks: &mut Benchmarks,
+    restarted: &mut bool,
+    result: &WrkResult,
+    observer: &mut F,
+) -> Result<()> {
+    let mut result = result.clone();
+    if std::mem::take(restarted) {
+        result.set_cold_start(true);
+    }
+    benchmarks.push(result.clone());
+    if *wrk.record_policy() == RecordPolicy::Always {
+        wrk.dump(date, benchmarks)?;
+    }
+    observer(&result);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::CorpusRunner, HistoryStore, WrkBuilder};
+
+    #[test]
+    fn soak_windows() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13735".to_string())
+            .history_store(HistoryStore::in_memory())
+            .record_policy(RecordPolicy::Never)
+            .runner(CorpusRunner)
+            .build()
+            .unwrap();
+        let mut session = BenchSession::from(wrk);
+        let windows = session
+            .bench_soak(&Benchmark::new(1, 1, 5), Duration::from_secs(2))
+            .unwrap();
+        let durations: Vec<_> = windows
+            .iter()
+            .map(|r| r.benchmark().as_ref().unwrap().duration().as_secs())
+            .collect();
+        assert_eq!(durations, vec![2, 2, 1]);
+        assert!(windows.iter().all(|r| *r.success()));
+        assert_eq!(
+            windows[2].benchmark().as_ref().unwrap().label().as_deref(),
+            Some("1-1-5-window-3")
+        );
+        assert!(windows.iter().all(|r| Some(*r.suite_id()) == *session.suite_id()));
+        assert_ne!(windows[0].run_id(), windows[1].run_id());
+        assert!(windows.iter().all(|r| !*r.cold_start()));
+        assert!(session.bench_soak(&Benchmark::new(1, 1, 5), Duration::ZERO).is_err());
+
+        session.target_restarted();
+        let windows = session
+            .bench_soak(&Benchmark::new(1, 1, 4), Duration::from_secs(2))
+            .unwrap();
+        let cold: Vec<_> = windows.iter().map(|r| *r.cold_start()).collect();
+        assert_eq!(cold, vec![true, false]);
+        assert!(!session.restarted);
+    }
+
+    #[tokio::test]
+    async fn bench_async() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13742".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .runner(CorpusRunner)
+            .build()
+            .unwrap();
+        let mut session = BenchSession::from(wrk);
+        // The future is Send, so it can run on any worker of the runtime.
+        let session = tokio::spawn(async move {
+            session
+                .bench_async(&[Benchmark::new(1, 1, 1), Benchmark::new(1, 2, 1)])
+                .await?;
+            Ok::<_, WrkError>(session)
+        })
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(session.benchmarks().len(), 2);
+        assert!(session.benchmarks().iter().all(|r| *r.success()));
+        assert!(session
+            .benchmarks()
+            .iter()
+            .all(|r| Some(*r.suite_id()) == *session.suite_id()));
+        assert_eq!(session.wrk().history(&HistoryPeriod::Last, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn bootstrap_baseline() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = |bootstrap| {
+            WrkBuilder::default()
+                .url("http://127.0.0.1:13741".to_string())
+                .history_dir(history_dir.path().to_path_buf())
+                .bootstrap_baseline(bootstrap)
+                .build()
+                .unwrap()
+        };
+        let result = crate::WrkResultBuilder::default()
+            .success(true)
+            .requests_sec(100.0)
+            .benchmark(Benchmark::new(1, 1, 1))
+            .build()
+            .unwrap();
+        let mut session = BenchSession::from(wrk(false));
+        session.benchmarks = vec![result.clone()];
+        assert!(matches!(
+            session.variance(HistoryPeriod::Last),
+            Err(WrkError::History(_))
+        ));
+
+        let mut session = BenchSession::from(wrk(true));
+        session.benchmarks = vec![result];
+        let variance = session.variance(HistoryPeriod::Last).unwrap();
+        assert!(variance.baseline);
+        assert_eq!(*variance.old.requests_sec(), 100.0);
+        assert!(variance.check_regression(5.0).is_ok());
+    }
+
+    #[test]
+    fn variance_ignores_cold_starts() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13741".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .build()
+            .unwrap();
+        let result = |requests_sec: f64, cold_start: bool| {
+            crate::test_utils::result_builder(requests_sec, 1.0)
+                .cold_start(cold_start)
+                .build()
+                .unwrap()
+        };
+        wrk.dump(Utc::now() - chrono::Duration::minutes(1), &vec![result(100.0, false)])
+            .unwrap();
+        let mut session = BenchSession::from(wrk);
+        session.benchmarks = vec![result(500.0, true), result(90.0, false)];
+        let variance = session.variance(HistoryPeriod::Last).unwrap();
+        assert_eq!(*variance.new.requests_sec(), 90.0);
+        assert_eq!(*variance.old.requests_sec(), 100.0);
+
+        session.benchmarks = vec![result(500.0, true)];
+        assert!(session.variance(HistoryPeriod::Last).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+//! Machine-readable progress of the running suite, so dashboards and CI timeouts can observe
+//! long suites from outside the process.
+use std::{path::Path, time::Duration};
+
+use chrono::{DateTime, Utc};
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{Benchmark, Result, Storage, WrkResult};
+
+/// Name of the progress file stored in the history directory.
+pub(crate) const STATUS_FILE: &str = "status.json";
+
+/// Progress of a suite, rewritten in the history directory before and after every run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Getters)]
+pub struct SuiteStatus {
+    /// Identifier of the suite.
+    #[getset(get = "pub")]
+    suite_id: Uuid,
+    /// Start of the suite.
+    #[getset(get = "pub")]
+    started: DateTime<Utc>,
+    /// Last update of this status.
+    #[getset(get = "pub")]
+    updated: DateTime<Utc>,
+    /// Number of completed runs.
+    #[getset(get = "pub")]
+    completed: usize,
+    /// Number of runs of the suite.
+    #[getset(get = "pub")]
+    total: usize,
+    /// Key of the running benchmark, missing between runs.
+    #[getset(get = "pub")]
+    running: Option<String>,
+    /// Seconds elapsed since the start of the suite.
+    #[getset(get = "pub")]
+    elapsed_secs: u64,
+    /// Estimated seconds to the end of the suite.
+    #[getset(get = "pub")]
+    remaining_secs: u64,
+    /// Estimated end of the suite.
+    #[getset(get = "pub")]
+    eta: DateTime<Utc>,
+    /// Summary of the last completed run.
+    #[getset(get = "pub")]
+    last_result: Option<RunSummary>,
+}
+
+/// Summary of a completed run in a [`SuiteStatus`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Getters)]
+pub struct RunSummary {
+    /// Key of the benchmark.
+    #[getset(get = "pub")]
+    benchmark: String,
+    /// Whether the run succeeded.
+    #[getset(get = "pub")]
+    success: bool,
+    /// Requests per second.
+    #[getset(get = "pub")]
+    requests_sec: f64,
+    /// 99th percentile latency in milliseconds.
+    #[getset(get = "pub")]
+    p99_latency_ms: f64,
+    /// Total errors.
+    #[getset(get = "pub")]
+    errors: f64,
+}
+
+impl SuiteStatus {
+    pub(crate) fn new(suite_id: Uuid, total: usize, remaining: Duration) -> Self {
+        let now = Utc::now();
+        let mut status = Self {
+            suite_id,
+            started: now,
+            updated: now,
+            completed: 0,
+            total,
+            running: None,
+            elapsed_secs: 0,
+            remaining_secs: 0,
+            eta: now,
+            last_result: None,
+        };
+        status.update(remaining);
+        status
+    }
+
+    fn update(&mut self, remaining: Duration) {
+        self.updated = Utc::now();
+        self.elapsed_secs = (self.updated - self.started).num_seconds().max(0) as u64;
+        self.remaining_secs = remaining.as_secs();
+        self.eta = self.updated + chrono::Duration::from_std(remaining).unwrap_or_else(|_| chrono::Duration::zero());
+    }
+
+    /// Whether every run of the suite completed.
+    pub fn finished(&self) -> bool {
+        self.completed == self.total
+    }
+
+    pub(crate) fn start(&mut self, benchmark: &Benchmark, remaining: Duration) {
+        self.update(remaining);
+        self.running = Some(benchmark.key());
+    }
+
+    pub(crate) fn complete(&mut self, benchmark: &Benchmark, result: Option<&WrkResult>, remaining: Duration) {
+        self.update(remaining);
+        self.completed += 1;
+        self.running = None;
+        if let Some(result) = result {
+            self.last_result = Some(RunSummary {
+                benchmark: benchmark.key(),
+                success: *result.success(),
+                requests_sec: *result.requests_sec(),
+                p99_latency_ms: *result.p99_latency_ms(),
+                errors: *result.errors(),
+            });
+        }
+    }
+}
+
+/// Atomically replace the progress file in `dir`.
+pub(crate) fn write(storage: &dyn Storage, dir: &Path, status: &SuiteStatus) -> Result<()> {
+    storage.write(&dir.join(STATUS_FILE), &serde_json::to_vec_pretty(status)?)?;
+    Ok(())
+}
+
+/// Read the progress file in `dir`, missing when no suite ran yet.
+pub(crate) fn read(storage: &dyn Storage, dir: &Path) -> Result<Option<SuiteStatus>> {
+    let path = dir.join(STATUS_FILE);
+    if !storage.exists(&path) {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_slice(&storage.read(&path)?)?))
+}
@@ -0,0 +1,202 @@
+//! Storage of the history directory of a [`crate::Wrk`]: result files, suite status and audit
+//! log, injectable so the history logic can be tested without touching the disk.
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// File operations used on the history directory. Paths are the ones the history would have
+/// on the local filesystem.
+pub trait Storage: Debug + Send + Sync {
+    /// Files directly inside `dir`, empty when it does not exist.
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Content of the file at `path`.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Whether a file exists at `path`.
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Replace the file at `path` with `data`, creating its directory. Readers never see a
+    /// partially written file.
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()>;
+
+    /// Append `data` to the file at `path`, creating it and its directory.
+    fn append(&self, path: &Path, data: &[u8]) -> io::Result<()>;
+
+    /// Remove the file at `path`.
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    /// Whether files are stored on the local filesystem at their path. Otherwise the Lua
+    /// scripts wrk needs on disk are cached in the temporary directory.
+    fn is_local(&self) -> bool {
+        false
+    }
+}
+
+/// Storage on the local filesystem, used by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalStorage;
+
+impl Storage for LocalStorage {
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        fs::read_dir(dir)?.map(|entry| Ok(entry?.path())).collect()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(dir)?;
+        // Write to a temporary file in the same directory and rename it, so a crash never
+        // leaves a truncated file behind.
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut file = tempfile::Builder::new()
+            .prefix(&format!(".{}.", name))
+            .tempfile_in(dir)?;
+        file.write_all(data)?;
+        file.as_file().sync_all()?;
+        file.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        OpenOptions::new().create(true).append(true).open(path)?.write_all(data)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+}
+
+/// Storage in memory, lost when the last [`HistoryStore`] using it is dropped.
+#[derive(Debug, Default)]
+pub struct MemoryStorage(Mutex<BTreeMap<PathBuf, Vec<u8>>>);
+
+impl MemoryStorage {
+    fn files(&self) -> std::sync::MutexGuard<'_, BTreeMap<PathBuf, Vec<u8>>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path.display()))
+}
+
+impl Storage for MemoryStorage {
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(self
+            .files()
+            .keys()
+            .filter(|path| path.parent() == Some(dir))
+            .cloned()
+            .collect())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files().get(path).cloned().ok_or_else(|| not_found(path))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files().contains_key(path)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.files().insert(path.to_path_buf(), data.to_vec());
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.files()
+            .entry(path.to_path_buf())
+            .or_default()
+            .extend_from_slice(data);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.files().remove(path).map(|_| ()).ok_or_else(|| not_found(path))
+    }
+}
+
+/// Shared [`Storage`] of the history of a [`crate::Wrk`].
+#[derive(Debug, Clone)]
+pub struct HistoryStore(Arc<dyn Storage>);
+
+impl Default for HistoryStore {
+    fn default() -> Self {
+        Self::local()
+    }
+}
+
+impl HistoryStore {
+    /// History stored on the local filesystem.
+    pub fn local() -> Self {
+        Self::new(LocalStorage)
+    }
+
+    /// History kept in memory, so nothing is written to the history directory. Clones share
+    /// the same history.
+    pub fn in_memory() -> Self {
+        Self::new(MemoryStorage::default())
+    }
+
+    /// History stored by `storage`.
+    pub fn new<S: Storage + 'static>(storage: S) -> Self {
+        Self(Arc::new(storage))
+    }
+}
+
+impl Deref for HistoryStore {
+    type Target = dyn Storage;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_storage() {
+        let store = HistoryStore::in_memory();
+        let dir = Path::new("history");
+        assert!(store.list(dir).unwrap().is_empty());
+        store.write(&dir.join("b.json"), b"[]").unwrap();
+        store.append(&dir.join("a.log"), b"one\n").unwrap();
+        store.clone().append(&dir.join("a.log"), b"two\n").unwrap();
+        store.write(&dir.join("nested/c.json"), b"{}").unwrap();
+        assert_eq!(store.list(dir).unwrap(), vec![dir.join("a.log"), dir.join("b.json")]);
+        assert_eq!(store.read(&dir.join("a.log")).unwrap(), b"one\ntwo\n");
+        store.remove(&dir.join("b.json")).unwrap();
+        assert!(!store.exists(&dir.join("b.json")));
+        assert_eq!(
+            store.read(&dir.join("b.json")).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert!(!store.is_local());
+    }
+}
@@ -0,0 +1,113 @@
+//! Summary of a suite of benchmarks exploring thread and connection counts, IE: the one run
+//! by [`crate::BenchSession::bench_exponential`].
+use std::fmt;
+
+use crate::{Benchmark, Benchmarks, Metric, Rank, WrkResult};
+
+/// Results of a suite, recommending the configuration to use.
+#[derive(Debug, Clone)]
+pub struct SuiteSummary {
+    results: Benchmarks,
+    p99_ceiling_ms: Option<f64>,
+}
+
+impl SuiteSummary {
+    /// Summarize `results`, only recommending configurations with a p99 latency within
+    /// `p99_ceiling_ms` when set.
+    pub fn new(results: Benchmarks, p99_ceiling_ms: Option<f64>) -> Self {
+        Self {
+            results,
+            p99_ceiling_ms,
+        }
+    }
+
+    /// Results of the suite.
+    pub fn results(&self) -> &Benchmarks {
+        &self.results
+    }
+
+    /// Successful result with the best requests/sec within the latency ceiling.
+    fn recommended(&self) -> Option<&WrkResult> {
+        self.results
+            .iter()
+            .filter(|r| *r.success() && r.benchmark().is_some())
+            .filter(|r| self.p99_ceiling_ms.is_none_or(|ceiling| *r.p99_latency_ms() <= ceiling))
+            .max_by(|a, b| a.requests_sec().total_cmp(b.requests_sec()))
+    }
+
+    /// Thread and connection counts with the best throughput whose p99 latency stays within
+    /// the ceiling, or `None` when no successful run satisfies it.
+    pub fn recommended_config(&self) -> Option<&Benchmark> {
+        self.recommended().and_then(|r| r.benchmark().as_ref())
+    }
+
+    /// Sentence describing the recommendation.
+    fn recommendation(&self) -> String {
+        let ceiling = self
+            .p99_ceiling_ms
+            .map(|ceiling| format!(" within the {:.2}ms p99 ceiling", ceiling))
+            .unwrap_or_default();
+        match self.recommended() {
+            Some(result) => {
+                let benchmark = result
+                    .benchmark()
+                    .as_ref()
+                    .expect("recommended results have a benchmark");
+                format!(
+                    "Recommended configuration: {} threads, {} connections ({:.2} requests/sec, {:.2}ms p99{})",
+                    benchmark.threads(),
+                    benchmark.connections(),
+                    result.requests_sec(),
+                    result.p99_latency_ms(),
+                    ceiling
+                )
+            }
+            None => format!("No configuration to recommend{}", ceiling),
+        }
+    }
+
+    /// Render the summary as a GitHub flavoured markdown table followed by the recommendation.
+    pub fn to_github_markdown(&self) -> String {
+        format!(
+            "{}\\n**{}**\\n",
+            self.results.rank_by(Metric::RequestsSec).to_github_markdown(),
+            self.recommendation()
+        )
+    }
+}
+
+impl fmt::Display for SuiteSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.results.rank_by(Metric::RequestsSec))?;
+        writeln!(f, "{}", self.recommendation())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::result_builder;
+
+    #[test]
+    fn recommended_config() {
+        let result = |threads: u16, connections: u16, requests_sec: f64, p99: f64| {
+            result_builder(requests_sec, p99)
+                .benchmark(Benchmark::new(threads, connections, 1))
+                .build()
+                .unwrap()
+        };
+        let results = vec![
+            result(2, 32, 1000.0, 5.0),
+            result(4, 64, 1800.0, 12.0),
+            result(8, 256, 2000.0, 80.0),
+        ];
+        let summary = SuiteSummary::new(results.clone(), Some(20.0));
+        assert_eq!(summary.recommended_config(), Some(&Benchmark::new(4, 64, 1)));
+        assert!(summary.to_string().contains("4 threads, 64 connections"));
+        assert_eq!(
+            SuiteSummary::new(results.clone(), None).recommended_config(),
+            Some(&Benchmark::new(8, 256, 1))
+        );
+        assert_eq!(SuiteSummary::new(results, Some(1.0)).recommended_config(), None);
+    }
+}
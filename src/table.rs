@@ -0,0 +1,105 @@
+//! Tables of the terminal reports, rendered by prettytable with the `report-table` feature and
+//! by a minimal plain text renderer otherwise.
+#[cfg(feature = "report-table")]
+pub(crate) use prettytable::{format, Attr, Cell, Row, Table};
+
+#[cfg(not(feature = "report-table"))]
+pub(crate) use plain::{format, Attr, Cell, Row, Table};
+
+#[cfg(not(feature = "report-table"))]
+mod plain {
+    use std::fmt;
+
+    pub(crate) mod format {
+        /// Table format, ignored by the plain renderer.
+        #[derive(Debug, Clone, Copy)]
+        pub(crate) struct TableFormat;
+
+        pub(crate) mod consts {
+            use super::TableFormat;
+
+            pub(crate) static FORMAT_CLEAN: &TableFormat = &TableFormat;
+            pub(crate) static FORMAT_BOX_CHARS: &TableFormat = &TableFormat;
+        }
+    }
+
+    /// Cell style, ignored by the plain renderer.
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) enum Attr {
+        Bold,
+    }
+
+    #[derive(Debug, Clone)]
+    pub(crate) struct Cell(String);
+
+    impl Cell {
+        pub(crate) fn new(content: &str) -> Self {
+            Self(content.to_string())
+        }
+
+        pub(crate) fn with_style(self, _attr: Attr) -> Self {
+            self
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub(crate) struct Row(Vec<Cell>);
+
+    impl Row {
+        pub(crate) fn new(cells: Vec<Cell>) -> Self {
+            Self(cells)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    pub(crate) struct Table {
+        titles: Option<Row>,
+        rows: Vec<Row>,
+    }
+
+    impl Table {
+        pub(crate) fn new() -> Self {
+            Self::default()
+        }
+
+        pub(crate) fn set_format(&mut self, _format: format::TableFormat) {}
+
+        pub(crate) fn set_titles(&mut self, titles: Row) {
+            self.titles = Some(titles);
+        }
+
+        pub(crate) fn add_row(&mut self, row: Row) {
+            self.rows.push(row);
+        }
+    }
+
+    impl fmt::Display for Table {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let rows: Vec<&Row> = self.titles.iter().chain(&self.rows).collect();
+            let mut widths: Vec<usize> = Vec::new();
+            for row in &rows {
+                for (i, cell) in row.0.iter().enumerate() {
+                    let width = cell.0.chars().count();
+                    match widths.get_mut(i) {
+                        Some(w) => *w = (*w).max(width),
+                        None => widths.push(width),
+                    }
+                }
+            }
+            for (i, row) in rows.iter().enumerate() {
+                let line: Vec<String> = row
+                    .0
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{:width$}", cell.0, width = width))
+                    .collect();
+                writeln!(f, "{}", line.join("  ").trim_end())?;
+                if i == 0 && self.titles.is_some() {
+                    let total = widths.iter().sum::<usize>() + 2 * widths.len().saturating_sub(1);
+                    writeln!(f, "{}", "-".repeat(total))?;
+                }
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Services started on a free port by [`crate::Wrk::with_dynamic_target`].
+use std::{net::TcpListener, ops::Deref, process::Child, sync::Arc};
+
+use url::Url;
+
+use crate::{harness, Result, Wrk, WrkError};
+
+/// Placeholder substituted with the selected port in the [`Wrk`] URL.
+pub(crate) const PORT_PLACEHOLDER: &str = "{port}";
+
+/// Service which can be stopped once the benchmarks are done.
+pub trait Teardown {
+    /// Stop the service.
+    fn teardown(&mut self);
+}
+
+impl Teardown for () {
+    fn teardown(&mut self) {}
+}
+
+impl Teardown for Child {
+    fn teardown(&mut self) {
+        if let Err(e) = self.kill().and_then(|_| self.wait().map(|_| ())) {
+            warn!("Unable to stop service process {}: {}", self.id(), e);
+        }
+    }
+}
+
+impl<T> Teardown for tokio::task::JoinHandle<T> {
+    fn teardown(&mut self) {
+        self.abort();
+    }
+}
+
+#[cfg(feature = "testing")]
+impl Teardown for crate::testing::TestTarget {
+    fn teardown(&mut self) {
+        // Dropping the target shuts the server down.
+    }
+}
+
+/// Service listening on a port selected by the crate, torn down when dropped. Dereferences to
+/// the [`Wrk`] configuration pointing to it.
+#[derive(Debug)]
+pub struct DynamicTarget<T: Teardown> {
+    wrk: Arc<Wrk>,
+    port: u16,
+    service: T,
+}
+
+impl<T: Teardown> DynamicTarget<T> {
+    pub(crate) fn spawn<F: FnOnce(TcpListener) -> T>(wrk: &Wrk, spawn: F) -> Result<Self> {
+        if !wrk.url().contains(PORT_PLACEHOLDER) {
+            return Err(WrkError::Exec(format!(
+                "Url {} has no {} placeholder to substitute",
+                wrk.url(),
+                PORT_PLACEHOLDER
+            )));
+        }
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let port = listener.local_addr()?.port();
+        let mut target = wrk.clone();
+        // Keep the history of every run in the same namespace, whatever the selected port.
+        if target.project().is_none() {
+            target.set_project(Some(wrk.history_namespace()));
+        }
+        target.set_url(wrk.url().replace(PORT_PLACEHOLDER, &port.to_string()));
+        let url = Url::parse(target.url())?;
+        let mut target = Self {
+            wrk: Arc::new(target),
+            port,
+            service: spawn(listener),
+        };
+        if let Err(e) = harness::wait_ready(&url, harness::READINESS_TIMEOUT) {
+            target.service.teardown();
+            return Err(e);
+        }
+        Ok(target)
+    }
+
+    /// Port selected for the service.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Shared configuration, to be used by [`crate::BenchSession::new`].
+    pub fn wrk(&self) -> Arc<Wrk> {
+        self.wrk.clone()
+    }
+}
+
+impl<T: Teardown> Deref for DynamicTarget<T> {
+    type Target = Wrk;
+
+    fn deref(&self) -> &Wrk {
+        &self.wrk
+    }
+}
+
+impl<T: Teardown> Drop for DynamicTarget<T> {
+    fn drop(&mut self) {
+        self.service.teardown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::WrkBuilder;
+
+    struct Listener(Option<TcpListener>);
+
+    impl Teardown for Listener {
+        fn teardown(&mut self) {
+            self.0.take();
+        }
+    }
+
+    #[test]
+    fn substitute_port() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:{port}/api".to_string())
+            .build()
+            .unwrap();
+        let target = wrk.with_dynamic_target(|listener| Listener(Some(listener))).unwrap();
+        assert_eq!(target.url(), &format!("http://127.0.0.1:{}/api", target.port()));
+        assert_eq!(target.history_path(), wrk.history_path());
+        let port = target.port();
+        drop(target);
+        assert!(TcpListener::bind(("127.0.0.1", port)).is_ok());
+    }
+}
@@ -0,0 +1,27 @@
+//! Fixtures shared by the unit tests.
+use crate::{Benchmark, CommandSpec, ProcessOutput, ProcessRunner, Result, WrkResultBuilder};
+
+/// Output of a real run of wrk 4.1.0 with the Lua script of this crate.
+pub(crate) const CORPUS: &str = "tests/corpus/wrk-4.1.0.txt";
+
+/// Runner answering every command with the output of wrk 4.1.0.
+#[derive(Debug)]
+pub(crate) struct CorpusRunner;
+
+impl ProcessRunner for CorpusRunner {
+    fn run(&self, _spec: &CommandSpec) -> Result<ProcessOutput> {
+        let stdout = std::fs::read_to_string(CORPUS)?;
+        Ok(ProcessOutput::new(Some(0), stdout, String::new()))
+    }
+}
+
+/// Builder of a successful result of a one thread, one connection and one second benchmark.
+pub(crate) fn result_builder(requests_sec: f64, p99_latency_ms: f64) -> WrkResultBuilder {
+    let mut builder = WrkResultBuilder::default();
+    builder
+        .success(true)
+        .requests_sec(requests_sec)
+        .p99_latency_ms(p99_latency_ms)
+        .benchmark(Benchmark::new(1, 1, 1));
+    builder
+}
@@ -0,0 +1,77 @@
+//! Helpers to benchmark axum routers from tests without booting servers by hand.
+use std::net::{SocketAddr, TcpListener};
+
+use axum::Router;
+use tokio::{sync::oneshot, task::JoinHandle};
+
+use crate::{Result, WrkError};
+
+/// Router served on a free local port by [`spawn_test_server`].
+#[derive(Debug)]
+pub struct TestTarget {
+    url: String,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: JoinHandle<std::result::Result<(), hyper::Error>>,
+}
+
+impl TestTarget {
+    /// Base URL of the server, IE: `http://127.0.0.1:34567/`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Gracefully stop the server, waiting for in-flight requests to complete.
+    pub async fn shutdown(mut self) -> Result<()> {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        (&mut self.server)
+            .await
+            .map_err(|e| WrkError::Exec(format!("Test server task failed: {}", e)))?
+            .map_err(|e| WrkError::Exec(format!("Test server failed: {}", e)))
+    }
+}
+
+impl Drop for TestTarget {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// Serve `router` on a free port of the loopback interface. The listener is bound before this
+/// function returns, so the target accepts connections right away. Must be called within a
+/// tokio runtime.
+pub fn spawn_test_server(router: Router) -> Result<TestTarget> {
+    let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))?;
+    let addr = listener.local_addr()?;
+    let (shutdown, signal) = oneshot::channel();
+    let server = axum::Server::from_tcp(listener)
+        .map_err(|e| WrkError::Exec(format!("Unable to start test server: {}", e)))?
+        .serve(router.into_make_service())
+        .with_graceful_shutdown(async {
+            let _ = signal.await;
+        });
+    Ok(TestTarget {
+        url: format!("http://{}/", addr),
+        shutdown: Some(shutdown),
+        server: tokio::spawn(server),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn serve_and_shutdown() {
+        let target = spawn_test_server(Router::new().route("/", get(|| async { "Hello, world!" }))).unwrap();
+        let response = hyper::Client::new().get(target.url().parse().unwrap()).await.unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"Hello, world!");
+        target.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+//! Per-metric performance policies, usually loaded from a `thresholds.toml` file living next
+//! to the benchmarked code:
+//!
+//! ```toml
+//! [requests_sec]
+//! max_regression = 5.0
+//!
+//! [p99_latency]
+//! max_regression = 10.0
+//! ceiling = 50.0
+//! ```
+use std::collections::BTreeMap;
+#[cfg(feature = "thresholds-file")]
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Metric, Result, Variance, WrkError};
+
+/// Rules checked on a single [`Metric`]. Percentages are relative to the old result and
+/// account for the direction of the metric, so a regression is always positive.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricRule {
+    /// Maximum percentage the metric may get worse by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_regression: Option<f64>,
+    /// Minimum percentage the metric must improve by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_improvement: Option<f64>,
+    /// Maximum absolute value of the metric.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ceiling: Option<f64>,
+    /// Minimum absolute value of the metric.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub floor: Option<f64>,
+}
+
+/// Rules of every checked [`Metric`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Thresholds {
+    /// Rules by metric.
+    pub rules: BTreeMap<Metric, MetricRule>,
+}
+
+impl Thresholds {
+    /// Load the thresholds from a TOML file with a table per metric.
+    #[cfg(feature = "thresholds-file")]
+    pub fn from_file(path: &Path) -> Result<Self> {
+        toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| WrkError::Config(format!("Invalid thresholds file {}: {}", path.display(), e)))
+    }
+
+    /// Thresholds allowing requests/sec and p99 latency to regress by `max_percent` at most.
+    pub fn max_regression(max_percent: f64) -> Self {
+        let rule = MetricRule {
+            max_regression: Some(max_percent),
+            ..Default::default()
+        };
+        Self {
+            rules: BTreeMap::from([(Metric::RequestsSec, rule.clone()), (Metric::P99Latency, rule)]),
+        }
+    }
+
+    /// Rules broken by `variance`. Percentage rules are skipped when the old value is zero.
+    pub fn violations(&self, variance: &Variance) -> Vec<String> {
+        let mut violations = Vec::new();
+        for (metric, rule) in &self.rules {
+            let (new, old) = (variance.new.get(*metric), variance.old.get(*metric));
+            if old != 0.0 {
+                let change = Variance::calculate(&new, &old);
+                let regression = if metric.higher_is_better() { -change } else { change };
+                if let Some(max) = rule.max_regression.filter(|max| regression > *max) {
+                    violations.push(format!(
+                        "{} regressed by {:.2}%, more than the allowed {}%",
+                        metric, regression, max
+                    ));
+                }
+                if let Some(min) = rule.min_improvement.filter(|min| -regression < *min) {
+                    violations.push(format!(
+                        "{} improved by {:.2}%, less than the required {}%",
+                        metric, -regression, min
+                    ));
+                }
+            }
+            if let Some(ceiling) = rule.ceiling.filter(|ceiling| new > *ceiling) {
+                violations.push(format!("{} is {:.2}, above the ceiling of {}", metric, new, ceiling));
+            }
+            if let Some(floor) = rule.floor.filter(|floor| new < *floor) {
+                violations.push(format!("{} is {:.2}, below the floor of {}", metric, new, floor));
+            }
+        }
+        violations
+    }
+
+    /// Fail with [`WrkError::Regression`] listing the rules broken by `variance`.
+    pub fn check(&self, variance: &Variance) -> Result<()> {
+        let violations = self.violations(variance);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(WrkError::Regression(violations.join(", ")))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "thresholds-file"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::result_builder;
+
+    #[test]
+    fn thresholds_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("thresholds.toml");
+        fs::write(
+            &path,
+            "[requests_sec]\nmax_regression = 5.0\nfloor = 50.0\n\n[p99_latency]\nmin_improvement = 1.0\nceiling = 12.0\n",
+        )
+        .unwrap();
+        let thresholds = Thresholds::from_file(&path).unwrap();
+        assert_eq!(thresholds.rules[&Metric::RequestsSec].max_regression, Some(5.0));
+
+        let result =
+            |requests_sec: f64, p99_latency_ms: f64| result_builder(requests_sec, p99_latency_ms).build().unwrap();
+        assert!(thresholds
+            .check(&Variance::new(result(97.0, 9.0), result(100.0, 10.0)))
+            .is_ok());
+        let violations = thresholds.violations(&Variance::new(result(40.0, 13.0), result(100.0, 10.0)));
+        assert_eq!(violations.len(), 4, "{:?}", violations);
+        assert_eq!(
+            violations[0],
+            "Requests/sec regressed by 60.00%, more than the allowed 5%"
+        );
+
+        fs::write(&path, "[requests_sec]\nmax_regresion = 5.0\n").unwrap();
+        assert!(matches!(Thresholds::from_file(&path), Err(WrkError::Config(_))));
+    }
+}
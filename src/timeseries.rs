@@ -0,0 +1,186 @@
+//! Per-second request counts, recorded by the Lua script in a sidecar file to reveal warmups
+//! or periodic stalls hidden by the aggregated result.
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+
+use crate::{Result, WrkError};
+
+/// Environment variable holding the path of the sidecar file written by the Lua script.
+pub(crate) const ENV: &str = "WRK_API_BENCH_TIMESERIES";
+
+/// Number of responses received during one second of a run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, Getters)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Sample {
+    /// Seconds elapsed since the first response.
+    #[getset(get = "pub")]
+    second: u64,
+    /// Responses received during this second.
+    #[getset(get = "pub")]
+    requests: u64,
+}
+
+impl Sample {
+    /// Create a sample of `requests` received `second` seconds into the run.
+    pub fn new(second: u64, requests: u64) -> Self {
+        Self { second, requests }
+    }
+}
+
+/// Parse the `<unix second> <requests>` lines written by the Lua script, filling the seconds
+/// without responses with zero.
+pub(crate) fn read(path: &Path) -> Result<Vec<Sample>> {
+    let mut counts = BTreeMap::new();
+    for line in fs::read_to_string(path)?.lines().filter(|l| !l.trim().is_empty()) {
+        let parse = || -> Option<(u64, u64)> {
+            let (second, requests) = line.trim().split_once(' ')?;
+            Some((second.parse().ok()?, requests.parse().ok()?))
+        };
+        let (second, requests) = parse().ok_or_else(|| WrkError::Lua(format!("Invalid timeseries line {:?}", line)))?;
+        *counts.entry(second).or_insert(0) += requests;
+    }
+    let (Some(first), Some(last)) = (counts.keys().next(), counts.keys().next_back()) else {
+        return Ok(Vec::new());
+    };
+    Ok((*first..=*last)
+        .map(|second| Sample::new(second - first, counts.get(&second).copied().unwrap_or(0)))
+        .collect())
+}
+
+/// Throughput dip within a run, usually pointing to GC pauses or connection resets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Getters)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Stall {
+    /// Seconds elapsed since the first response when the dip started.
+    #[getset(get = "pub")]
+    start: u64,
+    /// Length of the dip in seconds.
+    #[getset(get = "pub")]
+    duration: u64,
+    /// Lowest number of responses per second during the dip.
+    #[getset(get = "pub")]
+    min_requests: u64,
+    /// Median responses per second of the whole run.
+    #[getset(get = "pub")]
+    median_requests: f64,
+}
+
+impl fmt::Display for Stall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "throughput dropped to {} requests/sec (median {:.0}) for {}s from second {}",
+            self.min_requests, self.median_requests, self.duration, self.start
+        )
+    }
+}
+
+/// Detection of throughput dips below `threshold_percent` of the median requests per second,
+/// lasting at least `min_seconds`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Getters)]
+pub struct StallDetection {
+    /// Percentage of the median below which a second is part of a dip.
+    #[getset(get = "pub")]
+    threshold_percent: f64,
+    /// Minimum length of a dip in seconds.
+    #[getset(get = "pub")]
+    min_seconds: u64,
+}
+
+impl Default for StallDetection {
+    fn default() -> Self {
+        Self::new(50.0, 2)
+    }
+}
+
+impl StallDetection {
+    /// Flag dips below `threshold_percent` of the median lasting at least `min_seconds`.
+    pub fn new(threshold_percent: f64, min_seconds: u64) -> Self {
+        Self {
+            threshold_percent,
+            min_seconds: min_seconds.max(1),
+        }
+    }
+
+    /// Stalls found in `samples`. The first and last seconds are ignored, as they are usually
+    /// only partially covered by the run.
+    pub fn detect(&self, samples: &[Sample]) -> Vec<Stall> {
+        if samples.len() < 3 {
+            return Vec::new();
+        }
+        let samples = &samples[1..samples.len() - 1];
+        let mut sorted = samples.iter().map(|s| s.requests).collect::<Vec<_>>();
+        sorted.sort_unstable();
+        let middle = sorted.len() / 2;
+        let median = if sorted.len() % 2 == 0 {
+            (sorted[middle - 1] + sorted[middle]) as f64 / 2.0
+        } else {
+            sorted[middle] as f64
+        };
+        let threshold = median * self.threshold_percent / 100.0;
+        let mut stalls = Vec::new();
+        let mut current: Option<Stall> = None;
+        for sample in samples {
+            if (sample.requests as f64) < threshold {
+                let stall = current.get_or_insert(Stall {
+                    start: sample.second,
+                    duration: 0,
+                    min_requests: sample.requests,
+                    median_requests: median,
+                });
+                stall.duration += 1;
+                stall.min_requests = stall.min_requests.min(sample.requests);
+            } else if let Some(stall) = current.take() {
+                stalls.push(stall);
+            }
+        }
+        stalls.extend(current);
+        stalls.retain(|s| s.duration >= self.min_seconds);
+        stalls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_sidecar() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), "1700000002 40\n1700000000 100\n1700000003 90\n").unwrap();
+        assert_eq!(
+            read(file.path()).unwrap(),
+            vec![
+                Sample::new(0, 100),
+                Sample::new(1, 0),
+                Sample::new(2, 40),
+                Sample::new(3, 90)
+            ]
+        );
+        fs::write(file.path(), "garbage\n").unwrap();
+        assert!(read(file.path()).is_err());
+    }
+
+    #[test]
+    fn detect_stalls() {
+        let requests = [10, 100, 100, 20, 30, 100, 40, 100, 100, 5];
+        let samples = requests
+            .iter()
+            .enumerate()
+            .map(|(i, r)| Sample::new(i as u64, *r))
+            .collect::<Vec<_>>();
+        let stalls = StallDetection::new(50.0, 2).detect(&samples);
+        assert_eq!(
+            stalls,
+            vec![Stall {
+                start: 3,
+                duration: 2,
+                min_requests: 20,
+                median_requests: 100.0
+            }]
+        );
+        assert_eq!(StallDetection::new(50.0, 1).detect(&samples).len(), 2);
+    }
+}
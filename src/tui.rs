@@ -0,0 +1,227 @@
+//! Interactive terminal dashboard showing the live progress of a benchmark suite.
+use std::io::{self, Stdout};
+
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{
+        event::{self, Event, KeyEventKind},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    symbols::Marker,
+    widgets::{Axis, Block, Chart, Dataset, Gauge, GraphType, Paragraph},
+    Frame, Terminal,
+};
+
+use crate::{BenchSession, Benchmark, Benchmarks, HistoryPeriod, Result, Variance, Wrk, WrkResult};
+
+/// Progress of the suite rendered by the dashboard.
+#[derive(Debug)]
+struct State {
+    total: usize,
+    history: Benchmarks,
+    results: Benchmarks,
+    verdict: Option<(String, bool)>,
+}
+
+impl State {
+    /// Compare `result` with the best comparable historical run.
+    fn update(&mut self, wrk: &Wrk, result: &WrkResult) {
+        self.results.push(result.clone());
+        let comparable: Benchmarks = match result.benchmark() {
+            Some(benchmark) => self
+                .history
+                .iter()
+                .filter(|r| r.benchmark().as_ref().is_some_and(|b| b.is_comparable(benchmark)))
+                .cloned()
+                .collect(),
+            None => Benchmarks::new(),
+        };
+        let name = result.benchmark().as_ref().map_or_else(String::new, |b| b.name());
+        self.verdict = match wrk.best_benchmark(&comparable) {
+            Ok(old) => {
+                let variance = Variance::new(result.clone(), old);
+                let requests_sec = *variance.variance.requests_sec();
+                Some((
+                    format!(
+                        "{}: requests/sec {:+.2}%, p99 latency {:+.2}%",
+                        name,
+                        requests_sec,
+                        variance.variance.p99_latency_ms()
+                    ),
+                    requests_sec >= 0.0,
+                ))
+            }
+            Err(_) => Some((format!("{}: no comparable historical run", name), true)),
+        };
+    }
+}
+
+fn draw(frame: &mut Frame, state: &State) {
+    let [progress, chart, verdict] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(8), Constraint::Length(3)]).areas(frame.area());
+
+    let done = state.results.len();
+    let ratio = if state.total == 0 {
+        1.0
+    } else {
+        done as f64 / state.total as f64
+    };
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::bordered().title("Progress"))
+            .gauge_style(Style::default().fg(Color::Cyan))
+            .ratio(ratio.min(1.0))
+            .label(format!("{}/{} benchmarks", done, state.total)),
+        progress,
+    );
+
+    let data: Vec<(f64, f64)> = state
+        .results
+        .iter()
+        .enumerate()
+        .map(|(i, r)| ((i + 1) as f64, *r.requests_sec()))
+        .collect();
+    let max_y = data.iter().map(|(_, y)| *y).fold(0.0, f64::max).max(1.0) * 1.15;
+    let max_x = state.total.max(1) as f64;
+    frame.render_widget(
+        Chart::new(vec![Dataset::default()
+            .name("requests/sec")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Yellow))
+            .data(&data)])
+        .block(Block::bordered().title("Requests/sec per run"))
+        .x_axis(
+            Axis::default()
+                .title("run")
+                .bounds([0.0, max_x])
+                .labels(["0".to_string(), format!("{}", max_x)]),
+        )
+        .y_axis(
+            Axis::default()
+                .bounds([0.0, max_y])
+                .labels(["0".to_string(), format!("{:.0}", max_y)]),
+        ),
+        chart,
+    );
+
+    let (text, color) = match &state.verdict {
+        Some((text, true)) => (text.as_str(), Color::Green),
+        Some((text, false)) => (text.as_str(), Color::Red),
+        None => ("Waiting for the first result", Color::Gray),
+    };
+    frame.render_widget(
+        Paragraph::new(text)
+            .style(Style::default().fg(color))
+            .block(Block::bordered().title("Variance")),
+        verdict,
+    );
+}
+
+fn restore(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()
+}
+
+/// History recorded in `period`, empty when no run was recorded yet.
+fn load_history(wrk: &Wrk, period: &HistoryPeriod) -> Result<Benchmarks> {
+    if wrk.history_files(None)?.is_empty() {
+        return Ok(Benchmarks::new());
+    }
+    wrk.history(period, None)
+}
+
+/// Run `benchmarks` in `session` showing a live dashboard with the progress of the suite, a
+/// chart of requests/sec per completed run and the variance against the best comparable run
+/// recorded in `period`. The dashboard is closed by pressing any key once the suite is done.
+pub fn run(session: &mut BenchSession, benchmarks: &[Benchmark], period: HistoryPeriod) -> Result<()> {
+    let mut state = State {
+        total: benchmarks.len(),
+        history: load_history(session.wrk(), &period)?,
+        results: Benchmarks::new(),
+        verdict: None,
+    };
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    let result = (|| -> Result<()> {
+        terminal.draw(|frame| draw(frame, &state))?;
+        let wrk = session.wrk().clone();
+        session.bench_with(benchmarks, |result| {
+            state.update(&wrk, result);
+            if let Err(e) = terminal.draw(|frame| draw(frame, &state)) {
+                error!("Unable to draw the dashboard: {}", e);
+            }
+        })?;
+        loop {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    return Ok(());
+                }
+            }
+        }
+    })();
+    restore(&mut terminal)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+    use crate::{WrkBuilder, WrkResultBuilder};
+
+    #[test]
+    fn render() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13737".to_string())
+            .build()
+            .unwrap();
+        let mut state = State {
+            total: 2,
+            history: Benchmarks::new(),
+            results: Benchmarks::new(),
+            verdict: None,
+        };
+        let result = WrkResultBuilder::default()
+            .requests_sec(100.0)
+            .benchmark(Benchmark::new(1, 1, 1))
+            .build()
+            .unwrap();
+        state.update(&wrk, &result);
+        let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+        terminal.draw(|frame| draw(frame, &state)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(screen.contains("1/2 benchmarks"));
+        assert!(screen.contains("no comparable historical run"));
+    }
+
+    #[test]
+    fn history_errors() {
+        let store = crate::HistoryStore::in_memory();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13737".to_string())
+            .history_store(store.clone())
+            .build()
+            .unwrap();
+        assert!(load_history(&wrk, &HistoryPeriod::Last).unwrap().is_empty());
+        let path = wrk.history_path().join(crate::history::filename(
+            &chrono::Utc::now(),
+            crate::HistoryFormat::Json,
+        ));
+        store.write(&path, b"not json").unwrap();
+        assert!(load_history(&wrk, &HistoryPeriod::Last).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! Polling of watched files used by [`crate::Wrk::watch`].
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::WrkResult;
+
+/// Interval between two checks of the watched files.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Most recent modification time of `path`, recursing into directories.
+fn modified(path: &Path) -> Option<SystemTime> {
+    let metadata = fs::metadata(path).ok()?;
+    let mut latest = metadata.modified().ok();
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path).ok()?.flatten() {
+            latest = latest.max(modified(&entry.path()));
+        }
+    }
+    latest
+}
+
+/// Most recent modification time of all `paths`.
+pub(crate) fn latest_modification(paths: &[PathBuf]) -> Option<SystemTime> {
+    paths.iter().filter_map(|path| modified(path)).max()
+}
+
+/// Block until the watched `paths` change after `seen` and then settle for a whole poll
+/// interval, so half written files (IE: a binary being linked) are not benchmarked.
+pub(crate) fn wait_for_change(paths: &[PathBuf], seen: Option<SystemTime>) -> Option<SystemTime> {
+    let mut current = seen;
+    while current == seen {
+        thread::sleep(POLL_INTERVAL);
+        current = latest_modification(paths);
+    }
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let settled = latest_modification(paths);
+        if settled == current {
+            return settled;
+        }
+        current = settled;
+    }
+}
+
+/// Pair every successful result of `current` with the result of the comparable configuration
+/// in `previous`, if any, and replace it in `previous` so the next iteration compares with it.
+/// Configurations failing in this iteration keep their last successful result.
+pub(crate) fn pair_comparable(
+    previous: &mut Vec<WrkResult>,
+    current: &[WrkResult],
+) -> Vec<(WrkResult, Option<WrkResult>)> {
+    let mut pairs = Vec::new();
+    for result in current.iter().filter(|r| *r.success()) {
+        let Some(benchmark) = result.benchmark() else {
+            continue;
+        };
+        let position = previous
+            .iter()
+            .position(|r| r.benchmark().as_ref().is_some_and(|b| b.is_comparable(benchmark)));
+        let old = match position {
+            Some(index) => Some(std::mem::replace(&mut previous[index], result.clone())),
+            None => {
+                previous.push(result.clone());
+                None
+            }
+        };
+        pairs.push((result.clone(), old));
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+    use crate::{test_utils::result_builder, Benchmark};
+
+    #[test]
+    fn latest_modification_recurses() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("nested");
+        fs::create_dir(&nested).unwrap();
+        let file = File::create(nested.join("binary")).unwrap();
+        let future = SystemTime::now() + Duration::from_secs(3600);
+        file.set_modified(future).unwrap();
+        assert_eq!(latest_modification(&[dir.path().to_path_buf()]), Some(future));
+        assert_eq!(latest_modification(&[dir.path().join("missing")]), None);
+    }
+
+    #[test]
+    fn pairs_comparable_configurations() {
+        let result = |connections: u16, requests_sec: f64, success: bool| {
+            result_builder(requests_sec, 1.0)
+                .success(success)
+                .benchmark(Benchmark::new(1, connections, 1))
+                .build()
+                .unwrap()
+        };
+        let mut previous = Vec::new();
+        let first = pair_comparable(&mut previous, &[result(1, 100.0, true), result(2, 200.0, true)]);
+        assert!(first.iter().all(|(_, old)| old.is_none()));
+
+        // The order of the configurations changed and the second one failed.
+        let second = pair_comparable(&mut previous, &[result(2, 210.0, false), result(1, 110.0, true)]);
+        assert_eq!(second.len(), 1);
+        assert_eq!(*second[0].0.requests_sec(), 110.0);
+        assert_eq!(second[0].1.as_ref().map(|r| *r.requests_sec()), Some(100.0));
+
+        let third = pair_comparable(&mut previous, &[result(2, 220.0, true)]);
+        assert_eq!(third[0].1.as_ref().map(|r| *r.requests_sec()), Some(200.0));
+        assert_eq!(previous.len(), 2);
+    }
+}
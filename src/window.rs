@@ -0,0 +1,169 @@
+//! Comparison of a run against each of the previous N runs and their mean, so a single noisy
+//! historical best does not dominate the verdict.
+use std::fmt;
+
+use crate::table::{format, Attr, Cell, Row, Table};
+
+use crate::{LatencyUnit, Metric, NumberFormat, Variance, WrkResult, WrkResultBuilder};
+
+/// Characters of the trend sparkline, from the lowest to the highest value.
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// New run compared with the previous runs returned by [`crate::Wrk::variance_window`].
+#[derive(Debug, Clone)]
+pub struct VarianceWindow {
+    new: WrkResult,
+    runs: Vec<WrkResult>,
+    latency_unit: LatencyUnit,
+    number_format: NumberFormat,
+}
+
+impl VarianceWindow {
+    pub(crate) fn new(new: WrkResult, runs: Vec<WrkResult>) -> Self {
+        Self {
+            new,
+            runs,
+            latency_unit: LatencyUnit::default(),
+            number_format: NumberFormat::default(),
+        }
+    }
+
+    /// Display latencies using the given unit.
+    pub fn with_latency_unit(mut self, latency_unit: LatencyUnit) -> Self {
+        self.latency_unit = latency_unit;
+        self
+    }
+
+    /// Display numbers using the given separators and precision.
+    pub fn with_number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
+    }
+
+    /// Best result of the new run.
+    pub fn new_run(&self) -> &WrkResult {
+        &self.new
+    }
+
+    /// Best comparable result of every previous run, oldest first.
+    pub fn runs(&self) -> &[WrkResult] {
+        &self.runs
+    }
+
+    /// Variance of the new run against every previous run, oldest first.
+    pub fn variances(&self) -> Vec<Variance> {
+        self.runs
+            .iter()
+            .map(|old| Variance::new(self.new.clone(), old.clone()))
+            .collect()
+    }
+
+    /// Mean of the previous runs.
+    pub fn mean(&self) -> WrkResult {
+        let mean = |measurement: fn(&WrkResult) -> f64| {
+            self.runs.iter().map(measurement).sum::<f64>() / self.runs.len().max(1) as f64
+        };
+        WrkResultBuilder::default()
+            .success(true)
+            .benchmark(self.new.benchmark().clone().unwrap_or_default())
+            .requests(mean(|r| *r.requests()))
+            .errors(mean(|r| *r.errors()))
+            .successes(mean(|r| *r.successes()))
+            .requests_sec(mean(|r| *r.requests_sec()))
+            .avg_latency_ms(mean(|r| *r.avg_latency_ms()))
+            .min_latency_ms(mean(|r| *r.min_latency_ms()))
+            .max_latency_ms(mean(|r| *r.max_latency_ms()))
+            .stdev_latency_ms(mean(|r| *r.stdev_latency_ms()))
+            .p50_latency_ms(mean(|r| *r.p50_latency_ms()))
+            .p75_latency_ms(mean(|r| *r.p75_latency_ms()))
+            .p90_latency_ms(mean(|r| *r.p90_latency_ms()))
+            .p99_latency_ms(mean(|r| *r.p99_latency_ms()))
+            .transfer_mb(mean(|r| *r.transfer_mb()))
+            .errors_connect(mean(|r| *r.errors_connect()))
+            .errors_read(mean(|r| *r.errors_read()))
+            .errors_write(mean(|r| *r.errors_write()))
+            .errors_status(mean(|r| *r.errors_status()))
+            .errors_timeout(mean(|r| *r.errors_timeout()))
+            .errors_mismatch(mean(|r| *r.errors_mismatch()))
+            .build()
+            .expect("every field has a default")
+    }
+
+    /// Variance of the new run against the mean of the previous runs.
+    pub fn variance_to_mean(&self) -> Variance {
+        Variance::new(self.new.clone(), self.mean())
+    }
+}
+
+/// Sparkline of `values`, scaled between their minimum and maximum.
+fn sparkline(values: &[f64]) -> String {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            if max > min {
+                SPARKS[((value - min) / (max - min) * (SPARKS.len() - 1) as f64).round() as usize]
+            } else {
+                SPARKS[SPARKS.len() / 2]
+            }
+        })
+        .collect()
+}
+
+impl fmt::Display for VarianceWindow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_CLEAN);
+        let mut titles = vec!["Measurement".to_string(), "Current".to_string(), "Mean".to_string()];
+        titles.extend(self.runs.iter().map(|r| r.date().format("%Y-%m-%d %H:%M").to_string()));
+        titles.push("Trend".to_string());
+        table.add_row(Row::new(
+            titles.iter().map(|t| Cell::new(t).with_style(Attr::Bold)).collect(),
+        ));
+        let mean = self.mean();
+        let display = Variance {
+            latency_unit: self.latency_unit,
+            number_format: self.number_format,
+            ..Default::default()
+        };
+        for metric in Metric::iter() {
+            let new = self.new.get(metric);
+            let with_variance = |old: &WrkResult| {
+                format!(
+                    "{} ({})",
+                    display.value(metric, old),
+                    self.number_format
+                        .signed_percent(Variance::calculate(&new, &old.get(metric)))
+                )
+            };
+            let mut cells = vec![
+                Cell::new(&display.label(metric)).with_style(Attr::Bold),
+                Cell::new(&display.value(metric, &self.new)),
+                Cell::new(&with_variance(&mean)),
+            ];
+            cells.extend(self.runs.iter().map(|r| Cell::new(&with_variance(r))));
+            let mut trend: Vec<f64> = self.runs.iter().map(|r| r.get(metric)).collect();
+            trend.push(new);
+            cells.push(Cell::new(&sparkline(&trend)));
+            table.add_row(Row::new(cells));
+        }
+        writeln!(
+            f,
+            "## Rust Wrk benchmark report against the last {} runs:",
+            self.runs.len()
+        )?;
+        write!(f, "{}", table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sparkline_scales() {
+        assert_eq!(sparkline(&[1.0, 5.0, 3.0]), "▁█▅");
+        assert_eq!(sparkline(&[2.0, 2.0]), "▅▅");
+    }
+}
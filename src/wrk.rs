@@ -1,81 +1,190 @@
 use std::{
-    collections::HashMap,
-    fs::{self, File},
-    io::{BufReader, BufWriter},
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs,
+    io::Write,
+    net::TcpListener,
     ops::Sub,
     path::{Path, PathBuf},
-    process::Command,
+    sync::{Arc, OnceLock},
+    thread,
     time::Duration,
 };
 
-use chrono::{DateTime, Duration as ChronoDuration, NaiveDateTime, Utc};
+use chrono::{DateTime, Duration as ChronoDuration, FixedOffset, Utc};
 use getset::{Getters, MutGetters, Setters};
+use http::Method;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tempfile::NamedTempFile;
 use url::Url;
+use uuid::Uuid;
 
+#[cfg(feature = "bundle")]
+use crate::bundle;
+#[cfg(feature = "native")]
+use crate::native;
 use crate::{
-    benchmark::{Benchmark, BenchmarkBuilder},
+    audit::{self, AuditEntry},
+    benchmark::Benchmark,
+    command::{self, CommandSpec},
+    comparison::Comparison,
+    diagnosis,
+    encryption::{self, HistoryKey},
     error::WrkError,
-    result::{Deviation, WrkResult, WrkResultBuilder},
-    Gnuplot, LuaScript, Result,
+    guidance::{Enforcement, Guidance},
+    history::{self, HistoryFormat},
+    lua::ScriptOptions,
+    output,
+    overrides::Overrides,
+    preflight,
+    processor::ResultProcessor,
+    result::{Variance, WrkResult},
+    rollup,
+    runner::{self, ProcessOutput},
+    sanity,
+    status::{self, SuiteStatus},
+    target::{DynamicTarget, Teardown},
+    timeseries, watch,
+    window::VarianceWindow,
+    wrk2, BenchSession, Clock, CompositeScore, HistoryStore, LuaScript, MultiVariance, Multipart, ProcessRunner,
+    Profile, ResponseCheck, Result, StallDetection, SystemClock, SystemRunner, TextChart,
 };
 
-const DATE_FORMAT: &str = "%Y-%m-%d-%H:%M:%S-%z";
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Period of historical data to compare the current benchmark against.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub enum HistoryPeriod {
+    /// Last recorded benchmark.
+    #[default]
     Last,
+    /// Benchmarks recorded in the last hour.
     Hour,
+    /// Benchmarks recorded in the last day.
     Day,
+    /// Benchmarks recorded in the last week.
     Week,
+    /// Benchmarks recorded in the last 4 weeks.
     Month,
+    /// All the recorded benchmarks.
     Forever,
 }
 
-impl Default for HistoryPeriod {
-    fn default() -> Self {
-        HistoryPeriod::Last
-    }
-}
-
 impl HistoryPeriod {
+    /// Oldest date considered valid for this period.
     pub fn last_valid_datapoint(&self) -> DateTime<Utc> {
-        let now = Utc::now();
+        self.since(Utc::now())
+    }
+
+    /// Oldest date considered valid for this period at `now`.
+    pub fn since(&self, now: DateTime<Utc>) -> DateTime<Utc> {
         match self {
             Self::Last => now,
             Self::Hour => now.sub(ChronoDuration::hours(1)),
             Self::Day => now.sub(ChronoDuration::days(1)),
             Self::Week => now.sub(ChronoDuration::weeks(1)),
             Self::Month => now.sub(ChronoDuration::weeks(4)),
-            Self::Forever => DateTime::from_utc(NaiveDateTime::from_timestamp(1, 0), Utc),
+            Self::Forever => DateTime::from_timestamp(1, 0).expect("valid timestamp"),
+        }
+    }
+}
+
+/// Policy deciding when benchmark results are recorded in the history directory.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum RecordPolicy {
+    /// Record every run.
+    #[default]
+    Always,
+    /// Record a run only when all its benchmarks succeeded.
+    OnSuccess,
+    /// Never record, useful for exploratory runs.
+    Never,
+    /// Record only through explicit calls to [`Wrk::record`].
+    Manual,
+}
+
+/// Load generator running the benchmarks.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Engine {
+    /// The wrk binary run by the [`ProcessRunner`], and wrk2 for constant rate benchmarks, see
+    /// [`Wrk::wrk2_program`].
+    #[default]
+    Wrk,
+    /// Load generator built into this crate, for machines where wrk can not be installed. It
+    /// targets plain HTTP and does not support Lua scripts, multipart bodies, response checks
+    /// and constant rates.
+    #[cfg(feature = "native")]
+    Native,
+}
+
+impl Engine {
+    /// Program recorded in the audit log.
+    fn program(&self) -> &'static str {
+        match self {
+            Self::Wrk => "wrk",
+            #[cfg(feature = "native")]
+            Self::Native => native::PROGRAM,
         }
     }
 }
 
+/// Recorded run to execute again with [`Wrk::rerun`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoredRun {
+    /// Run recorded at the given date.
+    Date(DateTime<Utc>),
+    /// History file holding the run.
+    File(PathBuf),
+}
+
+impl From<DateTime<Utc>> for StoredRun {
+    fn from(date: DateTime<Utc>) -> Self {
+        Self::Date(date)
+    }
+}
+
+impl From<PathBuf> for StoredRun {
+    fn from(path: PathBuf) -> Self {
+        Self::File(path)
+    }
+}
+
+impl From<&Path> for StoredRun {
+    fn from(path: &Path) -> Self {
+        Self::File(path.to_path_buf())
+    }
+}
+
+/// Directory of the history where rendered Lua scripts are cached.
+const SCRIPTS_DIR: &str = "scripts";
+/// Subdirectory of the history path archiving the Lua scripts of the recorded runs by their
+/// SHA-256, IE: `script-archive/<sha256>.lua`.
+const SCRIPT_ARCHIVE_DIR: &str = "script-archive";
+/// Maximum number of runs of the rate search of [`Wrk::find_max_rate`].
+const MAX_RATE_STEPS: usize = 12;
+/// Directory of the history where the cold start series is stored.
+const COLD_START_DIR: &str = "cold-start";
+
+/// List of benchmark results.
 pub type Benchmarks = Vec<WrkResult>;
-pub type Headers = HashMap<String, String>;
+/// HTTP headers sent with every wrk request.
+pub type Headers = BTreeMap<String, String>;
 
 /// Wrapper around Wrk enabling to run benchmarks, record historical data and plot graphs.
+///
+/// The configuration is immutable once built and can be shared behind an [`std::sync::Arc`]
+/// by multiple [`BenchSession`]s, which hold the produced results.
 #[derive(Debug, Clone, Serialize, Deserialize, Getters, Setters, MutGetters, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
 pub struct Wrk {
     /// Url of the service to benchmark against. Use the full URL of the request.
     /// IE: http://localhost:1234/some/uri.
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     url: String,
-    /// Wrk timeout in seconds
-    #[serde(skip)]
+    /// Wrk timeout in seconds, see [`Wrk::request_timeout`].
+    #[serde(default = "default_timeout")]
     #[builder(default = "1")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     timeout: u8,
-    /// Set of benchmarks for the current instance.
-    #[builder(default)]
-    #[getset(get = "pub", set = "pub", get_mut = "pub")]
-    benchmarks: Benchmarks,
-    /// Historical benchmarks data, indexed by dates.
-    #[builder(default)]
-    #[getset(get = "pub", set = "pub", get_mut = "pub")]
-    benchmarks_history: Benchmarks,
     /// Directory on disk where to store and read the historical benchmark data.
     #[builder(default = "Path::new(\".\").join(\".wrk-api-bench\")")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
@@ -90,28 +199,273 @@ pub struct Wrk {
     #[builder(default)]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     headers: Headers,
+    /// Headers whose values are redacted, ignoring case, in the persisted history, bundles and
+    /// Lua scripts, which read them from the environment of the wrk process instead.
+    #[builder(default = "crate::headers::default_redacted()")]
+    #[serde(default = "crate::headers::default_redacted")]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    redacted_headers: Vec<String>,
     /// Method for the wrk request.
-    #[builder(default = "String::from(\"GET\")")]
+    #[builder(default = "Method::GET")]
+    #[serde(with = "crate::method")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
-    method: String,
+    method: Method,
     /// Body for the wrk request.
     #[builder(default)]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     body: String,
+    /// Multipart body sent instead of [`Wrk::body`], with its `Content-Type` header.
+    #[builder(default, setter(strip_option))]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    multipart: Option<Multipart>,
+    /// Checks every response body must pass, counting failures as `errors_mismatch`.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    response_checks: Vec<ResponseCheck>,
+    /// Status codes counted as success, IE: `404` for a not-found benchmark. Responses with
+    /// other statuses are counted as `errors_status`. Empty keeps wrk's 2xx/3xx default.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    success_statuses: Vec<u16>,
+    /// Record the responses received every second into [`WrkResult::timeseries`].
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    timeseries: bool,
+    /// Flag throughput dips in the per-second timeseries, which is recorded even when
+    /// [`Wrk::timeseries`] is disabled.
+    #[builder(default, setter(strip_option))]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    stall_detection: Option<StallDetection>,
+    /// Highest p99 latency in milliseconds of the configuration recommended by
+    /// [`crate::SuiteSummary::recommended_config`].
+    #[builder(default, setter(strip_option))]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    p99_ceiling_ms: Option<f64>,
+    /// Stream the engine output to the console while it runs, prefixing every line with the
+    /// [`Benchmark::key`], so long runs are observable.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    tee_output: bool,
+    /// Capture the `Server` header of the first response of every run in
+    /// [`WrkResult::server`].
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    capture_server: bool,
+    /// Header carrying the deployed version, IE: `X-App-Version`, captured from the first
+    /// response of every run in [`WrkResult::app_version`].
+    #[builder(default, setter(into, strip_option))]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    version_header: Option<String>,
+    /// Return a baseline [`Variance`] from [`BenchSession::variance`] when no history has
+    /// been recorded yet, instead of failing, so the first run of a pipeline passes.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    bootstrap_baseline: bool,
+    /// Minimum duration and request count checked before running a suite, also used to flag
+    /// too short results.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    guidance: Guidance,
+    /// Composite score stored in every successful [`WrkResult`].
+    #[builder(default, setter(strip_option))]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    score: Option<CompositeScore>,
     /// Max percentage of errors vs total request to conside a benchmark healthy.
     #[builder(default = "2")]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
     max_error_percentage: u8,
-    /// Current benchmark date and time.
-    #[serde(skip)]
+    /// Load generator running the benchmarks.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    engine: Engine,
+    /// Binary of wrk2 running the benchmarks with a [`Benchmark::rate`]. When not set, `wrk2`
+    /// is used when installed, otherwise `wrk` when it is a build of wrk2 accepting `-R`.
+    #[builder(default, setter(strip_option))]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    wrk2_program: Option<String>,
+    /// When to record benchmark results in the history directory. With [`RecordPolicy::Never`]
+    /// nothing is written there: no audit log, no suite status and no cached scripts, which
+    /// go to the temporary directory instead.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    record_policy: RecordPolicy,
+    /// Name of the project the history belongs to. When not set, the history is namespaced
+    /// by a slug of the benchmarked URL, so runs against different targets are never compared.
+    #[builder(default, setter(strip_option))]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    project: Option<String>,
+    /// Serialization format of the history files written by this instance.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    history_format: HistoryFormat,
+    /// Extra raw arguments appended to the generated wrk command line, before the URL, to use
+    /// engine flags not modelled by this crate.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    extra_args: Vec<String>,
+    /// Directories searched for Lua modules required by the user script, on top of the
+    /// directory of the script itself.
     #[builder(default)]
+    #[serde(default)]
     #[getset(get = "pub", set = "pub", get_mut = "pub")]
-    benchmark_date: Option<DateTime<Utc>>,
+    lua_search_paths: Vec<PathBuf>,
+    /// Environment variables set on the spawned wrk process, IE: `LUA_PATH` or `SSLKEYLOGFILE`.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    env: BTreeMap<String, String>,
+    /// Pause between consecutive benchmarks, letting the target drain queues and return to
+    /// idle before the next configuration starts.
+    #[builder(default)]
+    #[serde(default)]
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    cooldown: Duration,
+    /// Chain of processors applied to every result before it is recorded.
+    #[serde(skip)]
+    #[builder(default, setter(custom))]
+    #[getset(get = "pub")]
+    processors: Vec<Arc<dyn ResultProcessor>>,
+    /// Runner of the wrk commands, spawning local processes by default.
+    #[serde(skip, default = "default_runner")]
+    #[builder(default = "default_runner()", setter(custom))]
+    #[getset(get = "pub")]
+    runner: Arc<dyn ProcessRunner>,
+    /// Source of the current time, dating runs and history files and bounding history periods.
+    #[serde(skip, default = "default_clock")]
+    #[builder(default = "default_clock()")]
+    #[getset(get = "pub", set = "pub")]
+    clock: Arc<dyn Clock>,
+    /// wrk2 program detected through the runner, looked up once.
+    #[serde(skip)]
+    #[builder(setter(skip))]
+    detected_wrk2: Arc<OnceLock<Option<String>>>,
+    /// Scripts of the original run replayed by [`Wrk::rerun`] instead of rendering them.
+    #[serde(skip)]
+    #[builder(setter(skip))]
+    replayed_scripts: Vec<(Benchmark, PathBuf)>,
+    /// Storage of the history directory, the local filesystem by default.
+    #[serde(skip)]
+    #[builder(default)]
+    #[getset(get = "pub", set = "pub")]
+    history_store: HistoryStore,
+    /// Ordering of results replacing the built-in one when selecting the best result.
+    #[serde(skip)]
+    #[builder(default, setter(custom))]
+    best_comparator: Option<BestComparator>,
+}
+
+/// Ordering of two results.
+type Comparator = dyn Fn(&WrkResult, &WrkResult) -> Ordering + Send + Sync;
+
+/// Command running a single benchmark, with the files it needs.
+struct Execution {
+    script: PathBuf,
+    spec: CommandSpec,
+    sidecar: Option<NamedTempFile>,
+    warnings: Vec<String>,
+}
+
+/// Ordering of results used to select the best one, where the greatest result is the best.
+#[derive(Clone)]
+struct BestComparator(Arc<Comparator>);
+
+impl fmt::Debug for BestComparator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("BestComparator")
+    }
+}
+
+fn default_timeout() -> u8 {
+    1
+}
+
+/// Lowercase alphanumeric words of `value` joined by dashes, IE: `application-json` for
+/// `application/json`.
+pub(crate) fn slug(value: &str) -> String {
+    value
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
+/// Drop the results already seen, by [`WrkResult::identity`], keeping the first occurrence.
+pub(crate) fn dedup(results: Benchmarks) -> Benchmarks {
+    let mut seen = HashSet::new();
+    results.into_iter().filter(|r| seen.insert(r.identity())).collect()
+}
+
+fn default_runner() -> Arc<dyn ProcessRunner> {
+    Arc::new(SystemRunner)
+}
+
+fn default_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+impl WrkBuilder {
+    /// Select the best result with `comparator` instead of the built-in ordering, see
+    /// [`Wrk::set_best_comparator`].
+    pub fn best_comparator<F>(&mut self, comparator: F) -> &mut Self
+    where
+        F: Fn(&WrkResult, &WrkResult) -> Ordering + Send + Sync + 'static,
+    {
+        self.best_comparator = Some(Some(BestComparator(Arc::new(comparator))));
+        self
+    }
+
+    /// Apply the record policy, output mode, guidance and cooldown of `profile`. Settings
+    /// configured afterwards override the preset ones.
+    pub fn preset(&mut self, profile: Profile) -> &mut Self {
+        profile.apply(self);
+        self
+    }
+
+    /// Run the wrk commands with `runner` instead of spawning local processes.
+    pub fn runner<R: ProcessRunner + 'static>(&mut self, runner: R) -> &mut Self {
+        self.runner = Some(Arc::new(runner));
+        self
+    }
+
+    /// Append a processor to the chain applied to every result.
+    pub fn processor<P: ResultProcessor + 'static>(&mut self, processor: P) -> &mut Self {
+        self.processors.get_or_insert_with(Vec::new).push(Arc::new(processor));
+        self
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        if let Some(method) = &self.method {
+            crate::method::validate(method)?;
+        }
+        if let Some(headers) = &self.headers {
+            crate::headers::validate(headers)?;
+        }
+        Ok(())
+    }
 }
 
 impl Wrk {
     fn wrk_args(&self, benchmark: &Benchmark, url: &Url, lua_script: &Path) -> Result<Vec<String>> {
-        Ok(vec![
+        let mut args = vec![
             "-t".to_string(),
             benchmark.threads().to_string(),
             "-c".to_string(),
@@ -119,17 +473,73 @@ impl Wrk {
             "-d".to_string(),
             format!("{}s", benchmark.duration().as_secs()),
             "--timeout".to_string(),
-            format!("{}s", self.timeout()),
+            format!("{}s", self.request_timeout(benchmark).as_secs()),
+            "--latency".to_string(),
             "-s".to_string(),
             lua_script.to_string_lossy().to_string(),
-            url.to_string(),
-        ])
+        ];
+        if let Some(rate) = benchmark.rate() {
+            args.extend(["-R".to_string(), rate.to_string()]);
+        }
+        args.extend(self.extra_args().iter().cloned());
+        args.push(url.to_string());
+        Ok(args)
+    }
+
+    /// Fill the percentiles wrk was unable to expose through Lua with the ones parsed from
+    /// the `--latency` output.
+    fn fill_percentiles(run: &mut WrkResult, output: &str) {
+        if *run.p50_latency_ms() > 0.0 {
+            return;
+        }
+        for (percentile, latency) in output::latency_distribution(output) {
+            match percentile as u8 {
+                50 => *run.p50_latency_ms_mut() = latency,
+                75 => *run.p75_latency_ms_mut() = latency,
+                90 => *run.p90_latency_ms_mut() = latency,
+                99 => *run.p99_latency_ms_mut() = latency,
+                _ => {}
+            }
+        }
+    }
+
+    /// Cross-check the errors reported by the Lua script against the wrk summary, flagging
+    /// mismatches which usually point to a script bug.
+    fn check_errors(&self, run: &mut WrkResult, output: &str) {
+        let summary = output::error_summary(output);
+        let mut checks = vec![
+            ("connect", *run.errors_connect(), summary.connect),
+            ("read", *run.errors_read(), summary.read),
+            ("write", *run.errors_write(), summary.write),
+            ("timeout", *run.errors_timeout(), summary.timeout),
+        ];
+        // Status errors are counted by the script itself when an allowlist is set.
+        if self.success_statuses().is_empty() || self.user_script().is_some() {
+            checks.push(("status", *run.errors_status(), summary.status));
+        }
+        for (kind, json, wrk) in checks {
+            if json != wrk {
+                let warning = format!("Lua script reported {} {} errors, wrk reported {}", json, kind, wrk);
+                warn!("{}", warning);
+                run.warnings_mut().push(warning);
+            }
+        }
     }
 
-    fn wrk_result(&self, wrk_json: &str) -> WrkResult {
-        match serde_json::from_str::<WrkResult>(wrk_json) {
+    fn wrk_result(&self, output: &str, wrk_json: &str) -> WrkResult {
+        let parsed = serde_json::from_str::<WrkResult>(wrk_json).or_else(|e| {
+            let normalized = output::normalize_json_numbers(wrk_json);
+            if normalized == wrk_json {
+                return Err(e);
+            }
+            warn!("Wrk JSON result uses localized numbers, normalizing them");
+            serde_json::from_str::<WrkResult>(&normalized)
+        });
+        match parsed {
             Ok(mut run) => {
-                let error_percentage = run.errors() / 100.0 * run.requests();
+                Self::fill_percentiles(&mut run, output);
+                self.check_errors(&mut run, output);
+                let error_percentage = run.error_rate() * 100.0;
                 if error_percentage < *self.max_error_percentage() as f64 {
                     *run.success_mut() = true;
                 } else {
@@ -147,225 +557,2467 @@ impl Wrk {
         }
     }
 
-    pub fn bench(&mut self, benchmarks: &Vec<Benchmark>) -> Result<()> {
-        if !self.history_dir().exists() {
-            fs::create_dir(self.history_dir()).unwrap_or_else(|e| {
-                error!(
-                    "Unable to create storage dir {}: {}. Statistics calculation could be impaired",
-                    self.history_dir().display(),
-                    e
-                );
-            });
+    /// Run the given benchmarks, tagging every result with `date` and `suite_id`. `on_result`
+    /// is called as soon as every single result is available.
+    pub(crate) fn run<F>(
+        &self,
+        benchmarks: &[Benchmark],
+        date: DateTime<Utc>,
+        suite_id: Uuid,
+        mut on_result: F,
+    ) -> Result<Benchmarks>
+    where
+        F: FnMut(&WrkResult) -> Result<()>,
+    {
+        let (url, mut status, history) = self.start_suite(benchmarks, suite_id)?;
+        let mut results = Benchmarks::new();
+        for (i, benchmark) in benchmarks.iter().enumerate() {
+            status.start(benchmark, self.remaining(benchmarks, i));
+            self.write_status(&status);
+            if let Some(cooldown) = self.cooldown_before(benchmark, i) {
+                thread::sleep(cooldown);
+            }
+            let execution = self.prepare(benchmark, &url, &history)?;
+            let start = self.now();
+            let output = match self.engine() {
+                #[cfg(feature = "native")]
+                Engine::Native => self.native_run(benchmark, &url, &execution.spec),
+                Engine::Wrk if *self.tee_output() => self.runner().run_tee(&execution.spec, &benchmark.key()),
+                Engine::Wrk => self.runner().run(&execution.spec),
+            };
+            let run = self.finish(benchmark, execution, output, start, date, suite_id)?;
+            status.complete(benchmark, Some(&run), self.remaining(benchmarks, i + 1));
+            self.write_status(&status);
+            self.deliver(benchmark, run, &mut results, &mut on_result)?;
         }
-        let date = Utc::now();
-        *self.benchmark_date_mut() = Some(date);
-        let url = Url::parse(self.url())?;
-        let mut script_file = NamedTempFile::new()?;
-        LuaScript::render(
-            &mut script_file,
-            self.user_script().as_ref(),
-            url.path(),
-            self.method(),
-            self.headers(),
-            self.body(),
-        )?;
-        for benchmark in benchmarks {
-            let mut run = match Command::new("wrk")
-                .args(self.wrk_args(benchmark, &url, script_file.path())?)
-                .output()
-            {
-                Ok(wrk) => {
-                    let output = String::from_utf8_lossy(&wrk.stdout);
-                    let error = String::from_utf8_lossy(&wrk.stderr);
-                    if wrk.status.success() {
-                        debug!("Wrk execution succeded:\n{}", output);
-                        let wrk_json = output
-                            .split("JSON")
-                            .nth(1)
-                            .ok_or_else(|| WrkError::Lua("Wrk returned empty JSON".to_string()))?;
-                        self.wrk_result(wrk_json)
-                    } else {
-                        error!("Wrk execution failed.\nOutput: {}\nError: {}", output, error);
-                        WrkResult::fail(error.to_string())
-                    }
+        Ok(results)
+    }
+
+    /// Same as [`Wrk::run`], awaiting every execution with [`ProcessRunner::run_async`].
+    pub(crate) async fn run_async<F>(
+        &self,
+        benchmarks: &[Benchmark],
+        date: DateTime<Utc>,
+        suite_id: Uuid,
+        mut on_result: F,
+    ) -> Result<Benchmarks>
+    where
+        F: FnMut(&WrkResult) -> Result<()>,
+    {
+        let (url, mut status, history) = self.start_suite(benchmarks, suite_id)?;
+        let mut results = Benchmarks::new();
+        for (i, benchmark) in benchmarks.iter().enumerate() {
+            status.start(benchmark, self.remaining(benchmarks, i));
+            self.write_status(&status);
+            if let Some(cooldown) = self.cooldown_before(benchmark, i) {
+                tokio::time::sleep(cooldown).await;
+            }
+            let execution = self.prepare(benchmark, &url, &history)?;
+            let start = self.now();
+            let output = match self.engine() {
+                #[cfg(feature = "native")]
+                Engine::Native => {
+                    let wrk = self.clone();
+                    let (benchmark, url, spec) = (benchmark.clone(), url.clone(), execution.spec.clone());
+                    tokio::task::spawn_blocking(move || wrk.native_run(&benchmark, &url, &spec))
+                        .await
+                        .unwrap_or_else(|e| Err(WrkError::Exec(format!("Native run failed: {}", e))))
                 }
-                Err(e) => {
-                    error!("Wrk execution failed: {}", e);
-                    WrkResult::fail(e.to_string())
+                Engine::Wrk => {
+                    let output = self.runner().run_async(&execution.spec).await;
+                    if let (true, Ok(output)) = (*self.tee_output(), &output) {
+                        runner::echo(output, &benchmark.key())?;
+                    }
+                    output
                 }
             };
-            *run.date_mut() = date;
-            *run.benchmark_mut() = benchmark.clone();
-            self.benchmarks_mut().push(run);
+            let run = self.finish(benchmark, execution, output, start, date, suite_id)?;
+            status.complete(benchmark, Some(&run), self.remaining(benchmarks, i + 1));
+            self.write_status(&status);
+            self.deliver(benchmark, run, &mut results, &mut on_result)?;
         }
-        script_file.keep()?;
-        self.dump(date)?;
-        Ok(())
+        Ok(results)
     }
 
-    pub fn bench_exponential(&mut self, duration: Option<Duration>) -> Result<()> {
-        self.bench(&BenchmarkBuilder::exponential(duration))?;
-        Ok(())
+    /// Check `benchmarks` can run, returning the target URL, the status of the new suite and
+    /// the whole history.
+    fn start_suite(&self, benchmarks: &[Benchmark], suite_id: Uuid) -> Result<(Url, SuiteStatus, Benchmarks)> {
+        let url = Url::parse(self.url())?;
+        preflight::check(benchmarks)?;
+        let history = self.check_history(benchmarks);
+        if *self.guidance().enforcement() != Enforcement::Off {
+            self.guidance().check(benchmarks, |benchmark| {
+                history
+                    .iter()
+                    .filter(|r| *r.success() && r.benchmark().as_ref().is_some_and(|b| b.is_comparable(benchmark)))
+                    .map(|r| *r.requests_sec())
+                    .reduce(f64::max)
+            })?;
+        }
+        let estimate = self.suite_eta(benchmarks);
+        info!(
+            "Running {} benchmarks, estimated to take {:?}",
+            benchmarks.len(),
+            estimate
+        );
+        Ok((url, SuiteStatus::new(suite_id, benchmarks.len(), estimate), history))
     }
 
-    fn dump(&self, date: DateTime<Utc>) -> Result<()> {
-        let filename = format!("result.{}.json", date.format(DATE_FORMAT));
-        let file = File::create(self.history_dir().join(&filename))?;
-        let writer = BufWriter::new(file);
-        println!("Writing current benchmark to {}", filename);
-        serde_json::to_writer(writer, &self.benchmarks())?;
-        Ok(())
+    /// Successful recorded results comparable to `benchmarks`, checked by the guidance and the
+    /// request timeouts: the whole history when the guidance is enforced, otherwise the last
+    /// run only.
+    fn check_history(&self, benchmarks: &[Benchmark]) -> Benchmarks {
+        let period = match self.guidance().enforcement() {
+            Enforcement::Off => HistoryPeriod::Last,
+            _ => HistoryPeriod::Forever,
+        };
+        match self.history(&period, None) {
+            Ok(history) => history
+                .into_iter()
+                .filter(|r| *r.success())
+                .filter(|r| {
+                    r.benchmark()
+                        .as_ref()
+                        .is_some_and(|b| benchmarks.iter().any(|benchmark| benchmark.is_comparable(b)))
+                })
+                .collect(),
+            Err(WrkError::History(e)) => {
+                debug!("No history to check the benchmarks against: {}", e);
+                Benchmarks::new()
+            }
+            Err(e) => {
+                warn!("Unable to load the history, skipping the checks based on it: {}", e);
+                Benchmarks::new()
+            }
+        }
     }
 
-    fn load(&mut self, period: HistoryPeriod, best: bool) -> Result<()> {
-        if !self.history_dir().exists() {
-            fs::create_dir(self.history_dir())?;
+    /// Cooldown to wait before the benchmark at index `i` of a suite, if any.
+    fn cooldown_before(&self, benchmark: &Benchmark, i: usize) -> Option<Duration> {
+        if i == 0 || self.cooldown().is_zero() {
+            return None;
         }
-        let mut paths: Vec<_> = fs::read_dir(self.history_dir())?.map(|r| r.unwrap()).collect();
-        paths.sort_by_key(|dir| {
-            let metadata = fs::metadata(dir.path()).unwrap();
-            metadata.modified().unwrap()
-        });
-        let mut history = Benchmarks::new();
-        if period == HistoryPeriod::Last {
-            let file = File::open(paths.pop().unwrap().path())?;
-            let mut reader = BufReader::new(file);
-            history = serde_json::from_reader(&mut reader)?;
-            let benchmark = history.pop().unwrap();
-            if let Some(benchmark_date) = self.benchmark_date() {
-                if benchmark_date == benchmark.date() && !paths.is_empty() {
-                    let file = File::open(paths.pop().unwrap().path())?;
-                    let mut reader = BufReader::new(file);
-                    history = serde_json::from_reader(&mut reader)?;
-                    if best {
-                        let best = self.best_benchmark(&history)?;
-                        history = vec![best];
-                    }
+        debug!(
+            "Cooling down for {:?} before benchmark {}",
+            self.cooldown(),
+            benchmark.key()
+        );
+        Some(*self.cooldown())
+    }
+
+    /// Render the script and the command running `benchmark`, checking its request timeout
+    /// against the latencies in `history`.
+    fn prepare(&self, benchmark: &Benchmark, url: &Url, history: &Benchmarks) -> Result<Execution> {
+        let script = self.script(benchmark, url)?;
+        let mut spec = self.command_spec(benchmark, url, &script)?;
+        let sidecar = self.records_timeseries().then(NamedTempFile::new).transpose()?;
+        if let Some(sidecar) = &sidecar {
+            spec = spec.with_env(timeseries::ENV, &sidecar.path().to_string_lossy());
+        }
+        let warnings = self.timeout_warnings(benchmark, history);
+        for warning in &warnings {
+            warn!("Benchmark {}: {}", benchmark.key(), warning);
+        }
+        Ok(Execution {
+            script,
+            spec,
+            sidecar,
+            warnings,
+        })
+    }
+
+    /// Run `benchmark` with the native engine, reading the sidecar file of the timeseries from
+    /// the environment of `spec`.
+    #[cfg(feature = "native")]
+    fn native_run(&self, benchmark: &Benchmark, url: &Url, spec: &CommandSpec) -> Result<ProcessOutput> {
+        let unsupported = [
+            (self.user_script().is_some(), "Lua scripts"),
+            (self.multipart().is_some(), "multipart bodies"),
+            (!self.response_checks().is_empty(), "response checks"),
+            (benchmark.rate().is_some(), "constant rates"),
+        ];
+        if let Some((_, feature)) = unsupported.iter().find(|(used, _)| *used) {
+            return Err(WrkError::Config(format!(
+                "The native engine does not support {}",
+                feature
+            )));
+        }
+        let output = native::run(native::Load {
+            url: url.to_string(),
+            method: benchmark.method_or(self.method()).clone(),
+            headers: benchmark.headers_or(self.headers()),
+            body: benchmark.body_or(self.body()).to_string(),
+            threads: *benchmark.threads(),
+            connections: *benchmark.connections(),
+            duration: *benchmark.duration(),
+            timeout: self.request_timeout(benchmark),
+            success_statuses: self.success_statuses().clone(),
+            capture_server: *self.capture_server(),
+            version_header: self.version_header().clone(),
+            timeseries: spec.env().get(timeseries::ENV).map(PathBuf::from),
+        })?;
+        if *self.tee_output() {
+            runner::echo(&output, &benchmark.key())?;
+        }
+        Ok(output)
+    }
+
+    /// Timeout of every request of `benchmark`: its own one or [`Wrk::timeout`], lowered to
+    /// the duration of the run. Wrk only accepts whole seconds.
+    pub fn request_timeout(&self, benchmark: &Benchmark) -> Duration {
+        let timeout = self.configured_timeout(benchmark);
+        let secs = timeout.as_secs() + (timeout.subsec_nanos() > 0) as u64;
+        Duration::from_secs(secs.clamp(1, benchmark.duration().as_secs().max(1)))
+    }
+
+    fn configured_timeout(&self, benchmark: &Benchmark) -> Duration {
+        benchmark
+            .timeout()
+            .unwrap_or_else(|| Duration::from_secs(*self.timeout() as u64))
+    }
+
+    /// Problems of the request timeout of `benchmark`: longer than the run, or shorter than the
+    /// p99 latency of comparable runs in `history`, which would turn slow requests into errors.
+    fn timeout_warnings(&self, benchmark: &Benchmark, history: &Benchmarks) -> Vec<String> {
+        let timeout = self.request_timeout(benchmark);
+        let mut warnings = Vec::new();
+        let configured = self.configured_timeout(benchmark);
+        if configured > timeout {
+            warnings.push(format!(
+                "Timeout of {:?} exceeds the duration of the run, lowered to {}s",
+                configured,
+                timeout.as_secs()
+            ));
+        }
+        let p99 = history
+            .iter()
+            .filter(|r| *r.success() && r.benchmark().as_ref().is_some_and(|b| b.is_comparable(benchmark)))
+            .map(|r| *r.p99_latency_ms())
+            .reduce(f64::max);
+        if let Some(p99) = p99.filter(|p99| *p99 >= timeout.as_secs_f64() * 1000.0) {
+            warnings.push(format!(
+                "Timeout of {}s is below the p99 latency of {:.2}ms in the history: slow requests \
+                 will time out, raise the timeout",
+                timeout.as_secs(),
+                p99
+            ));
+        }
+        warnings
+    }
+
+    /// Turn the outcome of the `execution` of `benchmark`, started at `start`, into its result.
+    fn finish(
+        &self,
+        benchmark: &Benchmark,
+        prepared: Execution,
+        execution: Result<ProcessOutput>,
+        start: DateTime<Utc>,
+        date: DateTime<Utc>,
+        suite_id: Uuid,
+    ) -> Result<WrkResult> {
+        let Execution {
+            script,
+            spec,
+            sidecar,
+            warnings,
+        } = prepared;
+        let end = self.now();
+        let elapsed = (end - start).to_std().unwrap_or_default();
+        let cpu_time = execution.as_ref().ok().and_then(|output| *output.cpu_time());
+        let entry = AuditEntry::new(spec.program(), spec.args(), &script, start, end, &execution);
+        if self.writes_history() {
+            if let Err(e) = audit::append(&*self.history_store, &self.history_path(), &entry) {
+                error!("Unable to append to the audit log: {}", e);
+            }
+            if !entry.script_sha256().is_empty() {
+                if let Err(e) = self.archive_script(entry.script_sha256(), &script) {
+                    error!("Unable to archive the Lua script: {}", e);
+                }
+            }
+        }
+        let diagnoses = execution
+            .as_ref()
+            .map(|output| diagnosis::diagnose(output.stderr()))
+            .unwrap_or_default();
+        let mut run = match execution {
+            Ok(wrk) => {
+                if wrk.success() {
+                    debug!("Wrk execution succeded:\n{}", wrk.stdout());
+                    let wrk_json = output::json_result(wrk.stdout())
+                        .ok_or_else(|| WrkError::Lua("Wrk returned empty JSON".to_string()))?;
+                    self.wrk_result(wrk.stdout(), &wrk_json)
                 } else {
-                    return Err(WrkError::History(
-                        "Unable to load history with a single measurement".to_string(),
-                    ));
+                    error!(
+                        "Wrk execution failed.\nOutput: {}\nError: {}",
+                        wrk.stdout(),
+                        wrk.stderr()
+                    );
+                    WrkResult::fail(wrk.stderr().to_string())
                 }
             }
-        } else {
-            for path in paths {
-                if let Some(date_str) = path.file_name().to_string_lossy().split('.').nth(1) {
-                    let date = DateTime::parse_from_str(date_str, DATE_FORMAT)?;
-                    if date >= period.last_valid_datapoint() {
-                        let file = File::open(path.path())?;
-                        let mut reader = BufReader::new(file);
-                        let mut benchmarks: Vec<_> = serde_json::from_reader(&mut reader)?;
-                        benchmarks.retain(|x| !self.benchmarks_history().contains(x));
-                        if best {
-                            let best = self.best_benchmark(&benchmarks)?;
-                            history.push(best);
-                        } else {
-                            history.append(&mut benchmarks);
-                        }
+            Err(e) => {
+                error!("Wrk execution failed: {}", e);
+                WrkResult::fail(e.to_string())
+            }
+        };
+        if let Some(sidecar) = sidecar.filter(|_| *run.success()) {
+            match timeseries::read(sidecar.path()) {
+                Ok(samples) => {
+                    if let Some(detection) = self.stall_detection() {
+                        *run.stalls_mut() = detection.detect(&samples);
                     }
+                    for stall in run.stalls() {
+                        warn!("Benchmark {} stalled: {}", benchmark.key(), stall);
+                    }
+                    *run.timeseries_mut() = samples;
                 }
+                Err(e) => error!("Unable to read the timeseries of benchmark {}: {}", benchmark.key(), e),
+            }
+        }
+        for diagnosis in &diagnoses {
+            let hint = format!("{}: {}", diagnosis, diagnosis.hint());
+            if *run.success() {
+                warn!("Benchmark {}: {}", benchmark.key(), hint);
+                run.warnings_mut().push(hint);
+            } else {
+                *run.error_mut() += &format!("\nHint: {}", hint);
+            }
+        }
+        *run.diagnoses_mut() = diagnoses;
+        if *run.success() {
+            run.warnings_mut().extend(warnings);
+        }
+        *run.run_id_mut() = Uuid::new_v4();
+        *run.suite_id_mut() = suite_id;
+        *run.date_mut() = date;
+        *run.benchmark_mut() = Some(benchmark.clone());
+        *run.script_sha256_mut() = Some(entry.script_sha256().clone()).filter(|sha| !sha.is_empty());
+        for warning in sanity::check(&run, benchmark, *self.guidance().min_duration()) {
+            warn!("Benchmark {}: {}", benchmark.key(), warning);
+            run.warnings_mut().push(warning);
+        }
+        if let Some(warning) = cpu_time
+            .filter(|_| *run.success())
+            .and_then(|cpu_time| sanity::generator_saturation(cpu_time, elapsed, benchmark))
+        {
+            warn!("Benchmark {}: {}", benchmark.key(), warning);
+            run.set_generator_saturated(true);
+            run.warnings_mut().push(warning);
+        }
+        if let Some(score) = self.score() {
+            *run.score_mut() = score.score(&run);
+        }
+        Ok(run)
+    }
+
+    /// Pass `run` through the processors, collecting it in `results` unless filtered out.
+    fn deliver<F>(
+        &self,
+        benchmark: &Benchmark,
+        run: WrkResult,
+        results: &mut Benchmarks,
+        on_result: &mut F,
+    ) -> Result<()>
+    where
+        F: FnMut(&WrkResult) -> Result<()>,
+    {
+        match self.process(run) {
+            Some(run) => {
+                on_result(&run)?;
+                results.push(run);
             }
+            None => debug!("Result of benchmark {} filtered out by processors", benchmark.key()),
         }
-        *self.benchmarks_history_mut() = history;
         Ok(())
     }
 
-    fn best_benchmark(&self, benchmarks: &Benchmarks) -> Result<WrkResult> {
-        let best = benchmarks.iter().filter(|v| *v.success()).max_by(|a, b| {
-            (*a.requests_sec() as i64)
-                .cmp(&(*b.requests_sec() as i64))
-                .then((*a.successes() as i64).cmp(&(*b.successes() as i64)))
-                .then((*a.requests() as i64).cmp(&(*b.requests() as i64)))
-                .then((*a.requests() as i64).cmp(&(*b.requests() as i64)))
-                .then((*a.transfer_mb() as i64).cmp(&(*b.transfer_mb() as i64)))
-        });
-        best.cloned().ok_or_else(|| {
-            WrkError::Stats(format!(
-                "Unable to calculate best in a set of {} elements",
-                benchmarks.len()
-            ))
-        })
+    /// Estimated duration of a suite running `benchmarks`: the duration of every run plus the
+    /// cooldowns between them.
+    pub fn suite_eta(&self, benchmarks: &[Benchmark]) -> Duration {
+        let runs: Duration = benchmarks.iter().map(|b| *b.duration()).sum();
+        runs + *self.cooldown() * benchmarks.len().saturating_sub(1) as u32
     }
 
-    fn best(&self) -> Result<WrkResult> {
-        self.best_benchmark(self.benchmarks())
+    /// Estimated duration of the suite from the start of the benchmark at index `from`,
+    /// including the cooldown before it.
+    fn remaining(&self, benchmarks: &[Benchmark], from: usize) -> Duration {
+        match from {
+            0 => self.suite_eta(benchmarks),
+            _ if from >= benchmarks.len() => Duration::ZERO,
+            _ => self.suite_eta(&benchmarks[from..]) + *self.cooldown(),
+        }
     }
 
-    fn historical_best(&self) -> Result<WrkResult> {
-        self.best_benchmark(self.benchmarks_history())
+    /// Whether runs write their audit log, suite status and scripts under the history path.
+    fn writes_history(&self) -> bool {
+        *self.record_policy() != RecordPolicy::Never
     }
 
-    pub fn all_benchmarks(&self) -> Benchmarks {
-        let mut history = self.benchmarks_history().clone();
-        history.append(&mut self.benchmarks().clone());
-        history
+    fn write_status(&self, suite: &SuiteStatus) {
+        if !self.writes_history() {
+            return;
+        }
+        if let Err(e) = status::write(&*self.history_store, &self.history_path(), suite) {
+            error!("Unable to write the suite status: {}", e);
+        }
     }
 
-    pub fn deviation(&mut self, period: HistoryPeriod) -> Result<Deviation> {
-        self.load(period, false)?;
-        let new = self.best()?;
-        let old = self.historical_best()?;
-        Ok(Deviation::new(new, old))
+    /// Progress of the last suite run for this project or target URL, written while it runs so
+    /// it can be observed from outside the process.
+    pub fn suite_status(&self) -> Result<Option<SuiteStatus>> {
+        status::read(&*self.history_store, &self.history_path())
     }
 
-    pub fn plot(&self, title: &str, output: &Path, benchmarks: &Benchmarks) -> Result<()> {
-        Gnuplot::new(title, output).plot(benchmarks)
+    /// Whether the per-second timeseries is recorded, either on its own or for stall detection.
+    fn records_timeseries(&self) -> bool {
+        *self.timeseries() || self.stall_detection().is_some()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{net::SocketAddr, thread, time::Duration};
+    fn process(&self, result: WrkResult) -> Option<WrkResult> {
+        self.processors()
+            .iter()
+            .try_fold(result, |result, processor| processor.process(result))
+    }
 
-    use super::*;
-    use crate::benchmark::BenchmarkBuilder;
-    use axum::{
-        http::StatusCode,
-        response::IntoResponse,
-        routing::{get, post},
-        Json, Router,
-    };
-    use http::Request;
-    use hyper::Body;
+    /// Run the given benchmarks and return their results without writing anything to the
+    /// history directory, as with [`RecordPolicy::Never`]. Use [`Wrk::record`] to store them.
+    /// The `WRK_API_BENCH_*` environment overrides apply as in [`BenchSession::bench`].
+    pub fn bench_collect(&self, benchmarks: &[Benchmark]) -> Result<Benchmarks> {
+        let mut collecting = self.clone();
+        collecting.set_record_policy(RecordPolicy::Never);
+        let (wrk, benchmarks) = Overrides::from_env()?.apply(&Arc::new(collecting), benchmarks);
+        wrk.run(&benchmarks, self.now(), Uuid::new_v4(), |_| Ok(()))
+    }
 
-    async fn server() {
-        let app = Router::new().route("/", get(|| async { "Hello, world!" }));
+    /// Find the highest constant rate at which `benchmark` meets the p99 `latency_slo`, binary
+    /// searching the wrk2 `-R` rate until the bounds are within 5% of each other. The search
+    /// is bounded by the [`Benchmark::rate`] when set, which is tried first, otherwise by the
+    /// throughput of an unthrottled run. Fails right away when wrk2 is not available.
+    ///
+    /// The fastest run meeting the SLO is returned and recorded with its
+    /// [`WrkResult::capacity`], according to the [`RecordPolicy`]. It is recorded under the
+    /// benchmark without rate, so the capacities of successive searches are comparable.
+    pub fn find_max_rate(&self, benchmark: &Benchmark, latency_slo: Duration) -> Result<WrkResult> {
+        if *self.engine() != Engine::Wrk || self.rate_program().is_none() {
+            return Err(WrkError::Config(
+                "Searching the maximum rate needs wrk2, install it or set Wrk::wrk2_program".to_string(),
+            ));
+        }
+        let slo_ms = latency_slo.as_secs_f64() * 1000.0;
+        let meets = |result: &WrkResult| *result.success() && *result.p99_latency_ms() <= slo_ms;
+        let run = |rate: Option<u32>| -> Result<WrkResult> {
+            let mut benchmark = benchmark.clone();
+            benchmark.set_rate(rate);
+            self.bench_collect(&[benchmark])?
+                .pop()
+                .ok_or_else(|| WrkError::Exec("Rate search run produced no result".to_string()))
+        };
+        let mut best = None;
+        let mut high = match benchmark.rate() {
+            Some(rate) => *rate,
+            None => {
+                let probe = run(None)?;
+                if !probe.success() {
+                    return Err(WrkError::Exec(format!("Unthrottled run failed: {}", probe.error())));
+                }
+                probe.requests_sec().ceil() as u32
+            }
+        };
+        let mut low = 0;
+        if benchmark.rate().is_some() {
+            let result = run(Some(high))?;
+            if meets(&result) {
+                low = high;
+                best = Some(result);
+            }
+        }
+        for _ in 0..MAX_RATE_STEPS {
+            if high - low <= (high / 20).max(1) {
+                break;
+            }
+            let rate = low + (high - low) / 2;
+            let result = run(Some(rate))?;
+            debug!(
+                "Rate {} requests/sec: p99 latency {:.2}ms, success {}",
+                rate,
+                result.p99_latency_ms(),
+                result.success()
+            );
+            if meets(&result) {
+                low = rate;
+                best = Some(result);
+            } else {
+                high = rate;
+            }
+        }
+        let mut best =
+            best.ok_or_else(|| WrkError::Stats(format!("No rate meets the p99 latency SLO of {:?}", latency_slo)))?;
+        info!(
+            "Maximum rate meeting the p99 latency SLO of {:?}: {} requests/sec",
+            latency_slo, low
+        );
+        best.set_capacity(Some(low));
+        let mut base = benchmark.clone();
+        base.set_rate(None);
+        best.set_benchmark(Some(base));
+        self.record(&vec![best.clone()])?;
+        Ok(best)
+    }
 
-        let addr = SocketAddr::from(([127, 0, 0, 1], 13734));
-        println!("server listening on {}", addr);
-        axum::Server::bind(&addr).serve(app.into_make_service()).await.unwrap();
+    /// Re-run `benchmarks` whenever any of the watched `paths` changes, IE: after `cargo build`
+    /// replaced the service binary, printing the variance of every configuration against its
+    /// previous successful run.
+    /// Directories are watched recursively and results are never recorded. This function only
+    /// returns on errors.
+    pub fn watch(&self, paths: &[PathBuf], benchmarks: &[Benchmark]) -> Result<()> {
+        let mut previous = Benchmarks::new();
+        let mut seen = watch::latest_modification(paths);
+        loop {
+            info!("Running watch iteration of {} benchmarks", benchmarks.len());
+            let pairs = watch::pair_comparable(&mut previous, &self.bench_collect(benchmarks)?);
+            if pairs.is_empty() {
+                error!("Every benchmark of the watch iteration failed, waiting for the next change");
+            }
+            for (result, old) in pairs {
+                match old {
+                    Some(old) => println!("{}", Variance::new(result, old)),
+                    None => println!(
+                        "Baseline of {}: {:.2} requests/sec",
+                        result.benchmark().as_ref().map_or_else(String::new, Benchmark::name),
+                        result.requests_sec()
+                    ),
+                }
+            }
+            seen = watch::wait_for_change(paths, seen);
+        }
     }
 
-    #[tokio::test]
-    async fn benchmark() {
-        tokio::spawn(server());
-        tokio::time::sleep(Duration::from_secs(1)).await;
-        let client = hyper::Client::new();
+    /// Run `benchmarks` whenever `schedule` matches, turning the crate into a standing
+    /// performance monitor: every run is recorded according to the [`RecordPolicy`], its
+    /// variance against the previous run, or a baseline for the first one, is passed to
+    /// `report` (IE: to post it somewhere) and the history is compacted according to
+    /// [`Schedule::with_retention`]. Failed runs are logged and the daemon waits for the next
+    /// one. This function only returns on errors.
+    #[cfg(feature = "daemon")]
+    pub fn run_scheduled<F: FnMut(&Variance)>(
+        &self,
+        schedule: &crate::Schedule,
+        benchmarks: &[Benchmark],
+        mut report: F,
+    ) -> Result<()> {
+        loop {
+            let next = schedule
+                .next_after(self.now())
+                .ok_or_else(|| WrkError::Config("Schedule never matches, no run left to schedule".to_string()))?;
+            info!("Next scheduled run of {} benchmarks at {}", benchmarks.len(), next);
+            thread::sleep((next - self.now()).to_std().unwrap_or_default());
+            if let Err(e) = self.scheduled_run(schedule, benchmarks, &mut report) {
+                error!("Scheduled run failed, waiting for the next one: {}", e);
+            }
+        }
+    }
 
-        let response = client
-            .request(
-                Request::builder()
-                    .uri("http://127.0.0.1:13734/")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    #[cfg(feature = "daemon")]
+    fn scheduled_run<F: FnMut(&Variance)>(
+        &self,
+        schedule: &crate::Schedule,
+        benchmarks: &[Benchmark],
+        report: &mut F,
+    ) -> Result<()> {
+        // The first run of a new monitor has nothing to compare with.
+        let mut wrk = self.clone();
+        wrk.set_bootstrap_baseline(true);
+        let mut session = BenchSession::new(Arc::new(wrk));
+        session.bench(benchmarks)?;
+        report(&session.variance(HistoryPeriod::Last)?);
+        if let Some(days) = schedule.retention_days() {
+            let removed = self.compact_history(days)?;
+            debug!("Compacted {} history files older than {} days", removed, days);
+        }
+        Ok(())
+    }
 
-        let mut wrk = WrkBuilder::default()
-            .url("http://127.0.0.1:13734".to_string())
-            .build()
-            .unwrap();
-        // wrk.bench_exponential(Some(Duration::from_secs(30))).unwrap();
-        wrk.bench(&vec![BenchmarkBuilder::default()
-            .duration(Duration::from_secs(5))
-            .build()
-            .unwrap()])
-            .unwrap();
-        // println!("{}", wrk.deviation(HistoryPeriod::Hour).unwrap());
-        // wrk.load(HistoryPeriod::Day, false).unwrap();
-        // wrk.plot("Wrk Weeeeeee", Path::new("./some.png"), &wrk.all_benchmarks())
-        // .unwrap();
+    /// Record the given results in the history directory, using the date of the first result.
+    /// Results are never recorded with [`RecordPolicy::Never`].
+    pub fn record(&self, benchmarks: &Benchmarks) -> Result<()> {
+        if *self.record_policy() == RecordPolicy::Never {
+            info!("Record policy is Never, skipping {} results", benchmarks.len());
+            return Ok(());
+        }
+        match benchmarks.first() {
+            Some(first) => self.dump(*first.date(), benchmarks),
+            None => Err(WrkError::History(
+                "Unable to record an empty set of results".to_string(),
+            )),
+        }
+    }
+
+    /// Execute again the benchmarks of a recorded run, tagging the new results as reruns of
+    /// the original one and recording them according to the [`RecordPolicy`].
+    ///
+    /// The Lua scripts archived with the original run are replayed: results get a warning when
+    /// a script is missing, IE: for runs recorded by older versions, and is generated again
+    /// from the current configuration.
+    pub fn rerun<R: Into<StoredRun>>(&self, run: R) -> Result<Benchmarks> {
+        let original = match run.into() {
+            StoredRun::Date(date) => {
+                let (_, format, path) = self
+                    .history_files(None)?
+                    .into_iter()
+                    .find(|(file_date, _, _)| file_date.timestamp() == date.timestamp())
+                    .ok_or_else(|| WrkError::History(format!("No run recorded at {}", date)))?;
+                self.read(&path, format)?
+            }
+            StoredRun::File(path) => {
+                let format = HistoryFormat::from_path(&path)
+                    .ok_or_else(|| WrkError::History(format!("Unknown history format of {}", path.display())))?;
+                self.read(&path, format)?
+            }
+        };
+        let original_date = *original
+            .first()
+            .ok_or_else(|| WrkError::History("Unable to rerun an empty run".to_string()))?
+            .date();
+        let mut benchmarks: Vec<Benchmark> = Vec::new();
+        let mut replay = self.clone();
+        let mut regenerated = Vec::new();
+        for result in original.iter() {
+            let benchmark = match result.benchmark() {
+                Some(benchmark) if !benchmarks.contains(benchmark) => benchmark,
+                _ => continue,
+            };
+            benchmarks.push(benchmark.clone());
+            match result.script_sha256().as_ref().map(|sha| self.replay_script(sha)) {
+                Some(Ok(script)) => replay.replayed_scripts.push((benchmark.clone(), script)),
+                Some(Err(e)) => {
+                    warn!(
+                        "Unable to replay the Lua script of benchmark {}: {}",
+                        benchmark.key(),
+                        e
+                    );
+                    regenerated.push(benchmark.clone());
+                }
+                None => regenerated.push(benchmark.clone()),
+            }
+        }
+        if benchmarks.is_empty() {
+            return Err(WrkError::History(format!(
+                "Run of {} has no benchmark configuration to rerun",
+                original_date
+            )));
+        }
+        let date = self.now();
+        let mut results = replay.run(&benchmarks, date, Uuid::new_v4(), |_| Ok(()))?;
+        for result in results.iter_mut() {
+            *result.rerun_of_mut() = Some(original_date);
+            if result.benchmark().as_ref().is_some_and(|b| regenerated.contains(b)) {
+                let warning = format!(
+                    "Lua script of the original run of {} is not archived, generated again from the \
+                     current configuration",
+                    original_date
+                );
+                warn!("{}", warning);
+                result.warnings_mut().push(warning);
+            }
+        }
+        match self.record_policy() {
+            RecordPolicy::Always => self.dump(date, &results)?,
+            RecordPolicy::OnSuccess if results.iter().all(|r| *r.success()) => self.dump(date, &results)?,
+            policy => info!("Not recording rerun, record policy is {:?}", policy),
+        }
+        Ok(results)
+    }
+
+    /// Store the Lua `script` with SHA-256 `sha` in the archive of the history, once.
+    fn archive_script(&self, sha: &str, script: &Path) -> Result<()> {
+        let path = self
+            .history_path()
+            .join(SCRIPT_ARCHIVE_DIR)
+            .join(format!("{}.lua", sha));
+        if !self.history_store.exists(&path) {
+            self.history_store.write(&path, &fs::read(script)?)?;
+        }
+        Ok(())
+    }
+
+    /// Copy the archived Lua script with SHA-256 `sha` where wrk can read it, checking its hash.
+    fn replay_script(&self, sha: &str) -> Result<PathBuf> {
+        let archived = self
+            .history_path()
+            .join(SCRIPT_ARCHIVE_DIR)
+            .join(format!("{}.lua", sha));
+        let script = self.history_store.read(&archived)?;
+        if format!("{:x}", Sha256::digest(&script)) != sha {
+            return Err(WrkError::History(format!(
+                "Archived script {} does not match its hash",
+                archived.display()
+            )));
+        }
+        let dir = std::env::temp_dir().join("wrk-api-bench").join(SCRIPT_ARCHIVE_DIR);
+        let path = dir.join(format!("{}.lua", sha));
+        if !path.exists() {
+            fs::create_dir_all(&dir)?;
+            let mut file = tempfile::Builder::new().prefix(".script.").tempfile_in(&dir)?;
+            file.write_all(&script)?;
+            file.persist(&path)?;
+        }
+        Ok(path)
+    }
+
+    /// Environment of the wrk process, with `LUA_PATH` pointing to the directory of the user
+    /// script and to the search paths, ahead of any user provided `LUA_PATH`. Paths are made
+    /// absolute, as the script runs from a temporary file. The values of redacted headers are
+    /// placeholders, see [`Wrk::secret_env`].
+    fn command_env(&self, benchmark: &Benchmark) -> BTreeMap<String, String> {
+        let mut env = self.env().clone();
+        for name in self.secret_env(benchmark).into_keys() {
+            env.insert(name, crate::headers::REDACTED.to_string());
+        }
+        // Localized number formatting, IE: comma decimal separators, breaks the output parsing.
+        env.insert("LC_ALL".to_string(), "C".to_string());
+        let mut dirs: Vec<PathBuf> = self
+            .user_script()
+            .iter()
+            .filter_map(|script| script.parent())
+            .map(|dir| {
+                if dir.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    dir
+                }
+            })
+            .map(Path::to_path_buf)
+            .collect();
+        dirs.extend(self.lua_search_paths().iter().cloned());
+        if dirs.is_empty() {
+            return env;
+        }
+        let mut lua_path: Vec<String> = dirs
+            .iter()
+            .map(|dir| fs::canonicalize(dir).unwrap_or_else(|_| dir.clone()))
+            .flat_map(|dir| {
+                [
+                    dir.join("?.lua").display().to_string(),
+                    dir.join("?").join("init.lua").display().to_string(),
+                ]
+            })
+            .collect();
+        // An empty entry, IE: the trailing `;;`, stands for the default Lua path.
+        lua_path.push(env.get("LUA_PATH").cloned().unwrap_or_else(|| ";".to_string()));
+        env.insert("LUA_PATH".to_string(), lua_path.join(";"));
+        env
+    }
+
+    /// Environment variables passing the values of the redacted headers of `benchmark` to the
+    /// Lua script.
+    fn secret_env(&self, benchmark: &Benchmark) -> BTreeMap<String, String> {
+        benchmark
+            .headers_or(self.headers())
+            .into_iter()
+            .filter(|(name, _)| crate::headers::is_redacted(name, self.redacted_headers()))
+            .map(|(name, value)| (crate::headers::secret_env(&name), value))
+            .collect()
+    }
+
+    /// Command run by the configured runner, the only one holding the values of the redacted
+    /// headers.
+    fn command_spec(&self, benchmark: &Benchmark, url: &Url, script: &Path) -> Result<CommandSpec> {
+        let mut env = self.command_env(benchmark);
+        env.extend(self.secret_env(benchmark));
+        Ok(CommandSpec::new(
+            &self.program(benchmark),
+            self.wrk_args(benchmark, url, script)?,
+            env,
+            fs::read_to_string(script)?,
+        ))
+    }
+
+    /// wrk2 program running constant rate benchmarks, `None` when it is not installed.
+    fn rate_program(&self) -> Option<String> {
+        self.wrk2_program().clone().or_else(|| {
+            self.detected_wrk2
+                .get_or_init(|| wrk2::detect(&**self.runner()))
+                .clone()
+        })
+    }
+
+    /// Program running `benchmark`: wrk2 for the constant rate benchmarks of [`Engine::Wrk`].
+    fn program(&self, benchmark: &Benchmark) -> String {
+        match self.engine() {
+            Engine::Wrk if benchmark.rate().is_some() => self.rate_program().unwrap_or_else(|| "wrk".to_string()),
+            engine => engine.program().to_string(),
+        }
+    }
+
+    /// First line of the version printed by the program running `benchmark`, run through the
+    /// configured [`ProcessRunner`]. `None` when it can not be run.
+    #[cfg(feature = "bundle")]
+    pub(crate) fn program_version(&self, benchmark: &Benchmark) -> Option<String> {
+        #[cfg(feature = "native")]
+        if *self.engine() == Engine::Native {
+            return Some(format!("{} {}", native::PROGRAM, env!("CARGO_PKG_VERSION")));
+        }
+        let spec = CommandSpec::new(
+            &self.program(benchmark),
+            vec!["-v".to_string()],
+            BTreeMap::new(),
+            String::new(),
+        );
+        let output = self.runner().run(&spec).ok()?;
+        output
+            .stdout()
+            .lines()
+            .chain(output.stderr().lines())
+            .find(|line| !line.trim().is_empty())
+            .map(str::to_string)
+    }
+
+    /// Command which would run `benchmark`, with the Lua script expected in
+    /// `wrk-api-bench.lua` of the working directory. The `WRK_API_BENCH_HEADER_*` variables of
+    /// redacted headers hold placeholders the executor must replace with the real values.
+    pub fn command_for(&self, benchmark: &Benchmark) -> Result<CommandSpec> {
+        let url = Url::parse(self.url())?;
+        let script = self.script(benchmark, &url)?;
+        Ok(CommandSpec::new(
+            &self.program(benchmark),
+            self.wrk_args(benchmark, &url, Path::new(command::SCRIPT_PATH))?,
+            self.command_env(benchmark),
+            fs::read_to_string(script)?,
+        ))
+    }
+
+    /// Rendered Lua script of `benchmark`, cached in the history directory by a hash of its
+    /// configuration, so repeated runs reuse the same stable path. Scripts replayed by
+    /// [`Wrk::rerun`] are used as they are.
+    fn script(&self, benchmark: &Benchmark, url: &Url) -> Result<PathBuf> {
+        if let Some((_, script)) = self.replayed_scripts.iter().find(|(replayed, _)| replayed == benchmark) {
+            return Ok(script.clone());
+        }
+        let method = benchmark.method_or(self.method());
+        let headers = benchmark.headers_or(self.headers());
+        let body = benchmark.body_or(self.body());
+        let mut hasher = Sha256::new();
+        for part in [env!("CARGO_PKG_VERSION"), method.as_str(), url.path(), body] {
+            hasher.update(part);
+            hasher.update([0]);
+        }
+        hasher.update(serde_json::to_vec(&crate::headers::redact(
+            &headers,
+            self.redacted_headers(),
+        ))?);
+        hasher.update(serde_json::to_vec(self.redacted_headers())?);
+        hasher.update(serde_json::to_vec(self.multipart())?);
+        hasher.update(serde_json::to_vec(self.response_checks())?);
+        hasher.update(serde_json::to_vec(self.success_statuses())?);
+        hasher.update([self.records_timeseries() as u8, *self.capture_server() as u8]);
+        hasher.update(serde_json::to_vec(self.version_header())?);
+        if let Some(user_script) = self.user_script() {
+            // A missing user script is reported by the rendering.
+            hasher.update(fs::read(user_script).unwrap_or_default());
+        }
+        let dir = self.scripts_dir();
+        let path = dir.join(format!("{:x}.lua", hasher.finalize()));
+        if !path.exists() {
+            fs::create_dir_all(&dir)?;
+            let mut file = tempfile::Builder::new().prefix(".script.").tempfile_in(&dir)?;
+            LuaScript::render_request(
+                &mut file,
+                self.user_script().as_ref(),
+                url.path(),
+                method,
+                &headers,
+                body,
+                ScriptOptions {
+                    multipart: self.multipart().as_ref(),
+                    response_checks: self.response_checks(),
+                    success_statuses: self.success_statuses(),
+                    timeseries: self.records_timeseries(),
+                    capture_server: *self.capture_server(),
+                    version_header: self.version_header().as_deref(),
+                    redacted_headers: self.redacted_headers(),
+                },
+            )?;
+            file.persist(&path)?;
+        }
+        Ok(path)
+    }
+
+    /// Slug of the benchmarked URL followed by a short hash of it, IE: `localhost-1234-some-uri-`
+    /// and 8 hexadecimal digits for `http://localhost:1234/some/uri`, so URLs with the same slug,
+    /// IE: `/b-c` and `/b/c`, never share a namespace.
+    fn url_slug(&self) -> String {
+        let target = self.url().split("://").last().unwrap_or_default();
+        let hash = format!("{:x}", Sha256::digest(target));
+        format!("{}-{}", slug(target), &hash[..8])
+    }
+
+    /// Directories holding the history of this configuration before it was namespaced by
+    /// project or URL: the slug of the URL without hash, then the history directory itself.
+    fn legacy_history_paths(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        if self.project().is_none() {
+            paths.push(
+                self.history_dir()
+                    .join(slug(self.url().split("://").last().unwrap_or_default())),
+            );
+        }
+        paths.push(self.history_dir().clone());
+        paths
+    }
+
+    /// Directory where the history of this project or target URL is stored.
+    pub fn history_path(&self) -> PathBuf {
+        self.history_dir().join(self.history_namespace())
+    }
+
+    /// Name of the history subdirectory: the project or a slug of the URL.
+    pub(crate) fn history_namespace(&self) -> String {
+        match self.project() {
+            Some(project) => project.clone(),
+            None => self.url_slug(),
+        }
+    }
+
+    /// Start a service through `spawn` on a listener bound to a free port, substituting the
+    /// port to the `{port}` placeholder of the URL, IE: `http://127.0.0.1:{port}/api`. The
+    /// service serves the listener, IE: with `axum::Server::from_tcp`, so no other process can
+    /// take the port in between. The returned target waits for the service to accept
+    /// connections and tears it down when dropped, so parallel test runs never collide on a
+    /// fixed port.
+    pub fn with_dynamic_target<T, F>(&self, spawn: F) -> Result<DynamicTarget<T>>
+    where
+        T: Teardown,
+        F: FnOnce(TcpListener) -> T,
+    {
+        DynamicTarget::spawn(self, spawn)
+    }
+
+    /// Store `benchmarks` in the history file of `date`, and their cold starts in the one of
+    /// the cold start series.
+    pub(crate) fn dump(&self, date: DateTime<Utc>, benchmarks: &Benchmarks) -> Result<()> {
+        let (cold, warm): (Benchmarks, Benchmarks) = benchmarks.iter().cloned().partition(|r| *r.cold_start());
+        if !cold.is_empty() {
+            self.cold_start_series().write_history(date, &cold)?;
+        }
+        if !warm.is_empty() || cold.is_empty() {
+            self.write_history(date, &warm)?;
+        }
+        Ok(())
+    }
+
+    fn write_history(&self, date: DateTime<Utc>, benchmarks: &Benchmarks) -> Result<()> {
+        let key = HistoryKey::from_env()?;
+        let path = self.history_file(&date, key.is_some());
+        println!("Writing current benchmark to {}", path.display());
+        let mut data = Vec::new();
+        self.history_format().write(&mut data, &self.redact(benchmarks))?;
+        if let Some(key) = key {
+            data = key.seal(&data)?;
+        }
+        self.history_store.write(&path, &data)?;
+        Ok(())
+    }
+
+    /// Configuration whose history is the cold start series, stored in the `cold-start`
+    /// subdirectory of the history.
+    fn cold_start_series(&self) -> Self {
+        let mut wrk = self.clone();
+        wrk.set_project(Some(format!("{}/{}", self.history_namespace(), COLD_START_DIR)));
+        wrk
+    }
+
+    /// Results recorded in the given period as [`WrkResult::cold_start`], kept apart from the
+    /// steady-state history used by the variance, plots and reports.
+    pub fn cold_start_history(&self, period: HistoryPeriod) -> Result<Benchmarks> {
+        self.cold_start_series().history(&period, None)
+    }
+
+    /// Copy of `benchmarks` with the [`Wrk::redacted_headers`] of their configuration redacted.
+    pub(crate) fn redact(&self, benchmarks: &Benchmarks) -> Benchmarks {
+        benchmarks
+            .iter()
+            .cloned()
+            .map(|mut result| {
+                if let Some(headers) = result.benchmark_mut().as_mut().and_then(|b| b.headers_mut().as_mut()) {
+                    *headers = crate::headers::redact(headers, self.redacted_headers());
+                }
+                result
+            })
+            .collect()
+    }
+
+    /// Path of the history file of the run started at `date`, with the `.enc` extension when
+    /// `encrypted`.
+    pub(crate) fn history_file(&self, date: &DateTime<Utc>, encrypted: bool) -> PathBuf {
+        let mut filename = history::filename(date, *self.history_format());
+        if encrypted {
+            filename = format!("{}.{}", filename, encryption::EXTENSION);
+        }
+        self.history_path().join(filename)
+    }
+
+    /// Directory caching the rendered Lua scripts, which wrk reads from disk even when the
+    /// history is not stored there or not written at all.
+    fn scripts_dir(&self) -> PathBuf {
+        if self.history_store.is_local() && self.writes_history() {
+            self.history_path().join(SCRIPTS_DIR)
+        } else {
+            std::env::temp_dir().join("wrk-api-bench").join(SCRIPTS_DIR)
+        }
+    }
+
+    /// Read the history file at `path`, decrypting it when encrypted.
+    pub(crate) fn read(&self, path: &Path, format: HistoryFormat) -> Result<Benchmarks> {
+        format.read(&encryption::decode(path, self.history_store.read(path)?)?[..])
+    }
+
+    /// Current time according to the clock of this configuration.
+    pub(crate) fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// History files sorted by the date embedded in their name, excluding the `current` run.
+    pub(crate) fn history_files(
+        &self,
+        current: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<(DateTime<FixedOffset>, HistoryFormat, PathBuf)>> {
+        let files = self.history_files_in(&self.history_path(), current)?;
+        if !files.is_empty() {
+            return Ok(files);
+        }
+        // Fall back to the history recorded before namespacing, until this namespace has its own.
+        for dir in self.legacy_history_paths() {
+            let files = self.history_files_in(&dir, current)?;
+            if !files.is_empty() {
+                info!(
+                    "Reading the history from its legacy location {}, new runs are recorded in {}",
+                    dir.display(),
+                    self.history_path().display()
+                );
+                return Ok(files);
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    /// History files in `dir`, oldest first, excluding the `current` run.
+    fn history_files_in(
+        &self,
+        dir: &Path,
+        current: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<(DateTime<FixedOffset>, HistoryFormat, PathBuf)>> {
+        let mut files = Vec::new();
+        for path in self.history_store.list(dir)? {
+            match history::parse_filename(&path) {
+                Some((date, _)) if Some(date.timestamp()) == current.map(|c| c.timestamp()) => continue,
+                Some((date, format)) => files.push((date, format, path)),
+                None if path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(history::PREFIX)) =>
+                {
+                    warn!("Ignoring history file {} without a valid date", path.display())
+                }
+                None => {}
+            }
+        }
+        files.sort_by_key(|(date, _, _)| *date);
+        Ok(files)
+    }
+
+    /// Replace the history files of the days ending more than `days` days ago with one daily
+    /// rollup file, holding the best and the median result of every benchmark configuration
+    /// tagged with their [`WrkResult::rollup`], keeping long-term trends small while preserving
+    /// recent detail. Failed runs and time series are dropped. Returns the number of removed
+    /// files.
+    pub fn compact_history(&self, days: u32) -> Result<usize> {
+        rollup::compact(self, days, self.now())
+    }
+
+    /// Export the latest recorded run to a zip archive at `path`, holding the configuration,
+    /// the generated Lua scripts, the benchmark plan, environment metadata and the results.
+    /// Processors are not part of the bundle and the [`Wrk::redacted_headers`] are redacted.
+    #[cfg(feature = "bundle")]
+    pub fn export_bundle(&self, path: &Path) -> Result<()> {
+        bundle::export(self, path, &self.history(&HistoryPeriod::Last, None)?)
+    }
+
+    /// Export the history files recorded in `period` to a zip archive at `path`, to be merged
+    /// into the history of another machine with [`Wrk::import_history`].
+    #[cfg(feature = "bundle")]
+    pub fn export_history(&self, period: HistoryPeriod, path: &Path) -> Result<()> {
+        bundle::export_history(self, &period, path)
+    }
+
+    /// Merge the history archive at `path`, written by [`Wrk::export_history`], into the local
+    /// history, skipping the runs already stored. Returns the number of imported results.
+    #[cfg(feature = "bundle")]
+    pub fn import_history(&self, path: &Path) -> Result<usize> {
+        bundle::import_history(self, path)
+    }
+
+    /// Restore the configuration and the benchmark plan of a bundle written by
+    /// [`Wrk::export_bundle`], ready to be run again.
+    #[cfg(feature = "bundle")]
+    pub fn from_bundle(path: &Path) -> Result<(Self, Vec<Benchmark>)> {
+        bundle::import(path)
+    }
+
+    /// Run the same `benchmarks` against every `(name, url)` target, IE: axum and actix builds
+    /// of the same API. Every target is recorded in its own history namespace, named after the
+    /// target, according to the [`crate::RecordPolicy`].
+    pub fn matrix_targets(&self, targets: Vec<(String, String)>, benchmarks: &[Benchmark]) -> Result<Comparison> {
+        let mut results = Vec::new();
+        for (name, url) in targets {
+            let mut session = BenchSession::from(self.target(&name, url));
+            // A failing target is reported as failed in the comparison instead of aborting it.
+            if let Err(e) = session.bench(benchmarks) {
+                error!("Benchmarks of target {} failed: {}", name, e);
+            }
+            results.push((name, session.benchmarks().clone()));
+        }
+        Ok(Comparison::new(results))
+    }
+
+    /// Compare two `(name, url)` targets alternating their runs, A, B, A, B, `rounds` times for
+    /// every benchmark, so noise on the host affects both alike. See [`Comparison::paired`] for
+    /// the paired statistics per configuration. Targets are recorded as in
+    /// [`Wrk::matrix_targets`].
+    pub fn compare_interleaved(
+        &self,
+        a: (String, String),
+        b: (String, String),
+        benchmarks: &[Benchmark],
+        rounds: usize,
+    ) -> Result<Comparison> {
+        let mut session_a = BenchSession::from(self.target(&a.0, a.1));
+        let mut session_b = BenchSession::from(self.target(&b.0, b.1));
+        for benchmark in benchmarks {
+            for round in 1..=rounds {
+                // A failing round of a target is left out instead of aborting the comparison.
+                for (name, session) in [(&a.0, &mut session_a), (&b.0, &mut session_b)] {
+                    if let Err(e) = session.bench(std::slice::from_ref(benchmark)) {
+                        error!(
+                            "Round {} of {} against target {} failed: {}",
+                            round,
+                            benchmark.key(),
+                            name,
+                            e
+                        );
+                    }
+                }
+            }
+        }
+        Ok(Comparison::interleaved(vec![
+            (a.0, session_a.benchmarks().clone()),
+            (b.0, session_b.benchmarks().clone()),
+        ]))
+    }
+
+    /// Run the same `benchmarks` once per `Accept` header in `content_types`, IE:
+    /// `application/json` against `application/msgpack`, to compare the cost of the
+    /// serialization formats of the same endpoint. Every content type is recorded in its own
+    /// history namespace, named after a slug of the content type, according to the
+    /// [`crate::RecordPolicy`]. Benchmark headers still override the `Accept` header.
+    pub fn compare_content_types(&self, content_types: &[&str], benchmarks: &[Benchmark]) -> Result<Comparison> {
+        let mut results = Vec::new();
+        for content_type in content_types {
+            let mut wrk = self.target(&format!("accept-{}", slug(content_type)), self.url().clone());
+            wrk.headers_mut().retain(|name, _| !name.eq_ignore_ascii_case("accept"));
+            wrk.headers_mut().insert("Accept".to_string(), content_type.to_string());
+            let mut session = BenchSession::from(wrk);
+            // A failing content type is reported as failed in the comparison instead of aborting it.
+            if let Err(e) = session.bench(benchmarks) {
+                error!("Benchmarks of content type {} failed: {}", content_type, e);
+            }
+            results.push((content_type.to_string(), session.benchmarks().clone()));
+        }
+        Ok(Comparison::new(results))
+    }
+
+    /// Configuration benchmarking the target `url`, recorded in the history namespace named
+    /// after a slug of `name`, so it never escapes the history directory.
+    fn target(&self, name: &str, url: String) -> Self {
+        let mut wrk = self.clone();
+        wrk.set_url(url);
+        let name = Some(slug(name))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| wrk.url_slug());
+        wrk.set_project(Some(match self.project() {
+            Some(project) => format!("{}-{}", project, name),
+            None => name,
+        }));
+        wrk
+    }
+
+    /// Engine commands executed for this project or target URL, oldest first.
+    pub fn audit_log(&self) -> Result<Vec<AuditEntry>> {
+        audit::read(&*self.history_store, &self.history_path())
+    }
+
+    /// Load the historical benchmarks recorded in the given period, excluding the `current` run.
+    pub(crate) fn history(&self, period: &HistoryPeriod, current: Option<&DateTime<Utc>>) -> Result<Benchmarks> {
+        let mut files = self.history_files(current)?;
+        let mut history = Benchmarks::new();
+        if *period == HistoryPeriod::Last {
+            let (_, format, path) = files
+                .pop()
+                .ok_or_else(|| WrkError::History("Unable to load history with a single measurement".to_string()))?;
+            history = self.read(&path, format)?;
+        } else {
+            for (date, format, path) in files {
+                if date >= period.since(self.now()) {
+                    history.append(&mut self.read(&path, format)?);
+                }
+            }
+        }
+        Ok(dedup(history))
+    }
+
+    /// Historical benchmarks recorded in `period` grouped by their [`WrkResult::app_version`],
+    /// or their [`WrkResult::server`] when no version was captured. Results without either are
+    /// grouped under `unknown`.
+    pub fn history_by_version(&self, period: HistoryPeriod) -> Result<BTreeMap<String, Benchmarks>> {
+        let mut groups: BTreeMap<String, Benchmarks> = BTreeMap::new();
+        for result in self.history(&period, None)? {
+            let version = result
+                .app_version()
+                .clone()
+                .or_else(|| result.server().clone())
+                .unwrap_or_else(|| "unknown".to_string());
+            groups.entry(version).or_default().push(result);
+        }
+        Ok(groups)
+    }
+
+    /// Compare the best result of the latest recorded run with the best comparable result of
+    /// each of the previous `n` runs recorded in `period`, and with their mean.
+    /// [`HistoryPeriod::Last`] only considers the previous run.
+    pub fn variance_window(&self, period: HistoryPeriod, n: usize) -> Result<VarianceWindow> {
+        let mut files = self.history_files(None)?;
+        let (_, format, path) = files
+            .pop()
+            .ok_or_else(|| WrkError::History("No recorded run to compare".to_string()))?;
+        let new = self.best_benchmark(&self.read(&path, format)?)?;
+        let benchmark = new
+            .benchmark()
+            .clone()
+            .ok_or_else(|| WrkError::Stats("Latest run has no benchmark configuration to compare with".to_string()))?;
+        let (oldest, n) = match period {
+            HistoryPeriod::Last => (None, 1),
+            period => (Some(period.since(self.now())), n),
+        };
+        let mut runs = Vec::new();
+        for (date, format, path) in files.into_iter().rev() {
+            if runs.len() == n || oldest.is_some_and(|oldest| date < oldest) {
+                break;
+            }
+            let comparable: Benchmarks = self
+                .read(&path, format)?
+                .into_iter()
+                .filter(|r| r.benchmark().as_ref().is_some_and(|b| b.is_comparable(&benchmark)))
+                .collect();
+            match self.best_benchmark(&comparable) {
+                Ok(best) => runs.push(best),
+                Err(_) => debug!("Skipping run {} without comparable results", path.display()),
+            }
+        }
+        if runs.is_empty() {
+            return Err(WrkError::History(format!(
+                "No previous run comparable with {} recorded",
+                benchmark.name()
+            )));
+        }
+        runs.reverse();
+        Ok(VarianceWindow::new(new, runs))
+    }
+
+    /// Compare the best result of the latest recorded run with the best comparable result
+    /// recorded before it in each of `periods`, IE: `[Hour, Day, Week]`, in a single report.
+    pub fn variance_multi(&self, periods: &[HistoryPeriod]) -> Result<MultiVariance> {
+        let files = self.history_files(None)?;
+        let (date, format, path) = files
+            .last()
+            .ok_or_else(|| WrkError::History("No recorded run to compare".to_string()))?;
+        let new = self.best_benchmark(&self.read(path, *format)?)?;
+        let benchmark = new
+            .benchmark()
+            .clone()
+            .ok_or_else(|| WrkError::Stats("Latest run has no benchmark configuration to compare with".to_string()))?;
+        let current = date.to_utc();
+        let mut results = Vec::new();
+        for period in periods {
+            let comparable: Benchmarks = match self.history(period, Some(&current)) {
+                Ok(history) => history
+                    .into_iter()
+                    .filter(|r| r.benchmark().as_ref().is_some_and(|b| b.is_comparable(&benchmark)))
+                    .collect(),
+                Err(WrkError::History(_)) => Benchmarks::new(),
+                Err(e) => return Err(e),
+            };
+            results.push((period.clone(), self.best_benchmark(&comparable).ok()));
+        }
+        Ok(MultiVariance::new(new, results))
+    }
+
+    /// Best composite score of every run recorded in the given period, oldest first, to trend
+    /// the score over time. Runs without a score are left out.
+    pub fn score_trend(&self, period: HistoryPeriod) -> Result<Vec<(DateTime<Utc>, f64)>> {
+        let mut trend: Vec<(DateTime<Utc>, f64)> = Vec::new();
+        for result in self.history(&period, None)? {
+            let Some(score) = *result.score() else {
+                continue;
+            };
+            match trend.last_mut() {
+                Some((date, best)) if date == result.date() => *best = best.max(score),
+                _ => trend.push((*result.date(), score)),
+            }
+        }
+        Ok(trend)
+    }
+
+    /// Text chart of the requests/sec of the best result of every run recorded in the given
+    /// period, oldest first, to embed in GitHub job summaries.
+    pub fn history_chart(&self, period: HistoryPeriod, chart: TextChart) -> Result<String> {
+        let mut runs: BTreeMap<DateTime<Utc>, Benchmarks> = BTreeMap::new();
+        for result in self.history(&period, None)? {
+            runs.entry(*result.date()).or_default().push(result);
+        }
+        let best: Benchmarks = runs
+            .values()
+            .filter_map(|results| self.best_benchmark(results).ok())
+            .collect();
+        Ok(chart.render(&format!("Requests/sec of {}", self.history_namespace()), &best))
+    }
+
+    /// Best historical benchmark recorded in the given period.
+    pub fn best_of(&self, period: HistoryPeriod) -> Result<WrkResult> {
+        self.best_benchmark(&self.history(&period, None)?)
+    }
+
+    /// Best historical benchmark recorded in the given period for every benchmark
+    /// configuration, indexed by [`Benchmark::key`].
+    pub fn best_per_config(&self, period: HistoryPeriod) -> Result<HashMap<String, WrkResult>> {
+        let mut configs: HashMap<String, Benchmarks> = HashMap::new();
+        for result in self.history(&period, None)? {
+            match result.benchmark() {
+                Some(benchmark) => configs.entry(benchmark.key()).or_default().push(result),
+                None => debug!("Skipping historical run without benchmark configuration"),
+            }
+        }
+        configs
+            .into_iter()
+            .map(|(key, benchmarks)| Ok((key, self.best_benchmark(&benchmarks)?)))
+            .collect()
+    }
+
+    /// Select the best result with `comparator`, which orders results so that the greatest
+    /// one is the best, instead of the built-in ordering by requests/sec, successes, requests
+    /// and transferred data. Applies to every historical and current "best" lookup.
+    pub fn set_best_comparator<F>(&mut self, comparator: F) -> &mut Self
+    where
+        F: Fn(&WrkResult, &WrkResult) -> Ordering + Send + Sync + 'static,
+    {
+        self.best_comparator = Some(BestComparator(Arc::new(comparator)));
+        self
+    }
+
+    pub(crate) fn best_benchmark(&self, benchmarks: &Benchmarks) -> Result<WrkResult> {
+        let successful = benchmarks.iter().filter(|v| *v.success());
+        let best = match &self.best_comparator {
+            Some(BestComparator(comparator)) => successful.max_by(|a, b| comparator(a, b)),
+            None => successful.max_by(Self::best_order),
+        };
+        best.cloned().ok_or_else(|| {
+            WrkError::Stats(format!(
+                "Unable to calculate best in a set of {} elements",
+                benchmarks.len()
+            ))
+        })
+    }
+
+    /// Built-in ordering of results, by requests/sec, successes, requests and transferred data.
+    fn best_order(a: &&WrkResult, b: &&WrkResult) -> Ordering {
+        (*a.requests_sec() as i64)
+            .cmp(&(*b.requests_sec() as i64))
+            .then((*a.successes() as i64).cmp(&(*b.successes() as i64)))
+            .then((*a.requests() as i64).cmp(&(*b.requests() as i64)))
+            .then((*a.transfer_mb() as i64).cmp(&(*b.transfer_mb() as i64)))
+    }
+
+    /// Plot the given benchmarks to `output`.
+    #[cfg(feature = "plot")]
+    pub fn plot(&self, title: &str, output: &Path, benchmarks: &Benchmarks) -> Result<()> {
+        crate::Gnuplot::new(title, output).plot(benchmarks)
+    }
+
+    /// Plot the p50, p90 and p99 latency bands over time of every benchmark configuration in
+    /// `benchmarks` to `output`, as a PNG image.
+    #[cfg(feature = "plot")]
+    pub fn plot_percentiles(&self, title: &str, output: &Path, benchmarks: &Benchmarks) -> Result<()> {
+        crate::Gnuplot::new(title, output).percentiles(benchmarks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, net::SocketAddr, time::Duration};
+
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{benchmark::BenchmarkBuilder, test_utils::result_builder, BenchSession};
+    use axum::{routing::get, Router};
+    use http::Request;
+    use hyper::Body;
+
+    async fn server() {
+        let app = Router::new().route("/", get(|| async { "Hello, world!" }));
+
+        let addr = SocketAddr::from(([127, 0, 0, 1], 13734));
+        println!("server listening on {}", addr);
+        axum::Server::bind(&addr).serve(app.into_make_service()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn benchmark() {
+        tokio::spawn(server());
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let client = hyper::Client::new();
+
+        let response = client
+            .request(
+                Request::builder()
+                    .uri("http://127.0.0.1:13734/")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let _body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13734".to_string())
+            .build()
+            .unwrap();
+        let mut session = BenchSession::new(Arc::new(wrk));
+        // session.bench_exponential(Some(Duration::from_secs(30))).unwrap();
+        session
+            .bench(&[BenchmarkBuilder::default()
+                .duration(Duration::from_secs(5))
+                .build()
+                .unwrap()])
+            .unwrap();
+        // println!("{}", session.variance(HistoryPeriod::Hour).unwrap());
+        // wrk.plot("Wrk Weeeeeee", Path::new("./some.png"), &session.all_benchmarks())
+        // .unwrap();
+    }
+
+    #[test]
+    fn history_namespace() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13734/some/uri".to_string())
+            .build()
+            .unwrap();
+        let namespace = wrk.history_namespace();
+        assert!(namespace.starts_with("127-0-0-1-13734-some-uri-"), "{}", namespace);
+        assert_eq!(wrk.history_path(), wrk.history_dir().join(&namespace));
+        let mut other = wrk.clone();
+        other.set_url("http://127.0.0.1:13734/some-uri".to_string());
+        assert_ne!(other.history_namespace(), namespace);
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13734/some/uri".to_string())
+            .project("api".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(wrk.history_path(), wrk.history_dir().join("api"));
+    }
+
+    #[test]
+    fn legacy_history() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13734/api".to_string())
+            .history_store(HistoryStore::in_memory())
+            .build()
+            .unwrap();
+        let result = |requests_sec| result_builder(requests_sec, 0.0).build().unwrap();
+        let mut legacy = wrk.clone();
+        legacy.set_project(Some(String::new()));
+        let date = wrk.now() - ChronoDuration::hours(1);
+        legacy.dump(date, &vec![result(10.0)]).unwrap();
+        assert_eq!(
+            *wrk.history(&HistoryPeriod::Last, None).unwrap()[0].requests_sec(),
+            10.0
+        );
+
+        wrk.dump(wrk.now(), &vec![result(20.0)]).unwrap();
+        assert_eq!(
+            *wrk.history(&HistoryPeriod::Last, None).unwrap()[0].requests_sec(),
+            20.0
+        );
+        let now = wrk.now();
+        assert_eq!(
+            *wrk.history(&HistoryPeriod::Last, Some(&now)).unwrap()[0].requests_sec(),
+            10.0
+        );
+    }
+
+    #[test]
+    fn rerun() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13736".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .record_policy(RecordPolicy::Never)
+            .build()
+            .unwrap();
+        let date = Utc::now() - ChronoDuration::minutes(5);
+        let benchmark = BenchmarkBuilder::default()
+            .threads(1)
+            .connections(1)
+            .duration(Duration::from_secs(1))
+            .build()
+            .unwrap();
+        let result = crate::WrkResultBuilder::default()
+            .date(date)
+            .benchmark(benchmark.clone())
+            .build()
+            .unwrap();
+        wrk.dump(date, &vec![result.clone(), result]).unwrap();
+        let results = wrk.rerun(date).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(*results[0].benchmark(), Some(benchmark));
+        assert_eq!(results[0].rerun_of().map(|d| d.timestamp()), Some(date.timestamp()));
+        assert!(results[0].warnings()[0].contains("not archived"));
+        assert!(wrk.rerun(date - ChronoDuration::minutes(1)).is_err());
+    }
+
+    #[test]
+    fn command_for() {
+        let env = BTreeMap::from([("LUA_PATH".to_string(), "./lua/?.lua".to_string())]);
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13734/api".to_string())
+            .env(env.clone())
+            .build()
+            .unwrap();
+        let spec = wrk.command_for(&Benchmark::new(2, 16, 5)).unwrap();
+        let mut expected = env.clone();
+        expected.insert("LC_ALL".to_string(), "C".to_string());
+        assert_eq!(spec.env(), &expected);
+        assert_eq!(spec.program(), "wrk");
+        assert_eq!(
+            spec.args(),
+            &[
+                "-t",
+                "2",
+                "-c",
+                "16",
+                "-d",
+                "5s",
+                "--timeout",
+                "1s",
+                "--latency",
+                "-s",
+                "wrk-api-bench.lua",
+                "http://127.0.0.1:13734/api"
+            ]
+        );
+        assert!(spec.script().contains("/api"));
+    }
+
+    #[test]
+    fn wrk2_program() {
+        let mut wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13734/api".to_string())
+            .runner(RateRunner)
+            .build()
+            .unwrap();
+        let mut constant = Benchmark::new(2, 16, 5);
+        constant.set_rate(Some(500));
+        let spec = wrk.command_for(&constant).unwrap();
+        assert_eq!(spec.program(), "wrk2");
+        assert!(spec.args().windows(2).any(|args| args == ["-R", "500"]));
+
+        wrk.set_wrk2_program(Some("/opt/wrk2/wrk".to_string()));
+        assert_eq!(wrk.command_for(&constant).unwrap().program(), "/opt/wrk2/wrk");
+        assert_eq!(wrk.program(&constant), "/opt/wrk2/wrk");
+        assert_eq!(wrk.program(&Benchmark::new(2, 16, 5)), "wrk");
+        #[cfg(feature = "native")]
+        {
+            wrk.set_engine(Engine::Native);
+            let spec = wrk.command_for(&Benchmark::new(2, 16, 5)).unwrap();
+            assert_eq!(spec.program(), native::PROGRAM);
+        }
+    }
+
+    #[test]
+    fn request_timeout() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13744".to_string())
+            .timeout(10)
+            .history_store(HistoryStore::in_memory())
+            .build()
+            .unwrap();
+        let soak = Benchmark::new(1, 1, 60);
+        assert_eq!(wrk.request_timeout(&soak), Duration::from_secs(10));
+        let short = BenchmarkBuilder::default()
+            .duration(Duration::from_secs(5))
+            .timeout(Duration::from_millis(1500))
+            .build()
+            .unwrap();
+        assert_eq!(wrk.request_timeout(&short), Duration::from_secs(2));
+        let spec = wrk.command_for(&Benchmark::new(1, 1, 3)).unwrap();
+        assert!(spec.args().windows(2).any(|args| args == ["--timeout", "3s"]));
+        assert_eq!(wrk.timeout_warnings(&Benchmark::new(1, 1, 3), &vec![]).len(), 1);
+
+        let slow = crate::WrkResultBuilder::default()
+            .success(true)
+            .benchmark(short.clone())
+            .p99_latency_ms(2500.0)
+            .build()
+            .unwrap();
+        let warnings = wrk.timeout_warnings(&short, &vec![slow]);
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].contains("below the p99 latency of 2500.00ms"),
+            "{}",
+            warnings[0]
+        );
+        assert!(wrk.timeout_warnings(&soak, &vec![]).is_empty());
+    }
+
+    #[test]
+    fn check_history() {
+        let mut wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13744".to_string())
+            .history_store(HistoryStore::in_memory())
+            .build()
+            .unwrap();
+        let now = wrk.now();
+        for (hours, connections) in [(2, 1), (1, 2)] {
+            let result = crate::WrkResultBuilder::default()
+                .success(true)
+                .benchmark(Benchmark::new(1, connections, 1))
+                .build()
+                .unwrap();
+            wrk.dump(now - ChronoDuration::hours(hours), &vec![result]).unwrap();
+        }
+        let first = [Benchmark::new(1, 1, 1)];
+        wrk.guidance_mut().set_enforcement(Enforcement::Off);
+        assert!(wrk.check_history(&first).is_empty());
+        assert_eq!(wrk.check_history(&[Benchmark::new(1, 2, 1)]).len(), 1);
+        wrk.guidance_mut().set_enforcement(Enforcement::Warn);
+        assert_eq!(wrk.check_history(&first).len(), 1);
+    }
+
+    #[test]
+    fn lua_search_paths() {
+        let modules = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13734".to_string())
+            .lua_search_paths(vec![modules.path().to_path_buf()])
+            .env(BTreeMap::from([(
+                "LUA_PATH".to_string(),
+                "/opt/lua/?.lua;;".to_string(),
+            )]))
+            .build()
+            .unwrap();
+        let modules = fs::canonicalize(modules.path()).unwrap();
+        assert_eq!(
+            wrk.command_env(&Benchmark::default())["LUA_PATH"],
+            format!(
+                "{}/?.lua;{}/?/init.lua;/opt/lua/?.lua;;",
+                modules.display(),
+                modules.display()
+            )
+        );
+    }
+
+    #[test]
+    fn script_cache() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13734/api".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .build()
+            .unwrap();
+        let url = Url::parse(wrk.url()).unwrap();
+        let get = wrk.script(&Benchmark::new(1, 1, 1), &url).unwrap();
+        assert_eq!(get, wrk.script(&Benchmark::new(2, 4, 8), &url).unwrap());
+        assert!(get.starts_with(wrk.history_path().join(SCRIPTS_DIR)));
+        let mut post = Benchmark::new(1, 1, 1);
+        post.set_method(Some(Method::POST));
+        assert_ne!(get, wrk.script(&post, &url).unwrap());
+        assert!(wrk.history(&HistoryPeriod::Forever, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn extra_args() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13734".to_string())
+            .extra_args(vec!["--header".to_string(), "X-Extra: 1".to_string()])
+            .build()
+            .unwrap();
+        let url = Url::parse(wrk.url()).unwrap();
+        let args = wrk
+            .wrk_args(&Benchmark::new(1, 1, 1), &url, Path::new("script.lua"))
+            .unwrap();
+        assert_eq!(
+            args[args.len() - 3..],
+            ["--header", "X-Extra: 1", "http://127.0.0.1:13734/"]
+        );
+    }
+
+    #[test]
+    fn invalid_method() {
+        let builder = WrkBuilder::default()
+            .url("http://127.0.0.1:13734".to_string())
+            .method(Method::from_bytes(b"GTE").unwrap())
+            .build();
+        assert!(builder.is_err());
+        let json = r#"{"url": "http://localhost", "benchmarks": [], "benchmarks_history": [], "history_dir": ".",
+            "user_script": null, "headers": {}, "method": "GTE", "body": "", "max_error_percentage": 2}"#;
+        assert!(serde_json::from_str::<Wrk>(json).is_err());
+    }
+
+    #[test]
+    fn history_sorted_by_filename_date() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13734".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .build()
+            .unwrap();
+        let now = Utc::now();
+        let mtime = std::time::SystemTime::now();
+        for i in 0..3 {
+            let date = now - ChronoDuration::minutes(10 - i);
+            let result = crate::WrkResultBuilder::default()
+                .success(true)
+                .date(date)
+                .requests_sec(i as f64)
+                .build()
+                .unwrap();
+            fs::create_dir_all(wrk.history_path()).unwrap();
+            let path = wrk.history_path().join(history::filename(&date, HistoryFormat::Json));
+            serde_json::to_writer(File::create(&path).unwrap(), &vec![result]).unwrap();
+            // Oldest results get the most recent modification time.
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(mtime - Duration::from_secs(i as u64 * 60))
+                .unwrap();
+        }
+        assert_eq!(*wrk.best_of(HistoryPeriod::Last).unwrap().requests_sec(), 2.0);
+        let order: Vec<_> = wrk
+            .history(&HistoryPeriod::Hour, None)
+            .unwrap()
+            .iter()
+            .map(|r| *r.requests_sec())
+            .collect();
+        assert_eq!(order, vec![0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn variance_window() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13736".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .build()
+            .unwrap();
+        let now = Utc::now();
+        for (i, requests_sec) in [100.0, 80.0, 120.0, 110.0].into_iter().enumerate() {
+            let date = now - ChronoDuration::minutes(10 - i as i64);
+            let result = crate::WrkResultBuilder::default()
+                .success(true)
+                .date(date)
+                .benchmark(Benchmark::new(1, 1, 1))
+                .requests_sec(requests_sec)
+                .build()
+                .unwrap();
+            wrk.dump(date, &vec![result]).unwrap();
+        }
+        let window = wrk.variance_window(HistoryPeriod::Hour, 2).unwrap();
+        assert_eq!(*window.new_run().requests_sec(), 110.0);
+        let runs: Vec<_> = window.runs().iter().map(|r| *r.requests_sec()).collect();
+        assert_eq!(runs, vec![80.0, 120.0]);
+        assert_eq!(*window.mean().requests_sec(), 100.0);
+        assert_eq!(*window.variance_to_mean().variance.requests_sec(), 10.0);
+        assert_eq!(wrk.variance_window(HistoryPeriod::Last, 5).unwrap().runs().len(), 1);
+    }
+
+    #[test]
+    fn manual_clock() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let start = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 3, 1, 12, 0, 0).unwrap();
+        let clock = Arc::new(crate::ManualClock::new(start));
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13736".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .clock(clock.clone())
+            .runner(crate::test_utils::CorpusRunner)
+            .build()
+            .unwrap();
+        assert_eq!(wrk.now(), start);
+        for (minutes, requests_sec) in [(90, 10.0), (30, 20.0)] {
+            let date = start - ChronoDuration::minutes(minutes);
+            let result = crate::WrkResultBuilder::default()
+                .success(true)
+                .date(date)
+                .benchmark(Benchmark::new(1, 1, 1))
+                .requests_sec(requests_sec)
+                .build()
+                .unwrap();
+            wrk.dump(date, &vec![result]).unwrap();
+        }
+        let hour: Vec<_> = wrk
+            .history(&HistoryPeriod::Hour, None)
+            .unwrap()
+            .iter()
+            .map(|r| *r.requests_sec())
+            .collect();
+        assert_eq!(hour, vec![20.0]);
+        clock.advance(ChronoDuration::hours(1));
+        assert!(wrk.history(&HistoryPeriod::Hour, None).unwrap().is_empty());
+        assert_eq!(wrk.history(&HistoryPeriod::Day, None).unwrap().len(), 2);
+
+        // Executions are stamped by the clock.
+        BenchSession::from(wrk.clone())
+            .bench(&[Benchmark::new(1, 1, 1)])
+            .unwrap();
+        let entry = &wrk.audit_log().unwrap()[0];
+        assert_eq!(*entry.start(), start + ChronoDuration::hours(1));
+        assert_eq!(*entry.end(), *entry.start());
+    }
+
+    #[test]
+    fn best_comparator() {
+        let result = |requests_sec: f64, p99: f64| result_builder(requests_sec, p99).build().unwrap();
+        let results = vec![result(100.0, 5.0), result(200.0, 50.0)];
+        let mut wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13737".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(*wrk.best_benchmark(&results).unwrap().requests_sec(), 200.0);
+        // Lowest p99 latency wins.
+        wrk.set_best_comparator(|a, b| b.p99_latency_ms().total_cmp(a.p99_latency_ms()));
+        assert_eq!(*wrk.best_benchmark(&results).unwrap().requests_sec(), 100.0);
+    }
+
+    /// Runner answering every command with the same wrk output.
+    #[derive(Debug)]
+    struct FakeRunner(String);
+
+    impl ProcessRunner for FakeRunner {
+        fn run(&self, _spec: &CommandSpec) -> Result<crate::ProcessOutput> {
+            Ok(crate::ProcessOutput::new(Some(0), self.0.clone(), String::new()))
+        }
+    }
+
+    #[test]
+    fn custom_runner() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let output = r#"Running 1s test @ http://127.0.0.1:13738
+JSON{
+    "requests": 1000.00,
+    "errors": 0.00,
+    "successes": 1000.00,
+    "requests_sec": 1000.00,
+    "avg_latency_ms": 1.000000,
+    "min_latency_ms": 0.100000,
+    "max_latency_ms": 9.000000,
+    "stdev_latency_ms": 0.500000,
+    "p50_latency_ms": 0.900000,
+    "p75_latency_ms": 1.200000,
+    "p90_latency_ms": 2.000000,
+    "p99_latency_ms": 5.000000,
+    "transfer_mb": 1.00,
+    "errors_connect": 0.00,
+    "errors_read": 0.00,
+    "errors_write": 0.00,
+    "errors_status": 0.00,
+    "errors_timeout": 0.00,
+    "errors_mismatch": 0.00,
+    "server": "nginx/1.25.3",
+    "app_version": ""
+}
+"#;
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13738".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .runner(FakeRunner(output.to_string()))
+            .build()
+            .unwrap();
+        let results = wrk.bench_collect(&[Benchmark::new(1, 1, 1)]).unwrap();
+        assert!(*results[0].success(), "{}", results[0].error());
+        assert_eq!(*results[0].requests_sec(), 1000.0);
+        assert_eq!(results[0].server().as_deref(), Some("nginx/1.25.3"));
+        assert_eq!(results[0].app_version(), &None);
+        // Collected runs leave nothing in the history directory.
+        assert!(wrk.audit_log().unwrap().is_empty());
+        assert!(wrk.suite_status().unwrap().is_none());
+        assert!(!wrk.history_path().exists());
+
+        let mut session = BenchSession::from(wrk.clone());
+        session.bench(&[Benchmark::new(1, 1, 1)]).unwrap();
+        assert_eq!(wrk.audit_log().unwrap()[0].exit_status(), &Some(0));
+        let status = wrk.suite_status().unwrap().unwrap();
+        assert!(status.finished());
+        assert_eq!(*status.remaining_secs(), 0);
+        assert_eq!(status.last_result().as_ref().unwrap().requests_sec(), &1000.0);
+    }
+
+    /// Runner recording the suite status found in `dir` of `store` before every run.
+    #[derive(Debug)]
+    struct StatusRunner {
+        store: HistoryStore,
+        dir: PathBuf,
+        seen: Arc<std::sync::Mutex<Vec<SuiteStatus>>>,
+    }
+
+    impl ProcessRunner for StatusRunner {
+        fn run(&self, spec: &CommandSpec) -> Result<crate::ProcessOutput> {
+            let status = status::read(&*self.store, &self.dir)?.expect("status written before the run");
+            self.seen.lock().unwrap().push(status);
+            crate::test_utils::CorpusRunner.run(spec)
+        }
+    }
+
+    #[test]
+    fn suite_progress() {
+        let store = HistoryStore::in_memory();
+        let builder = || {
+            let mut builder = WrkBuilder::default();
+            builder
+                .url("http://127.0.0.1:13738".to_string())
+                .history_store(store.clone());
+            builder
+        };
+        let dir = builder().build().unwrap().history_path();
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let wrk = builder()
+            .runner(StatusRunner {
+                store: store.clone(),
+                dir,
+                seen: seen.clone(),
+            })
+            .build()
+            .unwrap();
+        let mut session = BenchSession::from(wrk.clone());
+        session
+            .bench(&[Benchmark::new(1, 1, 1), Benchmark::new(1, 2, 1)])
+            .unwrap();
+
+        let seen = seen.lock().unwrap().clone();
+        let suite_id = session.suite_id().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert!(seen
+            .iter()
+            .all(|status| *status.suite_id() == suite_id && *status.total() == 2));
+        assert_eq!((seen[0].running().as_deref(), *seen[0].completed()), (Some("1-1-1"), 0));
+        assert!(seen[0].last_result().is_none());
+        assert_eq!((seen[1].running().as_deref(), *seen[1].completed()), (Some("1-2-1"), 1));
+        assert_eq!(seen[1].last_result().as_ref().unwrap().benchmark(), "1-1-1");
+        let status = wrk.suite_status().unwrap().unwrap();
+        assert!(status.finished());
+        assert_eq!((status.running(), *status.remaining_secs()), (&None, 0));
+        assert_eq!(status.last_result().as_ref().unwrap().benchmark(), "1-2-1");
+
+        // Every run gets its own id, every suite too.
+        let results = session.benchmarks();
+        assert!(results
+            .iter()
+            .all(|r| *r.suite_id() == suite_id && !r.run_id().is_nil()));
+        assert_ne!(results[0].run_id(), results[1].run_id());
+        let mut next = BenchSession::from(wrk.clone());
+        next.bench(&[Benchmark::new(1, 1, 1)]).unwrap();
+        assert_ne!(next.suite_id().unwrap(), suite_id);
+        assert_eq!(
+            *wrk.suite_status().unwrap().unwrap().suite_id(),
+            next.suite_id().unwrap()
+        );
+    }
+
+    #[test]
+    fn in_memory_history() {
+        let output = std::fs::read_to_string("tests/corpus/wrk-4.1.0.txt").unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let history_dir = tmp.path().join("history");
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13738".to_string())
+            .history_dir(history_dir.clone())
+            .history_store(HistoryStore::in_memory())
+            .runner(FakeRunner(output))
+            .build()
+            .unwrap();
+        let benchmarks = [Benchmark::new(1, 1, 1)];
+        let date = Utc::now() - ChronoDuration::minutes(1);
+        wrk.dump(date, &wrk.bench_collect(&benchmarks).unwrap()).unwrap();
+        let wrk = Arc::new(wrk);
+        let mut session = BenchSession::new(wrk.clone());
+        session.bench(&benchmarks).unwrap();
+        assert_eq!(wrk.history(&HistoryPeriod::Hour, None).unwrap().len(), 2);
+        assert!(session.variance(HistoryPeriod::Last).is_ok());
+        // Only the session run is audited, the collected one writes nothing.
+        assert_eq!(wrk.audit_log().unwrap().len(), 1);
+        assert!(wrk.suite_status().unwrap().unwrap().finished());
+        assert!(!history_dir.exists());
+    }
+
+    #[cfg(feature = "daemon")]
+    #[test]
+    fn scheduled_run() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let output = std::fs::read_to_string("tests/corpus/wrk-4.1.0.txt").unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13738".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .runner(FakeRunner(output))
+            .build()
+            .unwrap();
+        let schedule = crate::Schedule::cron("@hourly").unwrap().with_retention(7);
+        let mut reports = Vec::new();
+        wrk.scheduled_run(&schedule, &[Benchmark::new(1, 1, 1)], &mut |variance: &Variance| {
+            reports.push(variance.baseline)
+        })
+        .unwrap();
+        assert_eq!(reports, vec![true]);
+        assert_eq!(wrk.history(&HistoryPeriod::Forever, None).unwrap().len(), 1);
+    }
+
+    /// Runner failing like wrk does against a target which is down.
+    #[derive(Debug)]
+    struct RefusedRunner;
+
+    impl ProcessRunner for RefusedRunner {
+        fn run(&self, _spec: &CommandSpec) -> Result<crate::ProcessOutput> {
+            let stderr = "unable to connect to 127.0.0.1:13738 Connection refused\n";
+            Ok(crate::ProcessOutput::new(Some(1), String::new(), stderr.to_string()))
+        }
+    }
+
+    #[test]
+    fn stderr_diagnoses() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13738".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .runner(RefusedRunner)
+            .build()
+            .unwrap();
+        let results = wrk.bench_collect(&[Benchmark::new(1, 1, 1)]).unwrap();
+        assert!(!*results[0].success());
+        assert_eq!(results[0].diagnoses(), &vec![crate::Diagnosis::Connect]);
+        assert!(results[0]
+            .error()
+            .contains("Hint: unable to connect: check the target is up"));
+    }
+
+    /// Runner answering faster to the commands whose script asks for MessagePack.
+    #[derive(Debug)]
+    struct AcceptRunner(String);
+
+    impl ProcessRunner for AcceptRunner {
+        fn run(&self, spec: &CommandSpec) -> Result<crate::ProcessOutput> {
+            let mut output = self.0.clone();
+            // Output without the JSON result, failing the run.
+            if spec.script().contains(r#"wrk.headers["Accept"] = "application/cbor""#) {
+                output = String::new();
+            }
+            if spec
+                .script()
+                .contains(r#"wrk.headers["Accept"] = "application/msgpack""#)
+            {
+                output = output.replace(r#""requests_sec": 1000.00"#, r#""requests_sec": 1500.00"#);
+            }
+            Ok(crate::ProcessOutput::new(Some(0), output, String::new()))
+        }
+    }
+
+    #[test]
+    fn matrix_targets() {
+        let output = std::fs::read_to_string("tests/corpus/wrk-4.1.0.txt").unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13745".to_string())
+            .history_store(HistoryStore::in_memory())
+            .runner(FakeRunner(output))
+            .build()
+            .unwrap();
+        let targets = vec![
+            ("../Axum".to_string(), "http://127.0.0.1:13745".to_string()),
+            ("down".to_string(), "not a url".to_string()),
+        ];
+        let comparison = wrk.matrix_targets(targets, &[Benchmark::new(1, 1, 1)]).unwrap();
+        let ranking = comparison.ranking();
+        assert_eq!(ranking[0].0, "../Axum");
+        assert!(ranking[1].1.is_none());
+        let axum = wrk.target("../Axum", wrk.url().clone());
+        assert_eq!(axum.history_path(), wrk.history_dir().join("axum"));
+        assert_eq!(axum.history(&HistoryPeriod::Last, None).unwrap().len(), 1);
+    }
+
+    /// Runner answering with the output of wrk 4.1.0, without the JSON result for the calls
+    /// listed in `failing`.
+    #[derive(Debug)]
+    struct FlakyRunner {
+        calls: std::sync::atomic::AtomicUsize,
+        failing: Vec<usize>,
+    }
+
+    impl ProcessRunner for FlakyRunner {
+        fn run(&self, spec: &CommandSpec) -> Result<crate::ProcessOutput> {
+            let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            match self.failing.contains(&call) {
+                true => Ok(crate::ProcessOutput::new(Some(0), String::new(), String::new())),
+                false => crate::test_utils::CorpusRunner.run(spec),
+            }
+        }
+    }
+
+    #[test]
+    fn compare_interleaved() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13745".to_string())
+            .history_store(HistoryStore::in_memory())
+            .runner(FlakyRunner {
+                calls: Default::default(),
+                failing: vec![1],
+            })
+            .build()
+            .unwrap();
+        let comparison = wrk
+            .compare_interleaved(
+                ("axum".to_string(), "http://127.0.0.1:13745".to_string()),
+                ("actix".to_string(), "http://127.0.0.1:13746".to_string()),
+                &[Benchmark::new(1, 1, 1)],
+                3,
+            )
+            .unwrap();
+        let runs: Vec<_> = comparison
+            .targets()
+            .iter()
+            .map(|(name, results)| (name.as_str(), results.len()))
+            .collect();
+        assert_eq!(runs, vec![("axum", 3), ("actix", 2)]);
+    }
+
+    #[test]
+    fn compare_content_types() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let output = r#"JSON{
+    "requests": 1000.00,
+    "errors": 0.00,
+    "successes": 1000.00,
+    "requests_sec": 1000.00,
+    "avg_latency_ms": 1.000000,
+    "min_latency_ms": 0.100000,
+    "max_latency_ms": 9.000000,
+    "stdev_latency_ms": 0.500000,
+    "p50_latency_ms": 0.900000,
+    "p75_latency_ms": 1.200000,
+    "p90_latency_ms": 2.000000,
+    "p99_latency_ms": 5.000000,
+    "transfer_mb": 1.00,
+    "errors_connect": 0.00,
+    "errors_read": 0.00,
+    "errors_write": 0.00,
+    "errors_status": 0.00,
+    "errors_timeout": 0.00,
+    "errors_mismatch": 0.00
+}
+"#;
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13745".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .headers(Headers::from([("accept".to_string(), "*/*".to_string())]))
+            .runner(AcceptRunner(output.to_string()))
+            .build()
+            .unwrap();
+        let comparison = wrk
+            .compare_content_types(
+                &["application/json", "application/cbor", "application/msgpack"],
+                &[Benchmark::new(1, 1, 1)],
+            )
+            .unwrap();
+        let ranking: Vec<_> = comparison
+            .ranking()
+            .into_iter()
+            .map(|(name, best)| (name, best.map(|r| *r.requests_sec())))
+            .collect();
+        assert_eq!(
+            ranking,
+            vec![
+                ("application/msgpack", Some(1500.0)),
+                ("application/json", Some(1000.0)),
+                ("application/cbor", None)
+            ]
+        );
+        assert!(history_dir.path().join("accept-application-msgpack").exists());
+    }
+
+    #[test]
+    fn rerun_replays_scripts() {
+        let output = std::fs::read_to_string("tests/corpus/wrk-4.1.0.txt")
+            .unwrap()
+            .replace("24278.51", "1000.00");
+        let mut wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13745".to_string())
+            .history_store(HistoryStore::in_memory())
+            .headers(Headers::from([(
+                "Accept".to_string(),
+                "application/msgpack".to_string(),
+            )]))
+            .runner(AcceptRunner(output))
+            .build()
+            .unwrap();
+        let date = Utc::now() - ChronoDuration::minutes(5);
+        let original = wrk
+            .run(&[Benchmark::new(1, 1, 1)], date, Uuid::new_v4(), |_| Ok(()))
+            .unwrap();
+        wrk.dump(date, &original).unwrap();
+        assert_eq!(*original[0].requests_sec(), 1500.0);
+        assert!(original[0].script_sha256().is_some());
+
+        wrk.set_headers(Headers::new());
+        let results = wrk.rerun(date).unwrap();
+        assert_eq!(*results[0].requests_sec(), 1500.0);
+        assert_eq!(results[0].script_sha256(), original[0].script_sha256());
+        assert!(results[0].warnings().iter().all(|w| !w.contains("not archived")));
+        assert_eq!(
+            *wrk.bench_collect(&[Benchmark::new(1, 1, 1)]).unwrap()[0].requests_sec(),
+            1000.0
+        );
+    }
+
+    #[test]
+    fn variance_multi() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13744".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .build()
+            .unwrap();
+        let result = |requests_sec: f64| result_builder(requests_sec, 0.0).build().unwrap();
+        let now = Utc::now();
+        wrk.dump(now - ChronoDuration::days(3), &vec![result(120.0)]).unwrap();
+        wrk.dump(now - ChronoDuration::minutes(30), &vec![result(100.0)])
+            .unwrap();
+        wrk.dump(now, &vec![result(110.0)]).unwrap();
+        let multi = wrk
+            .variance_multi(&[HistoryPeriod::Hour, HistoryPeriod::Day, HistoryPeriod::Week])
+            .unwrap();
+        let olds: Vec<_> = multi
+            .periods()
+            .iter()
+            .map(|(_, old)| old.as_ref().map(|r| *r.requests_sec()))
+            .collect();
+        assert_eq!(olds, vec![Some(100.0), Some(100.0), Some(120.0)]);
+    }
+
+    #[test]
+    fn suite_eta() {
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13743".to_string())
+            .cooldown(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let benchmarks = [
+            Benchmark::new(1, 1, 10),
+            Benchmark::new(1, 1, 20),
+            Benchmark::new(1, 1, 30),
+        ];
+        assert_eq!(wrk.suite_eta(&benchmarks), Duration::from_secs(70));
+        assert_eq!(wrk.suite_eta(&[]), Duration::ZERO);
+        assert_eq!(wrk.remaining(&benchmarks, 2), Duration::from_secs(35));
+        assert_eq!(wrk.remaining(&benchmarks, 3), Duration::ZERO);
+    }
+
+    #[test]
+    fn history_dedup() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13739".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .build()
+            .unwrap();
+        let now = Utc::now();
+        let result = crate::WrkResultBuilder::default()
+            .success(true)
+            .date(now)
+            .benchmark(Benchmark::new(1, 1, 1))
+            .build()
+            .unwrap();
+        let mut legacy = result.clone();
+        *legacy.run_id_mut() = Uuid::nil();
+        *legacy.date_mut() = now - ChronoDuration::minutes(5);
+        let mut other = legacy.clone();
+        *other.requests_sec_mut() = 10.0;
+        // The same runs re-imported in a later file.
+        wrk.dump(now - ChronoDuration::minutes(2), &vec![result.clone(), legacy.clone()])
+            .unwrap();
+        wrk.dump(now - ChronoDuration::minutes(1), &vec![result.clone(), other])
+            .unwrap();
+        let history = wrk.history(&HistoryPeriod::Hour, None).unwrap();
+        assert_eq!(history, vec![result, legacy]);
+    }
+
+    /// Runner whose p99 latency grows with the requested rate: 1ms every 100 requests/sec.
+    /// It answers `wrk2 -v` with the usage of wrk2.
+    #[derive(Debug)]
+    struct RateRunner;
+
+    impl ProcessRunner for RateRunner {
+        fn run(&self, spec: &CommandSpec) -> Result<crate::ProcessOutput> {
+            if spec.args() == &["-v"] {
+                let usage = match spec.program().as_str() {
+                    "wrk2" => "    -R, --rate        <T>  work rate (throughput)\n",
+                    _ => "    -t, --threads     <N>  Number of threads to use\n",
+                };
+                return Ok(crate::ProcessOutput::new(Some(1), usage.to_string(), String::new()));
+            }
+            let rate: f64 = match spec.args().iter().position(|arg| arg == "-R") {
+                Some(i) => spec.args()[i + 1].parse().unwrap(),
+                None => 2000.0,
+            };
+            let output = format!(
+                r#"JSON{{
+    "requests": {rate:.2},
+    "errors": 0.00,
+    "successes": {rate:.2},
+    "requests_sec": {rate:.2},
+    "avg_latency_ms": 1.000000,
+    "min_latency_ms": 0.100000,
+    "max_latency_ms": 90.000000,
+    "stdev_latency_ms": 0.500000,
+    "p50_latency_ms": 1.000000,
+    "p75_latency_ms": 1.000000,
+    "p90_latency_ms": 1.000000,
+    "p99_latency_ms": {p99:.6},
+    "transfer_mb": 1.00,
+    "errors_connect": 0.00,
+    "errors_read": 0.00,
+    "errors_write": 0.00,
+    "errors_status": 0.00,
+    "errors_timeout": 0.00,
+    "errors_mismatch": 0.00
+}}
+"#,
+                p99 = rate / 100.0
+            );
+            Ok(crate::ProcessOutput::new(Some(0), output, String::new()))
+        }
+    }
+
+    #[test]
+    fn find_max_rate() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13747".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .runner(RateRunner)
+            .build()
+            .unwrap();
+        let best = wrk
+            .find_max_rate(&Benchmark::new(1, 1, 1), Duration::from_millis(10))
+            .unwrap();
+        let capacity = best.capacity().unwrap();
+        assert!((950..=1000).contains(&capacity), "{}", capacity);
+        assert_eq!(best.benchmark().as_ref().unwrap(), &Benchmark::new(1, 1, 1));
+        let recorded = wrk.history(&HistoryPeriod::Last, None).unwrap();
+        assert_eq!(recorded[0].capacity(), &Some(capacity));
+
+        // A bound already meeting the SLO is the capacity.
+        let mut bounded = Benchmark::new(1, 1, 1);
+        bounded.set_rate(Some(800));
+        let best = wrk.find_max_rate(&bounded, Duration::from_millis(10)).unwrap();
+        assert_eq!(best.capacity(), &Some(800));
+        assert_eq!(best.benchmark().as_ref().unwrap().key(), "1-1-1");
+
+        let without_wrk2 = WrkBuilder::default()
+            .url("http://127.0.0.1:13747".to_string())
+            .history_store(HistoryStore::in_memory())
+            .runner(FakeRunner(String::new()))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            without_wrk2.find_max_rate(&Benchmark::new(1, 1, 1), Duration::from_millis(10)),
+            Err(WrkError::Config(_))
+        ));
+        assert!(wrk
+            .find_max_rate(&Benchmark::new(1, 1, 1), Duration::from_micros(1))
+            .is_err());
+    }
+
+    #[test]
+    fn cold_start_series() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13746".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .build()
+            .unwrap();
+        let result = |requests_sec: f64, cold_start: bool| {
+            result_builder(requests_sec, 0.0)
+                .cold_start(cold_start)
+                .build()
+                .unwrap()
+        };
+        let (cold, warm) = (result(10.0, true), result(100.0, false));
+        wrk.dump(Utc::now(), &vec![cold.clone(), warm.clone()]).unwrap();
+        assert_eq!(wrk.history(&HistoryPeriod::Last, None).unwrap(), vec![warm]);
+        assert_eq!(wrk.cold_start_history(HistoryPeriod::Day).unwrap(), vec![cold]);
+    }
+
+    #[test]
+    fn redacted_headers() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let wrk = WrkBuilder::default()
+            .url("http://127.0.0.1:13740".to_string())
+            .history_dir(history_dir.path().to_path_buf())
+            .headers(Headers::from([(
+                "Authorization".to_string(),
+                "Bearer secret".to_string(),
+            )]))
+            .build()
+            .unwrap();
+        let mut benchmark = Benchmark::new(1, 1, 1);
+        benchmark.set_headers(Some(Headers::from([("X-Api-Key".to_string(), "key".to_string())])));
+        let spec = wrk.command_for(&benchmark).unwrap();
+        assert!(!spec.script().contains("secret"));
+        assert!(spec
+            .script()
+            .contains(r#"wrk.headers["Authorization"] = os.getenv("WRK_API_BENCH_HEADER_AUTHORIZATION")"#));
+        assert_eq!(
+            spec.env()["WRK_API_BENCH_HEADER_AUTHORIZATION"],
+            crate::headers::REDACTED
+        );
+        assert_eq!(spec.env()["WRK_API_BENCH_HEADER_X_API_KEY"], crate::headers::REDACTED);
+        assert!(spec
+            .env()
+            .values()
+            .all(|value| !value.contains("secret") && value != "key"));
+        assert!(!serde_json::to_string(&spec).unwrap().contains("Bearer secret"));
+
+        // Only the command run locally holds the real values.
+        let url = Url::parse(wrk.url()).unwrap();
+        let script = wrk.script(&benchmark, &url).unwrap();
+        let local = wrk.command_spec(&benchmark, &url, &script).unwrap();
+        assert_eq!(local.env()["WRK_API_BENCH_HEADER_AUTHORIZATION"], "Bearer secret");
+        assert_eq!(local.env()["WRK_API_BENCH_HEADER_X_API_KEY"], "key");
+
+        let result = crate::WrkResultBuilder::default()
+            .success(true)
+            .benchmark(benchmark.clone())
+            .build()
+            .unwrap();
+        wrk.dump(Utc::now(), &vec![result]).unwrap();
+        let stored = wrk.history(&HistoryPeriod::Last, None).unwrap();
+        let stored = stored[0].benchmark().as_ref().unwrap();
+        assert_eq!(
+            stored.headers().as_ref().unwrap()["X-Api-Key"],
+            crate::headers::REDACTED
+        );
+        assert!(stored.is_comparable(&benchmark));
     }
 }
@@ -0,0 +1,69 @@
+//! Detection of [wrk2](https://github.com/giltene/wrk2), running the constant throughput
+//! benchmarks, IE: the ones with a [`crate::Benchmark::rate`]. Its latencies are corrected for
+//! coordinated omission.
+use std::collections::BTreeMap;
+
+use crate::{CommandSpec, ProcessRunner};
+
+/// Name wrk2 is usually installed as, next to wrk.
+pub(crate) const PROGRAM: &str = "wrk2";
+
+/// Whether `program`, run by `runner`, accepts the `-R` rate option according to the usage
+/// it prints.
+pub(crate) fn supports_rate(runner: &dyn ProcessRunner, program: &str) -> bool {
+    let spec = CommandSpec::new(program, vec!["-v".to_string()], BTreeMap::new(), String::new());
+    runner
+        .run(&spec)
+        .is_ok_and(|output| lists_rate(output.stdout()) || lists_rate(output.stderr()))
+}
+
+/// Whether `usage` lists the `-R, --rate` option of wrk2.
+fn lists_rate(usage: &str) -> bool {
+    usage.lines().any(|line| line.trim_start().starts_with("-R, --rate"))
+}
+
+/// Program running constant throughput benchmarks through `runner`: `wrk2` when installed,
+/// otherwise `wrk` when it is a build of wrk2.
+pub(crate) fn detect(runner: &dyn ProcessRunner) -> Option<String> {
+    let detected = [PROGRAM, "wrk"]
+        .into_iter()
+        .find(|program| supports_rate(runner, program));
+    match detected {
+        Some(program) => debug!("Running constant throughput benchmarks with {}", program),
+        None => warn!("Neither wrk2 nor a wrk supporting -R found, constant throughput benchmarks will fail"),
+    }
+    detected.map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProcessOutput, Result, SystemRunner};
+
+    /// Runner answering `wrk2 -v` with the usage of wrk2 and failing for other programs.
+    #[derive(Debug)]
+    struct Wrk2Runner;
+
+    impl ProcessRunner for Wrk2Runner {
+        fn run(&self, spec: &CommandSpec) -> Result<ProcessOutput> {
+            match spec.program().as_str() {
+                PROGRAM => Ok(ProcessOutput::new(
+                    Some(1),
+                    "    -R, --rate        <T>  work rate (throughput)\n".to_string(),
+                    String::new(),
+                )),
+                program => Err(crate::WrkError::Exec(format!("{} not found", program))),
+            }
+        }
+    }
+
+    #[test]
+    fn rate_support() {
+        let wrk = "Usage: wrk <options> <url>\n  Options:\n    -c, --connections <N>  Connections to keep open\n";
+        let wrk2 = format!("{}    -R, --rate        <T>  work rate (throughput)\n", wrk);
+        assert!(!lists_rate(wrk));
+        assert!(lists_rate(&wrk2));
+        assert!(!supports_rate(&SystemRunner, "wrk-api-bench-missing-wrk2"));
+        assert_eq!(detect(&Wrk2Runner).as_deref(), Some(PROGRAM));
+    }
+}
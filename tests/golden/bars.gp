@@ -0,0 +1,8 @@
+set style data histograms
+set style histogram clustered gap 1
+set style fill solid border -1
+set yrange [0:*]
+set key outside
+set xtics rotate by -45
+set title "Wrk \"history\""
+plot data using 2:xtic(1) title columnheader, data using 3:xtic(1) title columnheader
@@ -0,0 +1,10 @@
+set xdata time
+set timefmt "%Y-%m-%d %H:%M:%S"
+set format x "%m/%y/%d %H:%M:%S"
+set xrange ["2024-03-01 12:01:00":"2024-03-01 12:02:00"]
+set yrange [850:1380]
+set key top left
+set xtics rotate by -45
+set title "Wrk \"history\""
+plot data index 0 using 1:2 title "1-1-1" with linespoints linetype 6 linewidth 2, \
+     data index 1 using 1:2 title "get users" with linespoints linetype 7 linewidth 2
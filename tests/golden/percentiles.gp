@@ -0,0 +1,13 @@
+set xdata time
+set timefmt "%Y-%m-%d %H:%M:%S"
+set format x "%m/%d %H:%M"
+set xtics rotate by -45
+set key top left
+set ylabel "Latency (ms)"
+set terminal png size 1024,320
+set multiplot layout 1,1 title "Wrk \"history\""
+set title "1-1-1"
+plot data index 0 using 1:3:4 with filledcurves fillstyle transparent solid 0.3 title "p90-p99", \
+     data index 0 using 1:2:3 with filledcurves fillstyle transparent solid 0.5 title "p50-p90", \
+     data index 0 using 1:2 with linespoints linewidth 2 title "p50"
+unset multiplot